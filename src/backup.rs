@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Local, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::server_properties::ServerProperties;
+use crate::session;
+
+/// Folder of the server directory holding the archives
+pub const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "backup-";
+const BACKUP_EXTENSION: &str = ".tar.gz";
+const BACKUP_DATE_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// Logged by the server once `save-all flush` wrote everything
+const SAVED_LINE: &str = "Saved the game";
+const SAVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+/// Root files saved with the world
+const CONFIG_EXTENSIONS: [&str; 5] = ["properties", "json", "yml", "yaml", "toml"];
+/// Config folders of Paper and of most Fabric mods
+const CONFIG_DIRS: [&str; 1] = ["config"];
+/// Bukkit based servers store the other dimensions next to the world
+const DIMENSION_SUFFIXES: [&str; 3] = ["", "_nether", "_the_end"];
+
+/// How many archives `prune` keeps, an archive matching several rules is kept once
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: 7,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// `None` when no rule is given
+    pub fn new(
+        keep_last: Option<usize>,
+        keep_daily: Option<usize>,
+        keep_weekly: Option<usize>,
+    ) -> Option<Self> {
+        if keep_last.is_none() && keep_daily.is_none() && keep_weekly.is_none() {
+            return None;
+        }
+        Some(Self {
+            keep_last: keep_last.unwrap_or_default(),
+            keep_daily: keep_daily.unwrap_or_default(),
+            keep_weekly: keep_weekly.unwrap_or_default(),
+        })
+    }
+
+    /// Splits backups sorted newest first into kept and removed ones
+    fn apply(&self, backups: Vec<Backup>) -> (Vec<Backup>, Vec<Backup>) {
+        let mut kept = HashSet::new();
+        kept.extend(0..self.keep_last.min(backups.len()));
+        let mut days = HashSet::new();
+        let mut weeks = HashSet::new();
+        for (index, backup) in backups.iter().enumerate() {
+            let date = backup.created.date();
+            if days.len() < self.keep_daily && days.insert(date) {
+                kept.insert(index);
+            }
+            let week = date.iso_week();
+            if weeks.len() < self.keep_weekly && weeks.insert((week.year(), week.week())) {
+                kept.insert(index);
+            }
+        }
+        let (mut keep, mut remove) = (vec![], vec![]);
+        for (index, backup) in backups.into_iter().enumerate() {
+            if kept.contains(&index) {
+                keep.push(backup);
+            } else {
+                remove.push(backup);
+            }
+        }
+        (keep, remove)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: NaiveDateTime,
+    pub size: u64,
+}
+
+impl Backup {
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+}
+
+/// Archives found in the backup folder, newest first
+pub fn list_backups(server_path: &Path) -> Vec<Backup> {
+    let Ok(entries) = fs::read_dir(server_path.join(BACKUP_DIR)) else {
+        return vec![];
+    };
+    let mut backups: Vec<Backup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let date = name
+                .strip_prefix(BACKUP_PREFIX)?
+                .strip_suffix(BACKUP_EXTENSION)?;
+            Some(Backup {
+                created: NaiveDateTime::parse_from_str(date, BACKUP_DATE_FORMAT).ok()?,
+                size: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+                path: entry.path(),
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+    backups
+}
+
+/// World folders of the server, dimension folders of Bukkit based servers included
+fn world_folders(server_path: &Path) -> Vec<String> {
+    let level_name = ServerProperties::load(server_path)
+        .get_or_default("level-name")
+        .unwrap_or(String::from("world"));
+    DIMENSION_SUFFIXES
+        .iter()
+        .map(|suffix| format!("{}{}", level_name, suffix))
+        .filter(|folder| server_path.join(folder).is_dir())
+        .collect()
+}
+
+fn config_files(server_path: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(server_path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_file())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                // MCA.json, MCA.pid and the like belong to MCT, not to the server
+                .filter(|name| !name.starts_with("MCA"))
+                .filter(|name| {
+                    name.rsplit_once('.')
+                        .is_some_and(|(_, extension)| CONFIG_EXTENSIONS.contains(&extension))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.extend(
+        CONFIG_DIRS
+            .iter()
+            .filter(|dir| server_path.join(dir).is_dir())
+            .map(|dir| dir.to_string()),
+    );
+    files.sort();
+    files
+}
+
+fn write_archive(server_path: &Path, archive: &Path, entries: &[String]) -> std::io::Result<()> {
+    let file = fs::File::create(archive)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for entry in entries {
+        let path = server_path.join(entry);
+        if path.is_dir() {
+            builder.append_dir_all(entry, &path)?;
+        } else {
+            builder.append_path_with_name(&path, entry)?;
+        }
+    }
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+/// Archives the worlds and configs, pausing the saves of a running server meanwhile
+pub async fn create(server_path: &Path) -> Result<Backup, String> {
+    let worlds = world_folders(server_path);
+    if worlds.is_empty() {
+        return Err(format!(
+            "No world found in '{}', start the server once first",
+            server_path.to_string_lossy()
+        ));
+    }
+    let mut entries = worlds;
+    entries.extend(config_files(server_path));
+
+    let running = session::running_pid(server_path).is_some();
+    if running {
+        session::send_command(server_path, "save-off").await?;
+        // Archiving before the save completed would copy half written region files
+        let saved = session::send_command_until(server_path, "save-all flush", SAVED_LINE, SAVE_TIMEOUT).await;
+        if let Err(e) = saved {
+            let _ = session::send_command(server_path, "save-on").await;
+            return Err(e);
+        }
+    }
+
+    let backup_dir = server_path.join(BACKUP_DIR);
+    let created = Local::now().naive_local();
+    let name = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        created.format(BACKUP_DATE_FORMAT),
+        BACKUP_EXTENSION
+    );
+    let archive = backup_dir.join(&name);
+    // Written aside then renamed, a half written archive never looks like a backup
+    let partial = backup_dir.join(format!("{}.partial", name));
+    let result = fs::create_dir_all(&backup_dir)
+        .and_then(|_| write_archive(server_path, &partial, &entries))
+        .and_then(|_| fs::rename(&partial, &archive));
+
+    if running {
+        if let Err(e) = session::send_command(server_path, "save-on").await {
+            println!("❌ Unable to turn saving back on, run `save-on` : {}", e);
+        }
+    }
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("Error while writting the backup : {}", e));
+    }
+    Ok(Backup {
+        size: fs::metadata(&archive).map(|m| m.len()).unwrap_or_default(),
+        path: archive,
+        created,
+    })
+}
+
+/// Removes the archives not kept by the policy
+pub fn prune(server_path: &Path, policy: &RetentionPolicy) -> Vec<Backup> {
+    let (_, removed) = policy.apply(list_backups(server_path));
+    removed
+        .into_iter()
+        .filter(|backup| match fs::remove_file(&backup.path) {
+            Ok(_) => true,
+            Err(e) => {
+                println!("❌ Unable to remove {} : {}", backup.name(), e);
+                false
+            }
+        })
+        .collect()
+}
+
+/// A backup name, a path to an archive or `latest`
+fn find_backup(server_path: &Path, name: &str) -> Option<PathBuf> {
+    if name == "latest" {
+        return list_backups(server_path).into_iter().next().map(|b| b.path);
+    }
+    [
+        PathBuf::from(name),
+        server_path.join(BACKUP_DIR).join(name),
+        server_path
+            .join(BACKUP_DIR)
+            .join(format!("{}{}", name, BACKUP_EXTENSION)),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Replaces the worlds and configs by the content of an archive, all or nothing
+pub fn restore(server_path: &Path, name: &str) -> Result<PathBuf, String> {
+    if let Some(pid) = session::running_pid(server_path) {
+        return Err(format!("Server is running (pid {}), stop it first", pid));
+    }
+    let archive = find_backup(server_path, name).ok_or(format!("No backup named '{}'", name))?;
+    let staging = server_path.join(".restore");
+    let previous = server_path.join(".restore-previous");
+    if previous.exists() {
+        return Err(format!(
+            "{} is left from an interrupted restore, move its content back first",
+            previous.to_string_lossy()
+        ));
+    }
+    let _ = fs::remove_dir_all(&staging);
+
+    let unpacked = fs::File::open(&archive)
+        .and_then(|file| tar::Archive::new(GzDecoder::new(file)).unpack(&staging));
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("Error while reading {} : {}", archive.to_string_lossy(), e));
+    }
+
+    let entries: Vec<String> = fs::read_dir(&staging)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    // Worlds missing from the archive are moved aside too, the restored world is never mixed with them
+    let stale: Vec<String> = world_folders(server_path)
+        .into_iter()
+        .filter(|world| !entries.contains(world))
+        .collect();
+    // Current files are moved aside first so a failure can put everything back,
+    // the flag tells whether the archive put something in place of the entry
+    let mut swapped: Vec<(&String, bool)> = vec![];
+    let result = fs::create_dir_all(&previous).and_then(|_| {
+        for entry in &stale {
+            fs::rename(server_path.join(entry), previous.join(entry))?;
+            swapped.push((entry, false));
+        }
+        for entry in &entries {
+            if server_path.join(entry).exists() {
+                fs::rename(server_path.join(entry), previous.join(entry))?;
+            }
+            swapped.push((entry, true));
+            fs::rename(staging.join(entry), server_path.join(entry))?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        let mut rolled_back = true;
+        for (entry, restored) in swapped {
+            if restored && server_path.join(entry).exists() {
+                if let Err(e) = fs::rename(server_path.join(entry), staging.join(entry)) {
+                    println!("❌ Unable to remove the restored {} : {}", entry, e);
+                    rolled_back = false;
+                    continue;
+                }
+            }
+            if previous.join(entry).exists() {
+                if let Err(e) = fs::rename(previous.join(entry), server_path.join(entry)) {
+                    println!("❌ Unable to put {} back : {}", entry, e);
+                    rolled_back = false;
+                }
+            }
+        }
+        let _ = fs::remove_dir_all(&staging);
+        if !rolled_back {
+            // The previous files may be the only copy left, they are never deleted here
+            return Err(format!(
+                "Error while restoring : {}, the previous files are kept in {}",
+                e,
+                previous.to_string_lossy()
+            ));
+        }
+        let _ = fs::remove_dir_all(&previous);
+        return Err(format!("Error while restoring, nothing was changed : {}", e));
+    }
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_dir_all(&previous);
+    Ok(archive)
+}
+
+pub fn print_list(server_path: &Path) {
+    let backups = list_backups(server_path);
+    if backups.is_empty() {
+        println!("➡️ No backup in '{}'", server_path.join(BACKUP_DIR).to_string_lossy());
+        return;
+    }
+    for backup in backups {
+        println!(
+            "{}  {}  {:.1} MB",
+            backup.name(),
+            backup.created.format("%Y-%m-%d %H:%M:%S"),
+            backup.size as f64 / 1_048_576.0
+        );
+    }
+}
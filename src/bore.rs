@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::tunnel::{Tunnel, TunnelProvider};
+
+/// Control port of bore servers
+pub const BORE_CONTROL_PORT: u16 = 7835;
+/// Longest message accepted by bore servers, a null byte ends each message
+const MAX_FRAME_LENGTH: usize = 256;
+
+/// Messages sent to the bore server, serialized like bore does (`{"Hello":0}`)
+#[derive(Debug, Serialize)]
+enum ClientMessage {
+    /// HMAC of the challenge
+    Authenticate(String),
+    /// Remote port requested, 0 for any
+    Hello(u16),
+    /// Takes the public connection with this id
+    Accept(String),
+}
+
+#[derive(Debug, Deserialize)]
+enum ServerMessage {
+    /// Sent first when the server has a secret
+    Challenge(String),
+    /// Public port of the tunnel
+    Hello(u16),
+    Heartbeat,
+    /// A player connected, accept it on a new connection
+    Connection(String),
+    Error(String),
+}
+
+/// Connection to the control port, exchanging null terminated JSON messages
+struct Control {
+    stream: BufReader<TcpStream>,
+}
+
+impl Control {
+    async fn connect(address: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(address)
+            .await
+            .map_err(|e| format!("Unable to reach the bore server {} : {}", address, e))?;
+        let _ = stream.set_nodelay(true);
+        Ok(Self {
+            stream: BufReader::new(stream),
+        })
+    }
+
+    async fn send(&mut self, message: ClientMessage) -> Result<(), String> {
+        let mut frame = serde_json::to_vec(&message).unwrap();
+        frame.push(0);
+        self.stream
+            .get_mut()
+            .write_all(&frame)
+            .await
+            .map_err(|e| format!("Error while writting to the bore server : {}", e))
+    }
+
+    /// `None` when the server closed the connection
+    async fn recv(&mut self) -> Result<Option<ServerMessage>, String> {
+        let mut frame = vec![];
+        let read = (&mut self.stream)
+            .take(MAX_FRAME_LENGTH as u64 + 1)
+            .read_until(0, &mut frame)
+            .await
+            .map_err(|e| format!("Error while reading from the bore server : {}", e))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if frame.pop() != Some(0) {
+            return Err(String::from("Invalid message from the bore server"));
+        }
+        serde_json::from_slice(&frame)
+            .map(Some)
+            .map_err(|e| format!("Unknown message from the bore server : {}", e))
+    }
+
+    /// Answers the challenge of a server with a secret, every connection starts with it
+    async fn authenticate(&mut self, secret: Option<&str>) -> Result<(), String> {
+        let Some(secret) = secret else {
+            return Ok(());
+        };
+        match self.recv().await? {
+            Some(ServerMessage::Challenge(challenge)) => {
+                let answer = answer_challenge(secret, &challenge)?;
+                self.send(ClientMessage::Authenticate(answer)).await
+            }
+            Some(ServerMessage::Error(e)) => Err(format!("bore server error : {}", e)),
+            _ => Err(String::from("The bore server did not send a challenge, is a secret needed ?")),
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of the challenge uuid bytes, keyed by the SHA256 of the secret
+fn answer_challenge(secret: &str, challenge: &str) -> Result<String, String> {
+    let uuid: String = challenge.chars().filter(|c| *c != '-').collect();
+    let bytes = (0..uuid.len())
+        .step_by(2)
+        .map(|i| uuid.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .filter(|bytes| bytes.len() == 16)
+        .ok_or(format!("Invalid challenge '{}' from the bore server", challenge))?;
+    Ok(hmac_sha256(&Sha256::digest(secret.as_bytes()), &bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Native client of a bore server (https://github.com/ekzhang/bore)
+#[derive(Debug)]
+pub struct BoreClient {
+    host: String,
+    control_port: u16,
+    secret: Option<String>,
+}
+
+impl BoreClient {
+    /// `server` is `host` or `host:port`, the port defaults to 7835
+    pub fn build(server: &str, secret: Option<String>) -> Result<Self, String> {
+        let (host, control_port) = match server.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("Invalid bore server port '{}'", port))?,
+            ),
+            None => (server, BORE_CONTROL_PORT),
+        };
+        if host.is_empty() {
+            return Err(String::from("A bore server is required ex : bore.pub"));
+        }
+        Ok(Self {
+            host: host.to_owned(),
+            control_port,
+            secret: secret.filter(|s| !s.is_empty()),
+        })
+    }
+
+    fn address(&self) -> String {
+        format!("{}:{}", self.host, self.control_port)
+    }
+
+    /// Opens the control connection and returns the public port
+    async fn hello(&self) -> Result<(Control, u16), String> {
+        let mut control = Control::connect(&self.address()).await?;
+        control.authenticate(self.secret.as_deref()).await?;
+        control.send(ClientMessage::Hello(0)).await?;
+        match control.recv().await? {
+            Some(ServerMessage::Hello(port)) => Ok((control, port)),
+            Some(ServerMessage::Error(e)) => Err(format!("bore server error : {}", e)),
+            Some(message) => Err(format!("Unexpected message from the bore server : {:?}", message)),
+            None => Err(String::from("The bore server closed the connection")),
+        }
+    }
+
+    /// Takes the public connection `id` and forwards it to the local server
+    async fn accept(&self, id: String, local_port: u16) -> Result<(), String> {
+        let mut remote = Control::connect(&self.address()).await?;
+        remote.authenticate(self.secret.as_deref()).await?;
+        remote.send(ClientMessage::Accept(id)).await?;
+        let mut local = TcpStream::connect(("127.0.0.1", local_port))
+            .await
+            .map_err(|e| format!("Unable to reach the server on port {} : {}", local_port, e))?;
+        let _ = local.set_nodelay(true);
+        // Bytes read past the last message already belong to the player
+        let buffered = remote.stream.buffer().to_vec();
+        local
+            .write_all(&buffered)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut remote = remote.stream.into_inner();
+        let _ = tokio::io::copy_bidirectional(&mut remote, &mut local).await;
+        Ok(())
+    }
+}
+
+impl TunnelProvider for BoreClient {
+    fn open(self: Arc<Self>, local_port: u16) -> Result<Tunnel, String> {
+        println!(
+            "🚀 Starting bore tunnel for port {} through {}",
+            local_port,
+            self.address()
+        );
+        Ok(Tunnel::spawn("bore", |mut stopped| async move {
+            let mut control = tokio::select! {
+                hello = self.hello() => match hello {
+                    Ok((control, port)) => {
+                        println!("🌐 Public address : {}:{}", self.host, port);
+                        control
+                    }
+                    Err(e) => {
+                        println!("❌ {}", e);
+                        return;
+                    }
+                },
+                _ = &mut stopped => return,
+            };
+            loop {
+                tokio::select! {
+                    message = control.recv() => match message {
+                        Ok(Some(ServerMessage::Heartbeat)) => {}
+                        Ok(Some(ServerMessage::Connection(id))) => {
+                            let client = self.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = client.accept(id, local_port).await {
+                                    println!("⚠️ bore connection failed : {}", e);
+                                }
+                            });
+                        }
+                        Ok(Some(ServerMessage::Error(e))) => println!("⚠️ bore server error : {}", e),
+                        Ok(Some(message)) => println!("⚠️ Unexpected message from the bore server : {:?}", message),
+                        Ok(None) => {
+                            println!("⚠️ The bore server closed the tunnel");
+                            return;
+                        }
+                        Err(e) => {
+                            println!("⚠️ {}", e);
+                            return;
+                        }
+                    },
+                    _ = &mut stopped => return,
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        assert_eq!(
+            hex(&hmac_sha256(&[0x0b; 20], b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn challenges_are_answered() {
+        assert_eq!(
+            answer_challenge("my secret", "a1b2c3d4-e5f6-4789-8abc-def012345678").unwrap(),
+            "034733680afc14667f8b639d86a10fb3675c208d1f462711dda528223d914014"
+        );
+        // The hyphens are only formatting
+        assert_eq!(
+            answer_challenge("my secret", "a1b2c3d4e5f647898abcdef012345678").unwrap(),
+            "034733680afc14667f8b639d86a10fb3675c208d1f462711dda528223d914014"
+        );
+        for challenge in [
+            "",
+            "a1b2c3d4-e5f6-4789-8abc-def01234567",
+            "a1b2c3d4-e5f6-4789-8abc-def0123456789",
+            "z1b2c3d4-e5f6-4789-8abc-def012345678",
+            "é1b2c3d4-e5f6-4789-8abc-def01234567",
+        ] {
+            assert!(answer_challenge("my secret", challenge).is_err(), "{}", challenge);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Plugins and mods installed by MCT, in the server directory
+pub const CONTENT_FILE: &str = "MCA_content.json";
+
+/// One file installed by MCT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledContent {
+    /// Where it comes from ex : modrinth
+    pub source: String,
+    /// Project id in its source
+    pub project: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    /// Asset pattern of a GitHub release, reused by updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+}
+
+/// Installed files by path relative to the server directory, ex : `plugins/LuckPerms.jar`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContentManifest {
+    pub files: BTreeMap<String, InstalledContent>,
+}
+
+impl ContentManifest {
+    pub fn load(server_path: &Path) -> Self {
+        fs::read_to_string(server_path.join(CONTENT_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, server_path: &Path) -> std::io::Result<()> {
+        fs::write(
+            server_path.join(CONTENT_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+
+    /// File installed for a project, if any
+    pub fn find(&self, source: &str, project: &str) -> Option<(&String, &InstalledContent)> {
+        self.files
+            .iter()
+            .find(|(_, entry)| entry.source == source && entry.project == project)
+    }
+
+    /// Records `file`, removing the file of a previous version of the same project
+    pub fn record(&mut self, server_path: &Path, file: String, entry: InstalledContent) {
+        let previous: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(path, old)| {
+                **path != file && old.source == entry.source && old.project == entry.project
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in previous {
+            if fs::remove_file(server_path.join(&path)).is_ok() {
+                println!("➡️ Removed the previous version {}", path);
+            }
+            self.files.remove(&path);
+        }
+        self.files.insert(file, entry);
+    }
+}
+
+pub fn sha512_hex(content: &[u8]) -> String {
+    Sha512::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn sha1_hex(content: &[u8]) -> String {
+    Sha1::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_of_known_vectors() {
+        assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(sha512_hex(b"abc").starts_with("ddaf35a193617aba"));
+    }
+}
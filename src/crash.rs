@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha512};
+
+use crate::modrinth_request;
+
+pub const CRASH_REPORTS_DIR: &str = "crash-reports";
+/// Folders holding the jars a stack frame can be blamed on
+const CONTENT_DIRS: [&str; 2] = ["mods", "plugins"];
+/// Server, Java and common library packages, never a culprit even when shaded in a jar
+const IGNORED_PACKAGES: [&str; 18] = [
+    "java.",
+    "javax.",
+    "jdk.",
+    "sun.",
+    "com.sun.",
+    "net.minecraft.",
+    "com.mojang.",
+    "org.bukkit.",
+    "org.spigotmc.",
+    "io.papermc.",
+    "net.fabricmc.loader.",
+    "org.spongepowered.asm.",
+    "com.google.",
+    "io.netty.",
+    "org.apache.",
+    "org.slf4j.",
+    "it.unimi.",
+    "kotlin.",
+];
+/// `at com.example.Foo.bar(Foo.java:12)` in crash reports, `j  com.example.Foo.bar()V+4` in hs_err files
+const FRAME_PATTERN: &str = r"^\s*(?:at |[jJ] +(?:\d+ +(?:c\d +)?)?)([A-Za-z_$][\w$]*(?:\.[A-Za-z_$][\w$]*)+)\.([\w$<>]+)\(";
+/// Mixin handlers are named like `handler$zza000$modid$method`
+const MIXIN_PATTERN: &str = r"\$[a-z]{3}\d{3}\$([a-z][a-z0-9_-]*)\$";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrashKind {
+    /// `crash-reports/crash-*.txt` written by the server
+    Report,
+    /// `hs_err_pid*.log` written by the JVM
+    JvmError,
+}
+
+/// What is known about a crash from its report
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub path: PathBuf,
+    pub kind: CrashKind,
+    pub description: Option<String>,
+    pub error: Option<String>,
+    pub causes: Vec<String>,
+    /// Class of every stack frame, top of the stack first
+    pub frames: Vec<String>,
+    /// Mod ids found in mixin handler names, with the index of their frame
+    pub mixins: Vec<(usize, String)>,
+}
+
+impl CrashReport {
+    pub fn parse(path: &Path) -> std::io::Result<Self> {
+        let content = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+        let kind = if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("hs_err"))
+        {
+            CrashKind::JvmError
+        } else {
+            CrashKind::Report
+        };
+        let mut report = Self {
+            path: path.to_owned(),
+            kind,
+            description: None,
+            error: None,
+            causes: vec![],
+            frames: vec![],
+            mixins: vec![],
+        };
+        let frame = Regex::new(FRAME_PATTERN).unwrap();
+        let mixin = Regex::new(MIXIN_PATTERN).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        for (index, line) in lines.iter().enumerate() {
+            // The details section repeats the stack trace of the head
+            if line.starts_with("A detailed walkthrough of the error") {
+                break;
+            }
+            if let Some(captures) = frame.captures(line) {
+                if let Some(mod_id) = mixin.captures(&captures[2]) {
+                    report.mixins.push((report.frames.len(), mod_id[1].to_owned()));
+                }
+                report.frames.push(captures[1].to_owned());
+                continue;
+            }
+            let next = lines.get(index + 1).map(|l| l.trim_start_matches('#').trim());
+            match report.kind {
+                CrashKind::Report => {
+                    if let Some(description) = line.strip_prefix("Description: ") {
+                        report.description = Some(description.to_owned());
+                        // The exception follows the blank line after the description
+                        report.error = lines[index + 1..]
+                            .iter()
+                            .find(|l| !l.trim().is_empty())
+                            .map(|l| l.trim().to_owned());
+                    } else if let Some(cause) = line.trim().strip_prefix("Caused by: ") {
+                        report.causes.push(cause.to_owned());
+                    }
+                }
+                CrashKind::JvmError => {
+                    if line.starts_with("# A fatal error has been detected") {
+                        report.description = next.map(|l| l.to_owned());
+                    } else if line.starts_with("# Problematic frame:") {
+                        report.error = next.map(|l| l.to_owned());
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn display(&self, server_path: &Path) {
+        let name = self.path.strip_prefix(server_path).unwrap_or(&self.path);
+        match self.kind {
+            CrashKind::Report => println!("💥 Crash report : {}", name.to_string_lossy()),
+            CrashKind::JvmError => println!("💥 JVM crash log : {}", name.to_string_lossy()),
+        }
+        if let Some(description) = &self.description {
+            println!("   Description: {}", description);
+        }
+        if let Some(error) = &self.error {
+            println!("   Error: {}", error);
+        }
+        for cause in &self.causes {
+            println!("   Caused by: {}", cause);
+        }
+    }
+}
+
+/// Newest crash report or hs_err file of the server
+pub fn newest_crash(server_path: &Path) -> Option<PathBuf> {
+    let reports = fs::read_dir(server_path.join(CRASH_REPORTS_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".txt"));
+    let jvm_errors = fs::read_dir(server_path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with("hs_err_pid") && name.ends_with(".log")
+        });
+    reports
+        .chain(jvm_errors)
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// A mod or plugin jar with the packages of its classes
+struct ContentJar {
+    path: PathBuf,
+    packages: HashSet<String>,
+    mod_ids: Vec<String>,
+}
+
+impl ContentJar {
+    fn read(path: &Path) -> Option<Self> {
+        let mut archive = zip::ZipArchive::new(fs::File::open(path).ok()?).ok()?;
+        let mut packages = HashSet::new();
+        for name in archive.file_names() {
+            if name.starts_with("META-INF/") || !name.ends_with(".class") {
+                continue;
+            }
+            if let Some((package, _)) = name.rsplit_once('/') {
+                packages.insert(package.replace('/', "."));
+            }
+        }
+        let mut mod_ids = vec![];
+        if let Some(manifest) = read_entry(&mut archive, "fabric.mod.json") {
+            if let Some(id) = serde_json::from_str::<Value>(&manifest)
+                .ok()
+                .and_then(|m| m["id"].as_str().map(|id| id.to_owned()))
+            {
+                mod_ids.push(id);
+            }
+        }
+        let mod_id = Regex::new(r#"modId\s*=\s*"([^"]+)""#).unwrap();
+        for toml in ["META-INF/mods.toml", "META-INF/neoforge.mods.toml"] {
+            if let Some(manifest) = read_entry(&mut archive, toml) {
+                mod_ids.extend(mod_id.captures_iter(&manifest).map(|c| c[1].to_owned()));
+            }
+        }
+        Some(Self {
+            path: path.to_owned(),
+            packages,
+            mod_ids,
+        })
+    }
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Option<String> {
+    let mut content = String::new();
+    archive.by_name(name).ok()?.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
+fn content_jars(server_path: &Path) -> Vec<ContentJar> {
+    CONTENT_DIRS
+        .iter()
+        .flat_map(|dir| fs::read_dir(server_path.join(dir)).into_iter().flatten().flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "jar"))
+        .filter_map(|path| ContentJar::read(&path))
+        .collect()
+}
+
+/// A jar found in the stack trace
+#[derive(Debug, Clone)]
+pub struct Suspect {
+    pub path: PathBuf,
+    pub frames: usize,
+    /// Index of its topmost frame, the lower the closer to the error
+    pub first_frame: usize,
+}
+
+/// Jars owning the frames of the report, likeliest culprit first
+pub fn suspects(server_path: &Path, report: &CrashReport) -> Vec<Suspect> {
+    let jars = content_jars(server_path);
+    let mut by_package: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_mod_id: HashMap<&str, usize> = HashMap::new();
+    for (index, jar) in jars.iter().enumerate() {
+        for package in &jar.packages {
+            by_package.entry(package.as_str()).or_default().push(index);
+        }
+        for mod_id in &jar.mod_ids {
+            by_mod_id.insert(mod_id.as_str(), index);
+        }
+    }
+
+    let mut suspects: HashMap<usize, Suspect> = HashMap::new();
+    let mut blame = |jar: usize, frame: usize| {
+        let suspect = suspects.entry(jar).or_insert(Suspect {
+            path: jars[jar].path.clone(),
+            frames: 0,
+            first_frame: frame,
+        });
+        suspect.frames += 1;
+        suspect.first_frame = suspect.first_frame.min(frame);
+    };
+    for (frame, class) in report.frames.iter().enumerate() {
+        if IGNORED_PACKAGES.iter().any(|p| class.starts_with(p)) {
+            continue;
+        }
+        let Some((package, _)) = class.rsplit_once('.') else {
+            continue;
+        };
+        for jar in by_package.get(package).into_iter().flatten() {
+            blame(*jar, frame);
+        }
+    }
+    for (frame, mod_id) in &report.mixins {
+        if let Some(jar) = by_mod_id.get(mod_id.as_str()) {
+            blame(*jar, *frame);
+        }
+    }
+
+    let mut suspects: Vec<Suspect> = suspects.into_values().collect();
+    suspects.sort_by_key(|s| (s.first_frame, std::cmp::Reverse(s.frames)));
+    suspects
+}
+
+fn sha512(path: &Path) -> Option<String> {
+    let content = fs::read(path).ok()?;
+    Some(
+        Sha512::digest(&content)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    )
+}
+
+/// Parses a crash (the newest one by default) and prints the likely culprit
+pub async fn triage(server_path: &Path, file: Option<&Path>) {
+    let Some(path) = file.map(|f| f.to_owned()).or_else(|| newest_crash(server_path)) else {
+        println!(
+            "✅ No crash report found in '{}'",
+            server_path.to_string_lossy()
+        );
+        return;
+    };
+    let report = match CrashReport::parse(&path) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("❌ Unable to read '{}' : {}", path.to_string_lossy(), e);
+            return;
+        }
+    };
+    report.display(server_path);
+
+    let suspects = suspects(server_path, &report);
+    let Some(culprit) = suspects.first() else {
+        println!("➡️ No mod or plugin found in the stack trace, the crash likely comes from the server or Java itself");
+        return;
+    };
+    let name = |path: &Path| {
+        path.strip_prefix(server_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    };
+    println!(
+        "🔎 Likely culprit : {} ({} frame(s), topmost at #{})",
+        name(&culprit.path),
+        culprit.frames,
+        culprit.first_frame
+    );
+    match sha512(&culprit.path) {
+        Some(hash) => match modrinth_request::project_from_hash(&hash, "sha512").await {
+            Some(project) => println!(
+                "   Modrinth : {} - https://modrinth.com/{}/{}",
+                project["title"].as_str().unwrap_or_default(),
+                project["project_type"].as_str().unwrap_or("project"),
+                project["slug"].as_str().unwrap_or_default()
+            ),
+            None => println!("   Not found on Modrinth"),
+        },
+        None => println!("   Unable to read the jar"),
+    }
+    for suspect in &suspects[1..] {
+        println!(
+            "   Also in the stack : {} ({} frame(s))",
+            name(&suspect.path),
+            suspect.frames
+        );
+    }
+}
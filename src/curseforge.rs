@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::content::{self, ContentManifest, InstalledContent};
+use crate::fabric_request::FabricMCRequest;
+use crate::plugins::{self, FileHash, MODS_DIR};
+use crate::session;
+use crate::settings;
+
+/// Description of a pack, at the root of its zip
+const PACK_MANIFEST: &str = "manifest.json";
+/// CurseForge class of mods, resource packs and shaders are not used by servers
+const CLASS_MODS: u64 = 6;
+/// CurseForge hash algorithms
+const ALGORITHM_SHA1: u64 = 1;
+
+#[derive(Debug, Deserialize)]
+struct PackManifest {
+    name: Option<String>,
+    version: Option<String>,
+    minecraft: PackMinecraft,
+    #[serde(default)]
+    files: Vec<PackFile>,
+    #[serde(default = "default_overrides")]
+    overrides: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackMinecraft {
+    version: String,
+    #[serde(default, rename = "modLoaders")]
+    mod_loaders: Vec<PackLoader>,
+}
+
+/// `fabric-0.16.5`, `forge-47.3.0` or `neoforge-21.1.66`
+#[derive(Debug, Deserialize)]
+struct PackLoader {
+    id: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackFile {
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    #[serde(rename = "fileID")]
+    file_id: u64,
+    #[serde(default = "default_required")]
+    required: bool,
+}
+
+fn default_overrides() -> String {
+    String::from("overrides")
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// File of the pack and where it is downloaded from
+#[derive(Debug)]
+struct PlannedFile {
+    relative: String,
+    url: String,
+    hash: FileHash,
+    entry: InstalledContent,
+}
+
+/// POST returning JSON, the API key is only sent to CurseForge
+async fn post_json(url: &str, body: &Value, api_key: Option<&str>) -> Result<Value, String> {
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("MCT/", env!("CARGO_PKG_VERSION")),
+        )
+        .json(body);
+    if let Some(api_key) = api_key {
+        request = request.header("x-api-key", api_key);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed : {}", e))?;
+    if response.status() == reqwest::StatusCode::FORBIDDEN && api_key.is_some() {
+        return Err(String::from(
+            "CurseForge refused the API key, check the curseforge.api_key setting",
+        ));
+    }
+    response
+        .error_for_status()
+        .map_err(|e| format!("Request failed : {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {} : {}", url, e))
+}
+
+fn read_manifest(archive: &mut zip::ZipArchive<fs::File>) -> Result<PackManifest, String> {
+    let entry = archive.by_name(PACK_MANIFEST).map_err(|_| {
+        format!(
+            "No {} in the pack, is it a CurseForge pack ?",
+            PACK_MANIFEST
+        )
+    })?;
+    serde_json::from_reader(entry).map_err(|e| format!("Invalid {} : {}", PACK_MANIFEST, e))
+}
+
+/// Modrinth versions by the sha1 of their files, files found there are downloaded from Modrinth
+async fn modrinth_versions(sha1s: &[String]) -> HashMap<String, Value> {
+    if sha1s.is_empty() {
+        return HashMap::new();
+    }
+    match post_json(
+        &format!("{}/v2/version_files", settings::api("modrinth")),
+        &json!({ "hashes": sha1s, "algorithm": "sha1" }),
+        None,
+    )
+    .await
+    {
+        Ok(Value::Object(versions)) => versions.into_iter().collect(),
+        Ok(_) => HashMap::new(),
+        Err(e) => {
+            println!(
+                "⚠️ Modrinth lookup failed, every file comes from CurseForge : {}",
+                e
+            );
+            HashMap::new()
+        }
+    }
+}
+
+/// Fabric loader version of the pack, MCT only installs Fabric servers
+fn fabric_loader_version(manifest: &PackManifest) -> Result<&str, String> {
+    let loader = manifest
+        .minecraft
+        .mod_loaders
+        .iter()
+        .find(|loader| loader.primary)
+        .or(manifest.minecraft.mod_loaders.first())
+        .ok_or(String::from("The pack does not name its mod loader"))?;
+    let (name, version) = loader
+        .id
+        .split_once('-')
+        .ok_or(format!("Unknown mod loader '{}'", loader.id))?;
+    if name != "fabric" {
+        return Err(format!(
+            "MCT does not install {} servers yet, the pack needs {} {} for Minecraft {}",
+            name, name, version, manifest.minecraft.version
+        ));
+    }
+    Ok(version)
+}
+
+/// Files of the pack, from Modrinth when a file with the same hash is there
+async fn plan_files(
+    manifest: &PackManifest,
+    api_key: &str,
+) -> Result<(Vec<PlannedFile>, Vec<String>), String> {
+    let api = settings::api("curseforge");
+    let wanted: Vec<&PackFile> = manifest.files.iter().filter(|file| file.required).collect();
+    if wanted.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let files = post_json(
+        &format!("{}/v1/mods/files", api),
+        &json!({ "fileIds": wanted.iter().map(|file| file.file_id).collect::<Vec<u64>>() }),
+        Some(api_key),
+    )
+    .await?;
+    let files: HashMap<u64, Value> = files["data"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|file| Some((file["id"].as_u64()?, file)))
+        .collect();
+    let projects = post_json(
+        &format!("{}/v1/mods", api),
+        &json!({ "modIds": wanted.iter().map(|file| file.project_id).collect::<Vec<u64>>() }),
+        Some(api_key),
+    )
+    .await?;
+    let projects: HashMap<u64, Value> = projects["data"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|project| Some((project["id"].as_u64()?, project)))
+        .collect();
+
+    let sha1_of = |file: &Value| -> Option<String> {
+        file["hashes"]
+            .as_array()?
+            .iter()
+            .find(|hash| hash["algo"].as_u64() == Some(ALGORITHM_SHA1))
+            .and_then(|hash| hash["value"].as_str())
+            .map(|hash| hash.to_lowercase())
+    };
+    let sha1s: Vec<String> = files.values().filter_map(sha1_of).collect();
+    let modrinth = modrinth_versions(&sha1s).await;
+
+    let mut planned = vec![];
+    let mut manual = vec![];
+    let mut skipped = 0;
+    for wanted in wanted {
+        let project = projects.get(&wanted.project_id);
+        let name = project
+            .and_then(|project| project["name"].as_str())
+            .map(|name| name.to_owned())
+            .unwrap_or(format!("Project {}", wanted.project_id));
+        if project.is_some_and(|project| project["classId"].as_u64() != Some(CLASS_MODS)) {
+            skipped += 1;
+            continue;
+        }
+        let Some(file) = files.get(&wanted.file_id) else {
+            manual.push(format!(
+                "{} : file {} not found on CurseForge",
+                name, wanted.file_id
+            ));
+            continue;
+        };
+        let file_name = file["fileName"].as_str().unwrap_or_default().to_owned();
+        let sha1 = sha1_of(file);
+        // Names come from the APIs, a name that could leave the mods folder is replaced
+        let safe_name = plugins::checked_file_name(&file_name)
+            .map(|name| name.to_owned())
+            .unwrap_or(format!("{}-{}.jar", wanted.project_id, wanted.file_id));
+
+        let on_modrinth = sha1.as_ref().and_then(|sha1| {
+            let version = modrinth.get(sha1)?;
+            let mr_file = version["files"]
+                .as_array()?
+                .iter()
+                .find(|mr_file| mr_file["hashes"]["sha1"].as_str() == Some(sha1))?;
+            Some(PlannedFile {
+                relative: format!(
+                    "{}/{}",
+                    MODS_DIR,
+                    plugins::checked_file_name(mr_file["filename"].as_str()?).ok()?
+                ),
+                url: mr_file["url"].as_str()?.to_owned(),
+                hash: FileHash::Sha512(mr_file["hashes"]["sha512"].as_str()?.to_owned()),
+                entry: InstalledContent {
+                    source: String::from("modrinth"),
+                    project: version["project_id"].as_str()?.to_owned(),
+                    name: name.clone(),
+                    version: version["version_number"].as_str()?.to_owned(),
+                    sha512: None,
+                    asset: None,
+                },
+            })
+        });
+        if let Some(file) = on_modrinth {
+            planned.push(file);
+            continue;
+        }
+
+        // Authors can forbid downloads outside of the CurseForge launcher
+        let (Some(url), Some(sha1)) = (file["downloadUrl"].as_str(), sha1) else {
+            let page = project
+                .and_then(|project| project["links"]["websiteUrl"].as_str())
+                .map(|page| format!("{}/files/{}", page, wanted.file_id))
+                .unwrap_or(format!(
+                    "project {} file {}",
+                    wanted.project_id, wanted.file_id
+                ));
+            manual.push(format!("{} : download {} from {}", name, file_name, page));
+            continue;
+        };
+        planned.push(PlannedFile {
+            relative: format!("{}/{}", MODS_DIR, safe_name),
+            url: url.to_owned(),
+            hash: FileHash::Sha1(sha1),
+            entry: InstalledContent {
+                source: String::from("curseforge"),
+                project: wanted.project_id.to_string(),
+                name,
+                version: file["displayName"]
+                    .as_str()
+                    .unwrap_or(&file_name)
+                    .to_owned(),
+                sha512: None,
+                asset: None,
+            },
+        });
+    }
+    if skipped > 0 {
+        println!(
+            "➡️ {} resource packs or shaders skipped, servers do not use them",
+            skipped
+        );
+    }
+    Ok((planned, manual))
+}
+
+/// Downloads the files `download.concurrency` at a time, returns the installed ones
+async fn download_all(server_path: &Path, files: Vec<PlannedFile>) -> Vec<(PlannedFile, Vec<u8>)> {
+    let permits = Arc::new(Semaphore::new(
+        settings::get_usize("download.concurrency")
+            .unwrap_or(4)
+            .max(1),
+    ));
+    let mut downloads = JoinSet::new();
+    for file in files {
+        let permits = permits.clone();
+        let path = server_path.join(&file.relative);
+        downloads.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let content = plugins::download(&file.url, &path, Some(&file.hash), None).await;
+            (file, content)
+        });
+    }
+    let mut installed = vec![];
+    while let Some(result) = downloads.join_next().await {
+        match result {
+            Ok((file, Ok(content))) => installed.push((file, content)),
+            Ok((file, Err(e))) => println!("❌ {} not installed : {}", file.entry.name, e),
+            Err(e) => println!("❌ Download task failed : {}", e),
+        }
+    }
+    installed
+}
+
+/// Copies the `overrides/` of the pack into the server, ex : configs
+fn extract_overrides(
+    archive: &mut zip::ZipArchive<fs::File>,
+    overrides: &str,
+    server_path: &Path,
+) -> Result<usize, String> {
+    let prefix = PathBuf::from(overrides);
+    let mut count = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        // enclosed_name refuses entries escaping the server directory
+        let Some(relative) = entry.enclosed_name().and_then(|name| {
+            name.strip_prefix(&prefix)
+                .ok()
+                .map(|name| name.to_path_buf())
+        }) else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        let target = server_path.join(&relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut file = fs::File::create(&target)
+            .map_err(|e| format!("Error while writting {} : {}", target.to_string_lossy(), e))?;
+        std::io::copy(&mut entry, &mut file).map_err(|e| e.to_string())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// `MCT Modpack Import <pack.zip> <path>` : a server with the loader, mods and overrides of a pack
+pub async fn import(pack: &Path, server_path: &Path) -> Result<(), String> {
+    let mut archive = fs::File::open(pack)
+        .map_err(|e| e.to_string())
+        .and_then(|file| zip::ZipArchive::new(file).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Unable to open {} : {}", pack.to_string_lossy(), e))?;
+    let manifest = read_manifest(&mut archive)?;
+    let loader_version = fabric_loader_version(&manifest)?;
+    if session::read_metadata(server_path).is_some() {
+        return Err(format!(
+            "'{}' already has a server, import the pack in a new directory",
+            server_path.to_string_lossy()
+        ));
+    }
+    let api_key = settings::get("curseforge.api_key").ok_or(String::from(
+        "CurseForge needs an API key, set curseforge.api_key (https://console.curseforge.com)",
+    ))?;
+    println!(
+        "➡️ Importing {} {} for Minecraft {}",
+        manifest.name.as_deref().unwrap_or("the pack"),
+        manifest.version.as_deref().unwrap_or_default(),
+        manifest.minecraft.version
+    );
+    fs::create_dir_all(server_path).map_err(|e| e.to_string())?;
+
+    let (files, manual) = plan_files(&manifest, &api_key).await?;
+    FabricMCRequest::build(Some(server_path.to_path_buf()))
+        .provision(
+            server_path.to_path_buf(),
+            manifest.minecraft.version.clone(),
+            loader_version.to_owned(),
+        )
+        .await?;
+
+    let total = files.len();
+    let from_modrinth = files
+        .iter()
+        .filter(|file| file.entry.source == "modrinth")
+        .count();
+    let mut content_manifest = ContentManifest::load(server_path);
+    let installed = download_all(server_path, files).await;
+    let installed_count = installed.len();
+    for (mut file, content) in installed {
+        file.entry.sha512 = Some(content::sha512_hex(&content));
+        content_manifest.record(server_path, file.relative, file.entry);
+    }
+    content_manifest
+        .save(server_path)
+        .map_err(|e| format!("Error while writting {} : {}", content::CONTENT_FILE, e))?;
+
+    let overrides = extract_overrides(&mut archive, &manifest.overrides, server_path)?;
+    println!(
+        "✅ {}/{} mods installed, {} from Modrinth, {} override files copied",
+        installed_count, total, from_modrinth, overrides
+    );
+    for file in &manual {
+        println!("⚠️ {}", file);
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+
+/// Used when no nameserver can be read from /etc/resolv.conf
+const FALLBACK_NAMESERVER: &str = "1.1.1.1";
+const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// Resolves `_minecraft._tcp.<host>` and returns the target host and port
+/// with the lowest priority, `None` when there is no SRV record
+pub async fn resolve_minecraft_srv(host: &str) -> Option<(String, u16)> {
+    resolve_srv(&format!("_minecraft._tcp.{}", host)).await
+}
+
+pub async fn resolve_srv(name: &str) -> Option<(String, u16)> {
+    let id: u16 = rand::random();
+    let query = build_query(id, name)?;
+    let nameserver = SocketAddr::new(nameserver(), 53);
+    let bind = if nameserver.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(bind).await.ok()?;
+    socket.send_to(&query, nameserver).await.ok()?;
+
+    let mut response = vec![0u8; 4096];
+    let length = tokio::time::timeout(DNS_TIMEOUT, socket.recv(&mut response))
+        .await
+        .ok()?
+        .ok()?;
+    response.truncate(length);
+    parse_srv_response(id, &response)
+}
+
+fn nameserver() -> IpAddr {
+    fs::read_to_string("/etc/resolv.conf")
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some("nameserver"), Some(ip)) => ip.parse::<IpAddr>().ok(),
+                    _ => None,
+                }
+            })
+        })
+        .unwrap_or_else(|| FALLBACK_NAMESERVER.parse().unwrap())
+}
+
+fn build_query(id: u16, name: &str) -> Option<Vec<u8>> {
+    let mut query = Vec::with_capacity(name.len() + 18);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&TYPE_SRV.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Some(query)
+}
+
+fn parse_srv_response(id: u16, message: &[u8]) -> Option<(String, u16)> {
+    if message.len() < 12 || u16::from_be_bytes([message[0], message[1]]) != id {
+        return None;
+    }
+    let questions = u16::from_be_bytes([message[4], message[5]]);
+    let answers = u16::from_be_bytes([message[6], message[7]]);
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(message, offset)?.1 + 4;
+    }
+
+    let mut best: Option<(u16, String, u16)> = None;
+    for _ in 0..answers {
+        offset = read_name(message, offset)?.1;
+        let header = message.get(offset..offset + 10)?;
+        let record_type = u16::from_be_bytes([header[0], header[1]]);
+        let data_length = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data_start = offset + 10;
+        if record_type == TYPE_SRV {
+            let data = message.get(data_start..data_start + 6)?;
+            let priority = u16::from_be_bytes([data[0], data[1]]);
+            let port = u16::from_be_bytes([data[4], data[5]]);
+            let (target, _) = read_name(message, data_start + 6)?;
+            if best.as_ref().is_none_or(|(p, _, _)| priority < *p) {
+                best = Some((priority, target, port));
+            }
+        }
+        offset = data_start + data_length;
+    }
+    best.map(|(_, target, port)| (target, port))
+}
+
+/// Reads a possibly compressed domain name, returns it with the offset right after it
+fn read_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = vec![];
+    let mut end = None;
+    // Bounded to avoid looping on malicious compression pointers
+    for _ in 0..128 {
+        let length = *message.get(offset)? as usize;
+        if length == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if length & 0xC0 == 0xC0 {
+            let pointer = ((length & 0x3F) << 8) | *message.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        let label = message.get(offset + 1..offset + 1 + length)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += 1 + length;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answer to `_minecraft._tcp.example.com`, two records with compressed targets
+    const SRV_ANSWER: [u8; 95] = [
+        0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        0x0a, 0x5f, 0x6d, 0x69, 0x6e, 0x65, 0x63, 0x72, 0x61, 0x66, 0x74, 0x04,
+        0x5f, 0x74, 0x63, 0x70, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+        0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x21, 0x00, 0x01, 0xc0, 0x0c, 0x00,
+        0x21, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x0b, 0x00, 0x0a, 0x00,
+        0x05, 0x63, 0xdd, 0x02, 0x6d, 0x63, 0xc0, 0x1c, 0xc0, 0x0c, 0x00, 0x21,
+        0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x0f, 0x00, 0x05, 0x00, 0x00,
+        0x63, 0xde, 0x06, 0x62, 0x61, 0x63, 0x6b, 0x75, 0x70, 0xc0, 0x1c,
+    ];
+
+    #[test]
+    fn srv_answer_picks_lowest_priority() {
+        assert_eq!(
+            parse_srv_response(0x1234, &SRV_ANSWER),
+            Some((String::from("backup.example.com"), 25566))
+        );
+    }
+
+    #[test]
+    fn srv_answer_with_other_id_is_ignored() {
+        assert_eq!(parse_srv_response(0x4321, &SRV_ANSWER), None);
+    }
+
+    #[test]
+    fn truncated_srv_answer_is_ignored() {
+        assert_eq!(parse_srv_response(0x1234, &SRV_ANSWER[..70]), None);
+    }
+
+    #[test]
+    fn query_matches_answer_question() {
+        let query = build_query(0x1234, "_minecraft._tcp.example.com").unwrap();
+        assert_eq!(query[..2], SRV_ANSWER[..2]);
+        assert_eq!(query[12..], SRV_ANSWER[12..45]);
+    }
+
+    #[test]
+    fn compression_loop_is_refused() {
+        assert_eq!(read_name(&[0xc0, 0x00], 0), None);
+    }
+}
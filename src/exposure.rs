@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use inquire::Confirm;
+
+use crate::fabric_request;
+use crate::papermc_request;
+use crate::server_properties::{self, ServerProperties};
+use crate::session;
+
+/// Passwords refused whatever their length
+const COMMON_PASSWORDS: [&str; 8] = [
+    "password", "minecraft", "admin", "rcon", "changeme", "123456", "12345678", "qwerty",
+];
+const MIN_RCON_PASSWORD_LENGTH: usize = 12;
+
+/// Something that makes a public server unsafe
+#[derive(Debug, Clone, PartialEq)]
+pub enum Risk {
+    /// Anyone can join with any name, ops included
+    OfflineWithoutProxy,
+    WhitelistDisabled,
+    WeakRconPassword,
+    /// Current build, latest build
+    OutdatedBuild(String, String),
+}
+
+impl Risk {
+    /// Risks refusing the exposure unless forced, the others are warnings
+    pub fn is_blocking(&self) -> bool {
+        matches!(self, Risk::OfflineWithoutProxy | Risk::WeakRconPassword)
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Risk::OfflineWithoutProxy => String::from(
+                "online-mode=false without a Velocity or BungeeCord proxy, anyone can join as any player, ops included",
+            ),
+            Risk::WhitelistDisabled => String::from("white-list=false, anyone finding the address can join"),
+            Risk::WeakRconPassword => format!(
+                "RCON is enabled with a weak password, use at least {} characters",
+                MIN_RCON_PASSWORD_LENGTH
+            ),
+            Risk::OutdatedBuild(current, latest) => format!(
+                "The server runs {} while {} is available, update it to get the security fixes",
+                current, latest
+            ),
+        }
+    }
+}
+
+/// Proxy forwarding players with their online UUIDs, if any is configured
+pub fn proxy_forwarding(server_path: &Path) -> Option<&'static str> {
+    let paper_global = fs::read_to_string(server_path.join("config/paper-global.yml")).unwrap_or_default();
+    if yaml_section_enabled(&paper_global, "velocity") {
+        return Some("Velocity");
+    }
+    let spigot = fs::read_to_string(server_path.join("spigot.yml")).unwrap_or_default();
+    if spigot
+        .lines()
+        .any(|line| line.trim().replace(' ', "") == "bungeecord:true")
+    {
+        return Some("BungeeCord");
+    }
+    // FabricProxy and FabricProxy-Lite
+    let fabric_proxy = fs::read_dir(server_path.join("mods"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| entry.file_name().to_string_lossy().to_lowercase().starts_with("fabricproxy"));
+    fabric_proxy.then_some("FabricProxy")
+}
+
+/// `enabled: true` right under `<section>:`, enough for the Paper configuration files
+fn yaml_section_enabled(content: &str, section: &str) -> bool {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut section_indent = None;
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match section_indent {
+            Some(parent) if indent(line) > parent => {
+                if line.trim().replace(' ', "") == "enabled:true" {
+                    return true;
+                }
+            }
+            _ => {
+                section_indent = (line.trim() == format!("{}:", section)).then(|| indent(line));
+            }
+        }
+    }
+    false
+}
+
+fn is_weak_password(password: &str) -> bool {
+    password.chars().count() < MIN_RCON_PASSWORD_LENGTH
+        || COMMON_PASSWORDS.contains(&password.to_lowercase().as_str())
+}
+
+/// Newer build of the server platform, `None` when up to date or unknown
+async fn newer_build(server_path: &Path) -> Option<Risk> {
+    let metadata = session::read_metadata(server_path)?;
+    match metadata["project"].as_str()? {
+        "paper" => {
+            let game_version = metadata["game_version"].as_str()?;
+            let build = metadata["build"].as_i64()?;
+            let latest = papermc_request::latest_build(game_version).await?;
+            (latest > build).then(|| {
+                Risk::OutdatedBuild(
+                    format!("Paper {} build {}", game_version, build),
+                    format!("build {}", latest),
+                )
+            })
+        }
+        "fabric" => {
+            let loader = metadata["loader_version"].as_str()?;
+            let latest = fabric_request::latest_stable_loader().await?;
+            is_newer(&latest, loader).then(|| {
+                Risk::OutdatedBuild(format!("Fabric loader {}", loader), latest)
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `0.16.10` is newer than `0.16.9`, versions that do not parse are never newer
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| {
+        version
+            .split(['+', '-'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()
+    };
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Every risk of exposing the server as it is configured
+pub async fn risks(server_path: &Path) -> Vec<Risk> {
+    let properties = ServerProperties::load(server_path);
+    let mut risks = vec![];
+    if !properties.get_bool("online-mode") && proxy_forwarding(server_path).is_none() {
+        risks.push(Risk::OfflineWithoutProxy);
+    }
+    if !properties.get_bool("white-list") {
+        risks.push(Risk::WhitelistDisabled);
+    }
+    if properties.get_bool("enable-rcon")
+        && is_weak_password(&properties.get_or_default("rcon.password").unwrap_or_default())
+    {
+        risks.push(Risk::WeakRconPassword);
+    }
+    risks.extend(newer_build(server_path).await);
+    risks
+}
+
+/// Checks the server before a tunnel opens, returns `false` when it must not be exposed
+pub async fn guard(server_path: &Path, force: bool) -> bool {
+    println!("🔎 Checking the server before exposing it publicly");
+    let mut risks = risks(server_path).await;
+    if risks.contains(&Risk::WhitelistDisabled) && offer_whitelist(server_path) {
+        risks.retain(|risk| *risk != Risk::WhitelistDisabled);
+    }
+    if risks.is_empty() {
+        println!("✅ Nothing to report");
+        return true;
+    }
+    for risk in &risks {
+        if risk.is_blocking() {
+            println!("❌ {}", risk.message());
+        } else {
+            println!("⚠️ {}", risk.message());
+        }
+    }
+    if !risks.iter().any(|risk| risk.is_blocking()) {
+        return true;
+    }
+    if force {
+        println!("⚠️ --force given, exposing the server anyway");
+        true
+    } else {
+        println!("❌ Not exposing the server, fix the issues above or use --force");
+        false
+    }
+}
+
+/// Asks to turn the whitelist on, `true` when it was
+fn offer_whitelist(server_path: &Path) -> bool {
+    // Detached and scripted starts have nobody to answer
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    let enable = Confirm::new("➡️ The whitelist is disabled, enable it now ?")
+        .with_default(true)
+        .with_help_message("Players are added with `MCT Players Whitelist add <Name>`")
+        .prompt()
+        .unwrap_or(false);
+    if !enable {
+        return false;
+    }
+    let values = [
+        ("white-list", String::from("true")),
+        ("enforce-whitelist", String::from("true")),
+    ];
+    match server_properties::apply(server_path, &values) {
+        Ok(_) => {
+            println!("✅ Whitelist enabled");
+            true
+        }
+        Err(e) => {
+            println!("❌ {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(is_newer("0.16.10", "0.16.9"));
+        assert!(is_newer("0.17.0", "0.16.14"));
+        assert!(is_newer("1.0.0", "0.16"));
+        assert!(!is_newer("0.16.9", "0.16.10"));
+        assert!(!is_newer("0.16.10", "0.16.10"));
+        assert!(!is_newer("0.16.10+build.1", "0.16.10"));
+        assert!(!is_newer("unknown", "0.16.10"));
+    }
+}
@@ -4,12 +4,15 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
 
-const FABRICMC_API_GAME_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/game";
-const FABRICMC_API_LOADER_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/loader";
-const FABRICMC_API_INSTALLER_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/installer";
-const FABRICMC_API_DOWNLOAD: &str = "https://meta.fabricmc.net/v2/versions/loader";
+use crate::session;
+use crate::settings;
+use crate::supervisor::{LaunchOptions, Supervisor};
+
+const FABRICMC_API_GAME_VERSIONS: &str = "/v2/versions/game";
+const FABRICMC_API_LOADER_VERSIONS: &str = "/v2/versions/loader";
+const FABRICMC_API_INSTALLER_VERSIONS: &str = "/v2/versions/installer";
+const FABRICMC_API_DOWNLOAD: &str = "/v2/versions/loader";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct GameVersion {
@@ -56,7 +59,7 @@ impl FabricMCRequest {
 
     /// Fetch available game versions
     async fn fetch_game_versions(&self) -> Result<Vec<GameVersion>, Error> {
-        let response = reqwest::get(FABRICMC_API_GAME_VERSIONS)
+        let response = reqwest::get(format!("{}{}", settings::api("fabric"), FABRICMC_API_GAME_VERSIONS))
             .await?
             .json::<Vec<GameVersion>>()
             .await?;
@@ -65,7 +68,7 @@ impl FabricMCRequest {
 
     /// Fetch available Fabric Loader versions
     async fn fetch_loader_versions(&self) -> Result<Vec<LoaderVersion>, Error> {
-        let response = reqwest::get(FABRICMC_API_LOADER_VERSIONS)
+        let response = reqwest::get(format!("{}{}", settings::api("fabric"), FABRICMC_API_LOADER_VERSIONS))
             .await?
             .json::<Vec<LoaderVersion>>()
             .await?;
@@ -74,7 +77,7 @@ impl FabricMCRequest {
 
     /// Fetch available installer versions
     async fn fetch_installer_versions(&self) -> Result<Vec<InstallerVersion>, Error> {
-        let response = reqwest::get(FABRICMC_API_INSTALLER_VERSIONS)
+        let response = reqwest::get(format!("{}{}", settings::api("fabric"), FABRICMC_API_INSTALLER_VERSIONS))
             .await?
             .json::<Vec<InstallerVersion>>()
             .await?;
@@ -89,9 +92,7 @@ impl FabricMCRequest {
             return;
         }
 
-        if game_version.is_some() {
-            let gv = game_version.unwrap();
-
+        if let Some(gv) = game_version {
             for entry in versions.iter() {
                 if gv == entry.version {
                     self.game_version = Some(entry.version.clone());
@@ -154,8 +155,12 @@ impl FabricMCRequest {
             &self.installer_version,
         ) {
             self.download_url = Some(format!(
-                "{}/{}/{}/{}/server/jar",
-                FABRICMC_API_DOWNLOAD, game, loader, installer
+                "{}{}/{}/{}/{}/server/jar",
+                settings::api("fabric"),
+                FABRICMC_API_DOWNLOAD,
+                game,
+                loader,
+                installer
             ));
             println!("\n🔗 Download URL: {}", self.download_url.as_ref().unwrap());
         } else {
@@ -173,25 +178,50 @@ impl FabricMCRequest {
     }
 
     /// Downloads the FabricMC server JAR
-    pub async fn download_build(&self, server_path: PathBuf) {
-        if let Some(url) = &self.download_url {
-            println!("⬇️ Downloading FabricMC Server...");
-            let response = reqwest::get(url).await;
+    pub async fn download_build(&self, server_path: PathBuf) -> Result<(), String> {
+        let url = self
+            .download_url
+            .as_ref()
+            .ok_or(String::from("No Fabric server to download"))?;
+        println!("⬇️ Downloading FabricMC Server...");
+        let content = reqwest::get(url)
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("Download failed: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Download failed: {}", e))?;
+        let jar_path = server_path.join("fabric-server.jar");
+        fs::write(&jar_path, content).map_err(|e| format!("Failed to save the JAR file: {}", e))?;
+        println!("✅ Downloaded: {}", jar_path.to_string_lossy());
+        Ok(())
+    }
 
-            match response {
-                Ok(res) => {
-                    let content = res.bytes().await.unwrap();
-                    let jar_path = server_path.join("fabric-server.jar");
-                    fs::write(&jar_path, content).expect("❌ Failed to save the JAR file");
-                    println!("✅ Downloaded: {}", jar_path.to_string_lossy());
-                }
-                Err(e) => println!("❌ Download failed: {}", e),
-            }
-        }
+    /// Installs the server of a given game and loader version without prompting, ex : for a modpack
+    pub async fn provision(
+        &mut self,
+        server_path: PathBuf,
+        game_version: String,
+        loader_version: String,
+    ) -> Result<(), String> {
+        self.server_path = Some(server_path.clone());
+        self.game_version = Some(game_version);
+        self.loader_version = Some(loader_version);
+        self.fetch_latest_installer_version().await;
+        self.generate_download_url();
+        self.download_build(server_path.clone()).await?;
+        session::write_metadata(&server_path, &self)
+            .map_err(|e| format!("Error while writting MCA.json : {}", e))
     }
 
     /// Starts the Fabric server
-    pub fn start_server(&self, xmx: Option<String>, xms: Option<String>, is_gui: Option<bool>) {
+    pub async fn start_server(
+        &self,
+        xmx: Option<String>,
+        xms: Option<String>,
+        is_gui: Option<bool>,
+        options: LaunchOptions,
+    ) {
         let mut java_args: Vec<String> = vec![];
         let mut startup_script = String::from("java ");
         match fs::write(
@@ -225,12 +255,7 @@ impl FabricMCRequest {
             }
         }
 
-        let data_path = self.server_path.clone().unwrap().join("MCA.json");
-        fs::write(
-            data_path,
-            serde_json::to_string_pretty(&self).unwrap().as_bytes(),
-        )
-        .unwrap();
+        session::write_metadata(self.server_path.as_ref().unwrap(), &self).unwrap();
 
         if let Some(xmx) = xmx {
             java_args.push(format!("-Xmx{}", xmx));
@@ -254,15 +279,10 @@ impl FabricMCRequest {
         fs::write(&path, startup_script).unwrap();
 
         println!("🚀 Starting Fabric Server...");
-        let _ = std::process::Command::new("java")
-            .args(&java_args)
-            .current_dir(self.server_path.as_ref().unwrap())
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .expect("❌ Failed to start server.")
-            .wait();
+        Supervisor::build(self.server_path.clone().unwrap(), java_args)
+            .with_options(options)
+            .run()
+            .await;
     }
 }
 
@@ -272,13 +292,7 @@ impl FabricMCRequest {
     }
     pub fn get_download_path(&self) -> Option<PathBuf> {
         if self.server_path.is_some() {
-            Some(
-                Path::new(&format!(
-                    "{}\\mods",
-                    self.server_path.clone().unwrap().to_string_lossy()
-                ))
-                .to_path_buf(),
-            )
+            Some(self.server_path.clone().unwrap().join("mods"))
         } else {
             None
         }
@@ -312,3 +326,12 @@ impl FabricMCRequest {
         }
     }
 }
+
+/// Newest stable Fabric loader version
+pub async fn latest_stable_loader() -> Option<String> {
+    let versions = FabricMCRequest::build(None).fetch_loader_versions().await.ok()?;
+    versions
+        .into_iter()
+        .find(|v| v.stable)
+        .map(|v| v.version)
+}
@@ -0,0 +1,213 @@
+use serde_json::Value;
+
+use crate::plugins::{Credentials, FileHash, Release};
+use crate::settings;
+
+/// Token of the GitHub API, needed for private repositories
+fn token() -> Option<String> {
+    settings::get("github.token").or(std::env::var("GITHUB_TOKEN").ok())
+}
+
+/// `*` and `?` wildcards, ex : `*-fabric-*.jar`
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and of the name when it was met, to backtrack
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Assets built for players rather than developers
+fn is_default_asset(name: &str) -> bool {
+    name.ends_with(".jar")
+        && !["-sources", "-javadoc", "-dev", "-api"]
+            .iter()
+            .any(|suffix| name.contains(suffix))
+}
+
+/// Asset of a release, with the tag as version, the latest release when no tag is given
+pub async fn release(
+    repository: &str,
+    tag: Option<&str>,
+    asset_pattern: Option<&str>,
+) -> Result<Release, String> {
+    if repository
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .count()
+        != 2
+    {
+        return Err(format!(
+            "'{}' is not a GitHub repository, ex : github:owner/repo",
+            repository
+        ));
+    }
+    let api = settings::api("github");
+    let url = match tag {
+        Some(tag) => format!("{}/repos/{}/releases/tags/{}", api, repository, tag),
+        None => format!("{}/repos/{}/releases/latest", api, repository),
+    };
+    let token = token();
+    let release = get_json(&url, token.as_deref()).await?;
+    let tag_name = release["tag_name"].as_str().unwrap_or_default().to_owned();
+    let assets = release["assets"].as_array().cloned().unwrap_or_default();
+    let matching: Vec<&Value> = assets
+        .iter()
+        .filter(|asset| {
+            let name = asset["name"].as_str().unwrap_or_default();
+            match asset_pattern {
+                Some(pattern) => glob_match(pattern, name),
+                None => is_default_asset(name),
+            }
+        })
+        .collect();
+    let asset = match matching.as_slice() {
+        [asset] => *asset,
+        [] => {
+            return Err(format!(
+                "No asset of {} {} matches {}, assets : {}",
+                repository,
+                tag_name,
+                asset_pattern.unwrap_or("*.jar"),
+                asset_names(&assets)
+            ))
+        }
+        _ => {
+            return Err(format!(
+                "Several assets of {} {} match, pick one with --asset <pattern> : {}",
+                repository,
+                tag_name,
+                asset_names(&matching.into_iter().cloned().collect::<Vec<Value>>())
+            ))
+        }
+    };
+
+    // Private assets are only served by the API, with the token
+    let (url, credentials) = match token {
+        Some(token) => (
+            asset["url"].as_str().unwrap_or_default().to_owned(),
+            Some(Credentials::Bearer(token)),
+        ),
+        None => (
+            asset["browser_download_url"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            None,
+        ),
+    };
+    Ok(Release {
+        project: repository.to_owned(),
+        name: repository.to_owned(),
+        version: tag_name,
+        file_name: asset["name"].as_str().unwrap_or_default().to_owned(),
+        url,
+        hash: asset["digest"].as_str().and_then(FileHash::parse),
+        dependencies: Vec::new(),
+        credentials,
+    })
+}
+
+fn asset_names(assets: &[Value]) -> String {
+    assets
+        .iter()
+        .filter_map(|asset| asset["name"].as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+async fn get_json(url: &str, token: Option<&str>) -> Result<Value, String> {
+    let mut request = reqwest::Client::new()
+        .get(url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("MCT/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed : {}", e))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!(
+            "{} not found, private repositories need the github.token setting",
+            url
+        ));
+    }
+    response
+        .error_for_status()
+        .map_err(|e| format!("Request failed : {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {} : {}", url, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_match("*-fabric-*.jar", "sodium-fabric-0.6.0+mc1.21.jar"));
+        assert!(glob_match("*-fabric-*.jar", "a-fabric-.jar"));
+        assert!(!glob_match("*-fabric-*.jar", "sodium-neoforge-0.6.0.jar"));
+        assert!(!glob_match("*-fabric-*.jar", "sodium-fabric-0.6.0.jar.sha256"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_char() {
+        assert!(glob_match("mod-1.?.jar", "mod-1.2.jar"));
+        assert!(!glob_match("mod-1.?.jar", "mod-1.jar"));
+        assert!(!glob_match("mod-1.?.jar", "mod-1.10.jar"));
+    }
+
+    #[test]
+    fn trailing_star() {
+        assert!(glob_match("sodium-*", "sodium-fabric.jar"));
+        assert!(glob_match("sodium-*", "sodium-"));
+        assert!(glob_match("sodium**", "sodium"));
+        assert!(!glob_match("sodium-*", "lithium-fabric.jar"));
+    }
+
+    #[test]
+    fn several_stars_backtrack() {
+        assert!(glob_match("*a*b*c", "xxaxxbxxc"));
+        assert!(glob_match("*a*b*c", "abcabc"));
+        assert!(!glob_match("*a*b*c", "xxaxxcxxb"));
+        assert!(!glob_match("*-api-*-dev.jar", "mod-api-1.0.jar"));
+    }
+
+    #[test]
+    fn without_wildcards_names_must_be_equal() {
+        assert!(glob_match("mod.jar", "mod.jar"));
+        assert!(!glob_match("mod.jar", "mod.jar.asc"));
+        assert!(!glob_match("", "mod.jar"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn default_assets_skip_developer_jars() {
+        assert!(is_default_asset("mod-1.0.jar"));
+        assert!(!is_default_asset("mod-1.0-sources.jar"));
+        assert!(!is_default_asset("mod-1.0.zip"));
+    }
+}
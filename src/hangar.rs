@@ -0,0 +1,171 @@
+use serde_json::{json, Value};
+
+use crate::plugins::{self, Dependency, FileHash, Release};
+use crate::settings;
+
+/// Hangar platforms, given as `PAPER` to the API
+pub const PLATFORMS: [&str; 3] = ["paper", "velocity", "waterfall"];
+
+fn endpoint(path: &str) -> String {
+    format!("{}/api/v1{}", settings::api("hangar"), path)
+}
+
+/// Hangar platform running the plugins of an MCT server platform
+pub fn platform_of(server_platform: &str) -> &'static str {
+    match server_platform.to_lowercase().as_str() {
+        "velocity" => "VELOCITY",
+        "waterfall" | "bungeecord" => "WATERFALL",
+        // Purpur and Folia load Paper plugins
+        _ => "PAPER",
+    }
+}
+
+/// Hangar sort of a Modrinth sorting ex : `downloads` gives `-downloads`
+pub fn sort_of(sorting: &str) -> Option<&'static str> {
+    match sorting {
+        "downloads" => Some("-downloads"),
+        "follows" => Some("-stars"),
+        "newest" => Some("-newest"),
+        "updated" => Some("-updated"),
+        _ => None,
+    }
+}
+
+/// Projects matching `query`, as hits with the fields of the Modrinth search
+pub async fn search(
+    query: &str,
+    platform: &str,
+    game_version: Option<&str>,
+    limit: usize,
+    offset: usize,
+    sort: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let platform = platform.to_uppercase();
+    let mut parameters = vec![
+        ("q", query.to_owned()),
+        ("limit", limit.to_string()),
+        ("offset", offset.to_string()),
+        ("platform", platform.clone()),
+    ];
+    if let Some(game_version) = game_version {
+        parameters.push(("version", game_version.to_owned()));
+    }
+    if let Some(sort) = sort {
+        parameters.push(("sort", sort.to_owned()));
+    }
+    let data = plugins::get_json(&endpoint("/projects"), &parameters).await?;
+    Ok(data["result"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|project| {
+            json!({
+                "project_id": project["namespace"]["slug"],
+                "title": project["name"],
+                "author": project["namespace"]["owner"],
+                "project_type": "plugin",
+                "versions": project["supportedPlatforms"][&platform],
+                "description": project["description"],
+            })
+        })
+        .collect())
+}
+
+/// Given version of a project, or its newest one for the platform and game version, releases first
+pub async fn release(
+    slug: &str,
+    version: Option<&str>,
+    platform: &str,
+    game_version: Option<&str>,
+) -> Result<Release, String> {
+    let platform = platform.to_uppercase();
+    let project = plugins::get_json(&endpoint(&format!("/projects/{}", slug)), &[]).await?;
+    let slug = project["namespace"]["slug"]
+        .as_str()
+        .unwrap_or(slug)
+        .to_owned();
+    let name = project["name"].as_str().unwrap_or(&slug).to_owned();
+    let version = match version {
+        Some(version) => {
+            plugins::get_json(
+                &endpoint(&format!("/projects/{}/versions/{}", slug, version)),
+                &[],
+            )
+            .await?
+        }
+        None => {
+            let mut parameters = vec![
+                ("platform", platform.clone()),
+                ("limit", String::from("25")),
+            ];
+            if let Some(game_version) = game_version {
+                parameters.push(("platformVersion", game_version.to_owned()));
+            }
+            let versions = plugins::get_json(
+                &endpoint(&format!("/projects/{}/versions", slug)),
+                &parameters,
+            )
+            .await?;
+            let versions = versions["result"].as_array().cloned().unwrap_or_default();
+            versions
+                .iter()
+                .find(|version| version["channel"]["name"] == "Release")
+                .or(versions.first())
+                .cloned()
+                .ok_or(format!(
+                    "No version of {} for {} {}",
+                    name,
+                    platform,
+                    game_version.unwrap_or_default()
+                ))?
+        }
+    };
+    let version_name = version["name"].as_str().unwrap_or_default().to_owned();
+    let download = &version["downloads"][&platform];
+    if download.is_null() {
+        return Err(format!(
+            "{} {} has no {} download",
+            name, version_name, platform
+        ));
+    }
+    let Some(url) = download["downloadUrl"].as_str() else {
+        return Err(format!(
+            "{} {} is only available from {}, download it manually",
+            name,
+            version_name,
+            download["externalUrl"]
+                .as_str()
+                .unwrap_or("an external site")
+        ));
+    };
+    let dependencies = version["pluginDependencies"][&platform]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .filter(|dependency| dependency["required"].as_bool() == Some(true))
+        .filter_map(|dependency| {
+            let name = dependency["name"].as_str()?.to_owned();
+            Some(match dependency["externalUrl"].as_str() {
+                Some(url) => Dependency::External(format!("{} ({})", name, url)),
+                None => Dependency::Project(name, None),
+            })
+        })
+        .collect();
+    Ok(Release {
+        project: slug,
+        name,
+        version: version_name,
+        file_name: download["fileInfo"]["name"]
+            .as_str()
+            .map(|name| name.to_owned())
+            .unwrap_or_else(|| url.rsplit('/').next().unwrap_or_default().to_owned()),
+        url: url.to_owned(),
+        hash: download["fileInfo"]["sha256Hash"]
+            .as_str()
+            .map(|hash| FileHash::Sha256(hash.to_owned())),
+        dependencies,
+        credentials: None,
+    })
+}
@@ -0,0 +1,153 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::server_properties::ServerProperties;
+use crate::server_status::{self, StatusTarget};
+
+/// Group and port listened to by the "LAN Worlds" list of the clients
+const LAN_GROUP: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(224, 0, 2, 60)), 4445);
+/// Same pace as an integrated server opened to LAN
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Packet of a LAN world, clients join the address it came from on `port`
+fn lan_packet(motd: &str, port: u16) -> Vec<u8> {
+    format!("[MOTD]{}[/MOTD][AD]{}[/AD]", motd, port).into_bytes()
+}
+
+/// Announces a port of this machine to the LAN until closed
+pub struct LanAnnouncement {
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl LanAnnouncement {
+    pub async fn start(motd: String, port: u16) -> Result<Self, String> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .map_err(|e| format!("Unable to open the LAN announcement socket : {}", e))?;
+        // The LAN Worlds list is only meant for the local network
+        let _ = socket.set_multicast_ttl_v4(1);
+        let packet = lan_packet(&motd, port);
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut warned = false;
+            loop {
+                if let Err(e) = socket.send_to(&packet, LAN_GROUP).await {
+                    if !warned {
+                        println!("⚠️ LAN announcement failed : {}", e);
+                        warned = true;
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(ANNOUNCE_INTERVAL) => {}
+                    _ = &mut stopped => return,
+                }
+            }
+        });
+        println!("🌐 Announcing '{}' on port {} to the LAN", motd, port);
+        Ok(Self {
+            stop: Some(stop),
+            task,
+        })
+    }
+
+    /// Announces a server launched by MCT with the MOTD of its server.properties
+    pub async fn for_server(server_path: &Path, port: u16) -> Result<Self, String> {
+        let motd = ServerProperties::load(server_path)
+            .get_or_default("motd")
+            .map(|motd| server_status::strip_formatting(&motd))
+            .filter(|motd| !motd.is_empty())
+            .unwrap_or(String::from("A Minecraft Server"));
+        Self::start(motd, port).await
+    }
+
+    pub async fn close(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.task).await;
+        println!("➡️ LAN announcement stopped");
+    }
+}
+
+/// `MCT Lan Announce <target>` : a local server is announced as is, a remote one
+/// through a relay on `relay_port` since clients join the address announcing it
+pub async fn announce(target: &str, motd: Option<String>, relay_port: u16) {
+    let status_target = StatusTarget::resolve(target).await;
+    let motd = match motd {
+        Some(motd) => motd,
+        None => match server_status::ping(&status_target).await {
+            Ok(status) => server_status::strip_formatting(&status.motd)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned(),
+            Err(e) => {
+                println!("⚠️ {} did not answer the ping : {}", target, e);
+                target.to_owned()
+            }
+        },
+    };
+
+    let (port, relay) = if Path::new(target).is_dir() {
+        (status_target.port, None)
+    } else {
+        let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, relay_port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                println!("❌ Unable to listen on port {} : {}", relay_port, e);
+                return;
+            }
+        };
+        let port = listener
+            .local_addr()
+            .map(|a| a.port())
+            .unwrap_or(relay_port);
+        println!(
+            "➡️ Relaying port {} to {}",
+            port, status_target.connect_address
+        );
+        (
+            port,
+            Some(tokio::spawn(relay(listener, status_target.connect_address))),
+        )
+    };
+
+    let announcement = match LanAnnouncement::start(motd, port).await {
+        Ok(announcement) => announcement,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    println!("➡️ Ctrl-C to stop");
+    let _ = tokio::signal::ctrl_c().await;
+    announcement.close().await;
+    if let Some(relay) = relay {
+        relay.abort();
+    }
+}
+
+/// Forwards every connection to `address`
+async fn relay(listener: TcpListener, address: String) {
+    while let Ok((mut client, _)) = listener.accept().await {
+        let address = address.clone();
+        tokio::spawn(async move {
+            match TcpStream::connect(&address).await {
+                Ok(mut server) => {
+                    let _ = client.set_nodelay(true);
+                    let _ = server.set_nodelay(true);
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                }
+                Err(e) => println!("⚠️ Unable to reach {} : {}", address, e),
+            }
+        });
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::NaiveTime;
+use regex::Regex;
+
+pub const LATEST_LOG: &str = "logs/latest.log";
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+/// `[12:34:56] [Server thread/INFO]: ...` (vanilla, Paper) and `[12:34:56] [main/WARN] (Fabric) ...`
+const HEADER_PATTERN: &str = r"^\[(\d{2}:\d{2}:\d{2})(?:\.\d+)?\] \[[^\]]*/([A-Z]+)\]";
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.to_ascii_uppercase().as_str() {
+            "TRACE" => Some(Level::Trace),
+            "DEBUG" => Some(Level::Debug),
+            "INFO" => Some(Level::Info),
+            "WARN" | "WARNING" => Some(Level::Warn),
+            "ERROR" => Some(Level::Error),
+            "FATAL" => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// One log record, stack traces included
+#[derive(Debug, Clone)]
+struct Entry {
+    time: Option<NaiveTime>,
+    level: Option<Level>,
+    lines: Vec<String>,
+}
+
+/// Which entries are printed
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Minimum level
+    pub level: Option<Level>,
+    pub since: Option<NaiveTime>,
+    pub until: Option<NaiveTime>,
+    pub pattern: Option<Regex>,
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &Entry) -> bool {
+        if let Some(level) = self.level {
+            if entry.level.is_none_or(|l| l < level) {
+                return false;
+            }
+        }
+        if let (Some(since), Some(time)) = (self.since, entry.time) {
+            if time < since {
+                return false;
+            }
+        }
+        if let (Some(until), Some(time)) = (self.until, entry.time) {
+            if time > until {
+                return false;
+            }
+        }
+        match &self.pattern {
+            Some(pattern) => entry.lines.iter().any(|line| pattern.is_match(line)),
+            None => true,
+        }
+    }
+}
+
+/// `HH:MM` or `HH:MM:SS`
+pub fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM or HH:MM:SS", time))
+}
+
+/// Groups lines in entries, lines without a header belong to the previous entry
+struct EntryParser {
+    header: Regex,
+    current: Option<Entry>,
+}
+
+impl EntryParser {
+    fn build() -> Self {
+        Self {
+            header: Regex::new(HEADER_PATTERN).unwrap(),
+            current: None,
+        }
+    }
+
+    /// Returns the previous entry when `line` starts a new one
+    fn push(&mut self, line: &str) -> Option<Entry> {
+        match self.header.captures(line) {
+            Some(captures) => {
+                let entry = Entry {
+                    time: NaiveTime::parse_from_str(&captures[1], "%H:%M:%S").ok(),
+                    level: Level::parse(&captures[2]),
+                    lines: vec![line.to_owned()],
+                };
+                self.current.replace(entry)
+            }
+            None => {
+                match &mut self.current {
+                    Some(entry) => entry.lines.push(line.to_owned()),
+                    None => {
+                        self.current = Some(Entry {
+                            time: None,
+                            level: None,
+                            lines: vec![line.to_owned()],
+                        })
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Option<Entry> {
+        self.current.take()
+    }
+}
+
+fn print_entry(entry: &Entry) {
+    for line in &entry.lines {
+        println!("{}", line);
+    }
+}
+
+/// Prints the last `lines` matching entries of latest.log, then the new ones when following
+pub async fn show(server_path: &Path, filter: &LogFilter, lines: usize, follow: bool) {
+    let path = server_path.join(LATEST_LOG);
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("❌ Unable to read '{}' : {}", path.to_string_lossy(), e);
+            return;
+        }
+    };
+    let mut parser = EntryParser::build();
+    let mut entries: Vec<Entry> = vec![];
+    for line in String::from_utf8_lossy(&content).lines() {
+        entries.extend(parser.push(line));
+    }
+    entries.extend(parser.finish());
+    let matching: Vec<&Entry> = entries.iter().filter(|e| filter.matches(e)).collect();
+    for entry in &matching[matching.len().saturating_sub(lines)..] {
+        print_entry(entry);
+    }
+    if follow {
+        follow_log(path, content.len() as u64, filter).await;
+    }
+}
+
+/// Polls the log for new lines, starting over when the server rotates it
+async fn follow_log(path: PathBuf, mut offset: u64, filter: &LogFilter) {
+    let mut parser = EntryParser::build();
+    let mut pending = String::new();
+    loop {
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+        let Ok(mut file) = fs::File::open(&path) else {
+            continue;
+        };
+        let length = file.metadata().map(|m| m.len()).unwrap_or_default();
+        if length < offset {
+            offset = 0;
+            pending.clear();
+        }
+        if length == offset || file.seek(SeekFrom::Start(offset)).is_err() {
+            // Nothing new, an entry without continuation is complete by now
+            if let Some(entry) = parser.finish() {
+                if filter.matches(&entry) {
+                    print_entry(&entry);
+                }
+            }
+            continue;
+        }
+        let mut chunk = vec![];
+        if file.read_to_end(&mut chunk).is_err() {
+            continue;
+        }
+        offset += chunk.len() as u64;
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+        // Keep an incomplete last line for the next read
+        let complete = match pending.rfind('\n') {
+            Some(end) => pending.drain(..=end).collect::<String>(),
+            None => continue,
+        };
+        for line in complete.lines() {
+            if let Some(entry) = parser.push(line) {
+                if filter.matches(&entry) {
+                    print_entry(&entry);
+                }
+            }
+        }
+    }
+}
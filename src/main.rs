@@ -1,13 +1,49 @@
+mod backup;
+mod bore;
+mod content;
+mod crash;
+mod curseforge;
+mod dns;
+mod exposure;
 mod fabric_request;
+mod github;
+mod hangar;
+mod lan;
+mod logs;
+mod maven;
 mod modrinth_request;
+mod mc_protocol;
+mod on_demand;
 mod papermc_request;
+mod players;
+mod plugins;
+mod query;
+mod rcon;
+mod registry;
+mod router;
+mod server_properties;
+mod server_status;
+mod session;
+mod settings;
+mod shutdown;
+mod spiget;
+mod supervisor;
+mod tunnel;
+mod upnp;
 use clap::{Arg, Command};
 use fabric_request::FabricMCRequest;
 use modrinth_request::{
     ClientSide, ModLoaders, ModQuery, ModrinthEntry, ModrinthSortingFilter, ProjectType, ServerSide,
 };
+use on_demand::OnDemand;
 use papermc_request::PaperMCRequest;
+use players::{EntryOptions, PlayerList};
+use plugins::{ContentInstaller, FileSelection, PluginSource};
 use reqwest::Error;
+use router::{Route, Router};
+use shutdown::RestartSchedule;
+use supervisor::{ConsoleMode, LaunchOptions, RestartPolicy};
+use tunnel::TunnelProvider;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -82,6 +118,19 @@ async fn main() -> Result<(), Error> {
                         .aliases(["filter", "sort"])
                         .visible_aliases(["filter", "sort"])
                         .help("Sort results by relevance|downloads|follows|newest|updated")
+                        .value_parser(|s: &str| {
+                            ModrinthSortingFilter::with(Some(s))
+                                .ok_or("expected relevance|downloads|follows|newest|updated")
+                        })
+                        .required(false),
+                )
+                .arg(source_arg())
+                .arg(
+                    Arg::new("Platform")
+                        .long("platform")
+                        .value_parser(hangar::PLATFORMS)
+                        .ignore_case(true)
+                        .help("Plugin platform of the Hangar results, default value : paper\nex : paper | velocity | waterfall")
                         .required(false),
                 )
                 .subcommand(
@@ -164,8 +213,27 @@ async fn main() -> Result<(), Error> {
                 ).arg(Arg::new("For_Server")
                     .long("for_server")
                     .short('f').visible_short_alias('f')
-                    .help("Download mod for fabric server, provide server root directory to this ar&gument")
-                    .required(false)),
+                    .help("Download a mod for a Fabric server or a plugin for a Paper server, provide the server root directory to this argument")
+                    .required(false))
+                .arg(source_arg())
+                .arg(
+                    Arg::new("Tag")
+                        .long("tag")
+                        .help("Release tag of a github:owner/repo entry, default value : the latest release")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("Asset")
+                        .long("asset")
+                        .help("Release asset of a github:owner/repo entry, * and ? wildcards allowed\nex : *-fabric-*.jar")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("Hash")
+                        .long("hash")
+                        .help("Expected hash of a direct URL entry\nex : sha1:<hex> | sha256:<hex> | sha512:<hex>")
+                        .required(false),
+                ),
         ).subcommand(Command::new("Create_Server")
             .short_flag('c')
             .about("Create a directory with a Minecraft server")
@@ -221,8 +289,447 @@ async fn main() -> Result<(), Error> {
                     .aliases(["pip","broadcastip","p_ip","bcast"])
                     .visible_aliases(["pip","broadcastip","p_ip","bcast"])
                     .value_parser(clap::value_parser!(bool))
-                    .help("Broadcast your server with a network tunnel using a service depending on the online services (Serveo, Bore.pub, Tunnelto, Ownserver,...) ex : True | False")
+                    .help("Broadcast your server with a network tunnel, same as --tunnel bore ex : True | False")
+                    .required(false))
+            .arg(
+                Arg::new("Rcon")
+                    .long("rcon")
+                    .value_parser(clap::value_parser!(bool))
+                    .help("Enable RCON in server.properties with a generated password ex : true | false")
+                    .required(false))
+            .args(server_settings_args())
+            .arg(
+                Arg::new("Query_Port")
+                    .long("query")
+                    .value_parser(clap::value_parser!(u16))
+                    .help("Enable the query protocol in server.properties on the given port ex : 25565")
+                    .required(false))
+            .arg(
+                Arg::new("Name")
+                    .long("name")
+                    .short('n')
+                    .help("Register the server under this name, see Servers List")
+                    .required(false))
+            .args(supervisor_args())
+            .args(tunnel_args()))
+        .subcommand(Command::new("Servers")
+            .alias("servers")
+            .about("Named servers, usable with --server <Name> instead of a path")
+            .subcommand(Command::new("List")
+                .alias("list")
+                .about("List the registered servers with their platform, version, port and state"))
+            .subcommand(Command::new("Add")
+                .alias("add")
+                .about("Register a server directory under a name")
+                .arg(Arg::new("Name").help("ex : survival").required(true))
+                .arg(Arg::new("Path").help("Server path Directory").required(true)))
+            .subcommand(Command::new("Remove")
+                .alias("remove")
+                .about("Forget a server, its files are kept")
+                .arg(Arg::new("Name").help("ex : survival").required(true))))
+        .subcommand(Command::new("Router")
+            .alias("router")
+            .about("Share one public port between registered servers, routed by the hostname players connect to")
+            .subcommand(Command::new("Start")
+                .alias("start")
+                .about("Accept players and forward them to the server routed for their hostname")
+                .arg(
+                    Arg::new("Port")
+                        .long("port")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("25565")
+                        .help("Public port of the router"))
+                .arg(
+                    Arg::new("Accept_Proxy_Protocol")
+                        .long("accept_proxy_protocol")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Read the player address from the PROXY protocol header of a load balancer in front of the router")))
+            .subcommand(Command::new("List")
+                .alias("list")
+                .about("List the routes"))
+            .subcommand(Command::new("Add")
+                .alias("add")
+                .about("Route a hostname to a registered server, replacing its previous route")
+                .arg(Arg::new("Hostname").help("ex : survival.example.com | *.example.com | * for the default backend").required(true))
+                .arg(Arg::new("Name").help("Registered server ex : survival").required(true))
+                .arg(
+                    Arg::new("Motd")
+                        .long("motd")
+                        .help("MOTD answered by the router for this hostname instead of the server's")
+                        .required(false))
+                .arg(
+                    Arg::new("Proxy_Protocol")
+                        .long("proxy_protocol")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Send a PROXY protocol v2 header, the server must have proxy-protocol enabled")))
+            .subcommand(Command::new("Remove")
+                .alias("remove")
+                .about("Remove the route of a hostname")
+                .arg(Arg::new("Hostname").help("ex : survival.example.com").required(true))))
+        .subcommand(Command::new("Lan")
+            .alias("lan")
+            .about("Show servers in the LAN Worlds list of players on the local network")
+            .subcommand(Command::new("Announce")
+                .alias("announce")
+                .about("Announce a server to the LAN until Ctrl-C, remote servers are relayed through this machine")
+                .arg(
+                    Arg::new("Target")
+                        .help("Server path Directory or host:port ex : play.example.com:25565")
+                        .conflicts_with("Server")
+                        .required_unless_present("Server"))
+                .arg(server_arg())
+                .arg(
+                    Arg::new("Motd")
+                        .long("motd")
+                        .help("Name shown in the LAN Worlds list, default value : the MOTD of the server")
+                        .required(false))
+                .arg(
+                    Arg::new("Port")
+                        .long("port")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("0")
+                        .help("Local port relaying a remote server, 0 picks a free one"))))
+        .subcommand(Command::new("Modpack")
+            .alias("modpack")
+            .about("Create servers from modpacks")
+            .subcommand(Command::new("Import")
+                .alias("import")
+                .about("Create a server from a CurseForge pack zip : loader, mods and overrides")
+                .arg(
+                    Arg::new("Pack")
+                        .help("CurseForge pack zip ex : MyPack-1.0.zip")
+                        .required(true))
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory, created if needed, default value : server.path")
+                        .required(false))
+                .arg(
+                    Arg::new("Name")
+                        .long("name")
+                        .short('n')
+                        .help("Register the server under this name, see Servers List")
+                        .required(false))))
+        .subcommand(Command::new("Rcon")
+            .alias("rcon")
+            .about("Run a command on a server with RCON, opens a shell when no command is given")
+            .arg(
+                Arg::new("Target")
+                    .help("Server path Directory or host:port, omitted with --server")
+                    .required_unless_present("Server"))
+            .arg(
+                Arg::new("Command")
+                    .help("Console command ex : \"say hello\"")
+                    .conflicts_with("Server"))
+            .arg(server_arg())
+            .arg(
+                Arg::new("Password")
+                    .long("password")
+                    .short('P')
+                    .help("RCON password, read from server.properties when a directory is given")
                     .required(false)))
+        .subcommand(Command::new("Status")
+            .alias("status")
+            .about("Ping a server and show its version, MOTD, players and latency")
+            .arg(
+                Arg::new("Target")
+                    .help("host[:port] or Server path Directory")
+                    .conflicts_with("Server")
+                    .required_unless_present("Server"))
+            .arg(server_arg())
+            .arg(
+                Arg::new("Json")
+                    .long("json")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print the status as JSON")))
+        .subcommand(Command::new("Query")
+            .alias("query")
+            .about("Query a server with the GameSpy4 protocol to list players, plugins and map")
+            .arg(
+                Arg::new("Target")
+                    .help("host[:port] or Server path Directory")
+                    .conflicts_with("Server")
+                    .required_unless_present("Server"))
+            .arg(server_arg())
+            .arg(
+                Arg::new("Basic")
+                    .long("basic")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Only request the basic stat"))
+            .arg(
+                Arg::new("Json")
+                    .long("json")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print the answer as JSON")))
+        .subcommand(Command::new("Config")
+            .alias("config")
+            .arg(server_arg().global(true))
+            .about("Read and edit the server.properties of a server, comments and order are kept, or show the MCT settings")
+            .subcommand(Command::new("Get")
+                .alias("get")
+                .about("Print the value of a key")
+                .arg(dir_arg().required(true))
+                .arg(Arg::new("Key").help("ex : motd").required_unless_present("Server").conflicts_with("Server")))
+            .subcommand(Command::new("Set")
+                .alias("set")
+                .about("Set the value of a key, known keys are validated")
+                .arg(dir_arg().required(true))
+                .arg(Arg::new("Key").help("ex : motd").required(true))
+                .arg(Arg::new("Value").help("ex : \"My server\"").required_unless_present("Server").conflicts_with("Server")))
+            .subcommand(Command::new("List")
+                .alias("list")
+                .about("List every key with its type, defaults included")
+                .arg(Arg::new("Path").help("Server path Directory").required_unless_present("Server")))
+            .subcommand(Command::new("Show")
+                .alias("show")
+                .about("Show the effective MCT settings and where they come from (defaults, config.toml, mct.toml, MCT_* env vars)")))
+        .subcommand(Command::new("Server")
+            .alias("server")
+            .arg(server_arg().global(true))
+            .about("Start and control an existing server directory")
+            .subcommand(Command::new("Start")
+                .alias("start")
+                .about("Start the server found in the directory")
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory")
+                        .required(false))
+                .arg(
+                    Arg::new("Gui")
+                        .long("Gui")
+                        .short('g')
+                        .value_parser(clap::value_parser!(bool))
+                        .help("Shows the server graphic user interface ex : true | false")
+                        .required(false))
+                .arg(
+                    Arg::new("Max_Ram")
+                        .long("max_ram")
+                        .alias("Xmx")
+                        .visible_alias("Xmx")
+                        .help("Max Amount of ram ex: 1024k | 512m | 8g")
+                        .required(false))
+                .arg(
+                    Arg::new("Min_Ram")
+                        .long("min_ram")
+                        .alias("Xms")
+                        .visible_alias("Xms")
+                        .help("Initial amount of ram ex: 1024k | 512m | 8g")
+                        .required(false))
+                .arg(
+                    Arg::new("Detach")
+                        .long("detach")
+                        .short('d')
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Run the server in the background, use Attach or Send to reach its console"))
+                .arg(
+                    Arg::new("Daemon")
+                        .long("daemon")
+                        .action(clap::ArgAction::SetTrue)
+                        .hide(true))
+                .args(supervisor_args())
+                .args(tunnel_args()))
+            .subcommand(Command::new("Sleep")
+                .alias("sleep")
+                .about("Answer on the server port while the server is stopped, start it when a player joins and stop it when nobody plays")
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory")
+                        .required(false))
+                .arg(
+                    Arg::new("Motd")
+                        .long("motd")
+                        .help("Message shown in the server list while the server sleeps")
+                        .required(false))
+                .arg(
+                    Arg::new("Idle")
+                        .long("idle")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("10")
+                        .help("Minutes without players before the server is stopped"))
+                .arg(
+                    Arg::new("Backend_Port")
+                        .long("backend_port")
+                        .value_parser(clap::value_parser!(u16))
+                        .help("Port the server listens on behind MCT, default value : the server port + 1")
+                        .required(false))
+                .arg(
+                    Arg::new("Proxy_Protocol")
+                        .long("proxy_protocol")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Send a PROXY protocol v2 header so the server sees the address of the players instead of 127.0.0.1, the server must have proxy-protocol enabled"))
+                .arg(
+                    Arg::new("Max_Ram")
+                        .long("max_ram")
+                        .alias("Xmx")
+                        .visible_alias("Xmx")
+                        .help("Max Amount of ram ex: 1024k | 512m | 8g")
+                        .required(false))
+                .arg(
+                    Arg::new("Min_Ram")
+                        .long("min_ram")
+                        .alias("Xms")
+                        .visible_alias("Xms")
+                        .help("Initial amount of ram ex: 1024k | 512m | 8g")
+                        .required(false))
+                .args(supervisor_args()))
+            .subcommand(Command::new("Attach")
+                .alias("attach")
+                .about("Attach to the console of a detached server, Ctrl-D to detach")
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory")
+                        .required(false)))
+            .subcommand(Command::new("Send")
+                .alias("send")
+                .about("Send one command to the console of a detached server")
+                .arg(dir_arg().required(true))
+                .arg(
+                    Arg::new("Command")
+                        .help("Console command ex : \"say hello\"")
+                        .required_unless_present("Server")
+                        .conflicts_with("Server")))
+            .subcommand(Command::new("Stop")
+                .alias("stop")
+                .about("Warn the players, save and stop a running server, killing it if it hangs")
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory")
+                        .required(false))
+                .arg(
+                    Arg::new("Countdown")
+                        .long("countdown")
+                        .short('c')
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("0")
+                        .help("Seconds of countdown broadcast to the players before stopping"))
+                .arg(
+                    Arg::new("Timeout")
+                        .long("timeout")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("60")
+                        .help("Seconds to wait for the server to exit before killing it")))
+            .subcommand(Command::new("Schedule")
+                .alias("schedule")
+                .about("Show or set the daily restart of a server, applied on its next start")
+                .arg(
+                    Arg::new("Path")
+                        .help("Server path Directory")
+                        .required(false))
+                .arg(
+                    Arg::new("Daily")
+                        .long("daily")
+                        .help("Local time of the restart ex : 04:00"))
+                .arg(
+                    Arg::new("Warnings")
+                        .long("warnings")
+                        .requires("Daily")
+                        .help("Seconds before the restart at which players are warned ex : 600,300,60,10"))
+                .arg(
+                    Arg::new("Disable")
+                        .long("disable")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("Daily")
+                        .help("Remove the daily restart"))))
+        .subcommand(Command::new("Backup")
+            .alias("backup")
+            .arg(server_arg().global(true))
+            .about("Archive the worlds and configs of a server in <Path>/backups")
+            .subcommand(Command::new("Create")
+                .alias("create")
+                .about("Create a backup, saving is paused meanwhile when the server is running")
+                .arg(Arg::new("Path").help("Server path Directory").required_unless_present("Server"))
+                .args(retention_args()))
+            .subcommand(Command::new("List")
+                .alias("list")
+                .about("List the backups, newest first")
+                .arg(Arg::new("Path").help("Server path Directory").required_unless_present("Server")))
+            .subcommand(Command::new("Restore")
+                .alias("restore")
+                .about("Replace the worlds and configs by a backup, the server must be stopped")
+                .arg(Arg::new("Path").help("Server path Directory").required_unless_present("Server"))
+                .arg(Arg::new("Backup").help("Backup name or \"latest\"").default_value("latest")))
+            .subcommand(Command::new("Prune")
+                .alias("prune")
+                .about("Remove the backups not kept by the retention policy, default keeps the last 7, 7 daily and 4 weekly")
+                .arg(Arg::new("Path").help("Server path Directory").required_unless_present("Server"))
+                .args(retention_args())))
+        .subcommand(Command::new("Logs")
+            .alias("logs")
+            .arg(server_arg())
+            .about("Print the end of logs/latest.log, filtered by level, time or regex")
+            .arg(
+                Arg::new("Path")
+                    .help("Server path Directory")
+                    .required(false))
+            .arg(
+                Arg::new("Level")
+                    .long("level")
+                    .short('l')
+                    .value_parser(["trace", "debug", "info", "warn", "error", "fatal"])
+                    .ignore_case(true)
+                    .help("Minimum level ex : warn"))
+            .arg(
+                Arg::new("Since")
+                    .long("since")
+                    .help("Only entries logged from this time ex : 14:30"))
+            .arg(
+                Arg::new("Until")
+                    .long("until")
+                    .help("Only entries logged up to this time ex : 15:00:30"))
+            .arg(
+                Arg::new("Grep")
+                    .long("grep")
+                    .short('e')
+                    .help("Only entries matching this regex ex : \"(?i)exception\""))
+            .arg(
+                Arg::new("Lines")
+                    .long("lines")
+                    .short('n')
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("50")
+                    .help("Number of entries printed"))
+            .arg(
+                Arg::new("Follow")
+                    .long("follow")
+                    .short('f')
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Keep printing new entries")))
+        .subcommand(Command::new("Crash")
+            .alias("crash")
+            .arg(server_arg())
+            .about("Read the newest crash report or hs_err file and find the mod or plugin to blame")
+            .arg(
+                Arg::new("Path")
+                    .help("Server path Directory")
+                    .required(false))
+            .arg(
+                Arg::new("File")
+                    .long("file")
+                    .help("Crash report to read instead of the newest one")))
+        .subcommand(Command::new("Players")
+            .alias("players")
+            .arg(server_arg().global(true))
+            .about("Manage the whitelist, ops and ban lists, through the console when the server is running")
+            .subcommand(player_list_command("Whitelist", "Players allowed to join when the whitelist is on")
+                .alias("whitelist"))
+            .subcommand(player_list_command("Op", "Server operators")
+                .alias("op")
+                .arg(
+                    Arg::new("Level")
+                        .long("level")
+                        .value_parser(clap::value_parser!(u8).range(1..=4))
+                        .help("Permission level from 1 to 4, default 4")))
+            .subcommand(player_list_command("Ban", "Banned players")
+                .alias("ban")
+                .arg(
+                    Arg::new("Reason")
+                        .long("reason")
+                        .help("ex : \"Griefing\"")))
+            .subcommand(player_list_command("Ban_Ip", "Banned IP addresses")
+                .alias("ban-ip")
+                .arg(
+                    Arg::new("Reason")
+                        .long("reason")
+                        .help("ex : \"Griefing\""))))
         .get_matches();
 
     match commands.subcommand() {
@@ -241,15 +748,64 @@ async fn main() -> Result<(), Error> {
                 server_side = args.get_one::<ServerSide>("Server_Side");
             }
 
+            let source = sub_commands
+                .get_one::<String>("Source")
+                .and_then(|source| PluginSource::parse(source))
+                .unwrap_or(PluginSource::Modrinth);
+            if source != PluginSource::Modrinth {
+                let sorting = sorting
+                    .cloned()
+                    .or_else(|| ModrinthSortingFilter::with(settings::get("search.sorting")));
+                let limit = max_mod_number
+                    .cloned()
+                    .or(settings::get_usize("search.limit"))
+                    .unwrap_or(10);
+                let offset = offset.cloned().unwrap_or(0);
+                let hits = match source {
+                    PluginSource::Hangar => hangar::search(
+                        name,
+                        sub_commands
+                            .get_one::<String>("Platform")
+                            .map(|p| p.as_str())
+                            .unwrap_or("paper"),
+                        version.map(|v| v.as_str()),
+                        limit,
+                        offset,
+                        sorting.and_then(|sorting| hangar::sort_of(sorting.get_filter())),
+                    )
+                    .await,
+                    PluginSource::Spigot => spiget::search(
+                        name,
+                        limit,
+                        offset,
+                        sorting.and_then(|sorting| spiget::sort_of(sorting.get_filter())),
+                    )
+                    .await,
+                    _ => Err(format!(
+                        "{} entries are not searchable, download them by id ex : github:owner/repo | maven:group:artifact:version",
+                        source.name()
+                    )),
+                };
+                match hits {
+                    Ok(hits) => modrinth_request::print_entries(&hits),
+                    Err(e) => println!("❌ {}", e),
+                }
+                return Ok(());
+            }
+
             let mut modrinth_mod = ModrinthEntry::builder();
             modrinth_mod
                 .search_modrinth(ModQuery::new(
                     name,
                     version,
                     loader.cloned(),
-                    max_mod_number.cloned(),
+                    max_mod_number
+                        .cloned()
+                        .or(settings::get_usize("search.limit")),
                     project_type.cloned(),
-                    sorting.cloned(),
+                    sorting
+                        .cloned()
+                        .or_else(|| ModrinthSortingFilter::with(settings::get("search.sorting"))),
                     offset.cloned(),
                     client_side.cloned(),
                     server_side.cloned(),
@@ -261,13 +817,65 @@ async fn main() -> Result<(), Error> {
         Some(("Download_Entry", sub_commands)) => {
             let for_server = sub_commands.get_one::<String>("For_Server");
             let id = sub_commands.get_one::<String>("Id");
-            let version = sub_commands.get_one::<String>("Version");
+            let version = sub_commands
+                .get_one::<String>("Version")
+                .cloned()
+                .or(settings::get("download.game_version"));
             let name = sub_commands.get_one::<String>("Name");
-            let download_path = sub_commands.get_one::<String>("Download_Path");
+            let download_path = sub_commands
+                .get_one::<String>("Download_Path")
+                .cloned()
+                .or(settings::get("download.dir"));
             let do_download_dependencies = sub_commands.get_one::<bool>("With_Dependencies");
-            let for_loader = sub_commands.get_one::<String>("For_Loader");
+            let for_loader = sub_commands
+                .get_one::<String>("For_Loader")
+                .cloned()
+                .or(settings::get("download.loader"));
 
-            if for_server.is_some() {
+            let server_project = for_server.and_then(|path| {
+                session::read_metadata(Path::new(path))
+                    .and_then(|data| data["project"].as_str().map(|p| p.to_owned()))
+            });
+            // `github:owner/repo`, `maven:group:artifact:version` and URLs carry their source
+            let prefixed = id.and_then(|id| PluginSource::from_id(id));
+            let id = prefixed.as_ref().map(|(_, project)| project).or(id);
+            let source = prefixed.as_ref().map(|(source, _)| *source).or(sub_commands
+                .get_one::<String>("Source")
+                .and_then(|source| PluginSource::parse(source)));
+            let is_plugin_server = server_project
+                .as_deref()
+                .and_then(plugins::plugin_loaders)
+                .is_some();
+            if source.is_some_and(|source| source != PluginSource::Modrinth) && for_server.is_none() {
+                println!("❌ Entries from other sources than Modrinth are installed with --for_server <server directory>");
+            } else if is_plugin_server || source.is_some_and(|source| source != PluginSource::Modrinth) {
+                let source = source.unwrap_or(PluginSource::Modrinth);
+                println!(
+                    "➡️ {} server, installing from {}",
+                    server_project.as_deref().unwrap_or("Unknown"),
+                    source.name()
+                );
+                let selection = FileSelection {
+                    tag: sub_commands.get_one::<String>("Tag").cloned(),
+                    asset: sub_commands.get_one::<String>("Asset").cloned(),
+                    hash: sub_commands.get_one::<String>("Hash").cloned(),
+                };
+                let installed = match ContentInstaller::build(Path::new(for_server.unwrap()), source, selection) {
+                    Ok(mut installer) => {
+                        installer
+                            .install(
+                                id.cloned(),
+                                name.cloned(),
+                                do_download_dependencies.cloned().unwrap_or(true),
+                            )
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = installed {
+                    println!("❌ {}", e);
+                }
+            } else if for_server.is_some() {
                 let server_path = verify_path(for_server.cloned());
                 let mut fabric_server = FabricMCRequest::build(server_path);
                 if fabric_server.check_data(None).is_ok() {
@@ -289,9 +897,9 @@ async fn main() -> Result<(), Error> {
                     .download_mod(
                         &mut id.cloned(),
                         name.cloned(),
-                        for_loader.cloned(),
-                        version.cloned(),
-                        verify_path(download_path.cloned()),
+                        for_loader,
+                        version,
+                        verify_path(download_path),
                         do_download_dependencies.cloned(),
                     )
                     .await;
@@ -299,13 +907,30 @@ async fn main() -> Result<(), Error> {
         }
         Some(("Create_Server", sub_commands)) => {
             let path = sub_commands.get_one::<String>("Path");
-            let game_version = sub_commands.get_one::<String>("Game_Version");
+            let game_version = sub_commands
+                .get_one::<String>("Game_Version")
+                .cloned()
+                .or(settings::get("server.game_version"));
+            let game_version = game_version.as_ref();
             let build = sub_commands.get_one::<String>("Build");
-            let platform = sub_commands.get_one::<String>("Platform");
-            let xmx = sub_commands.get_one::<String>("Max_Ram");
-            let xms = sub_commands.get_one::<String>("Min_Ram");
+            let platform = sub_commands
+                .get_one::<String>("Platform")
+                .cloned()
+                .or(settings::get("server.platform"));
+            let xmx = sub_commands
+                .get_one::<String>("Max_Ram")
+                .cloned()
+                .or(settings::get("server.max_ram"));
+            let xmx = xmx.as_ref();
+            let xms = sub_commands
+                .get_one::<String>("Min_Ram")
+                .cloned()
+                .or(settings::get("server.min_ram"));
+            let xms = xms.as_ref();
             let is_gui = sub_commands.get_one::<bool>("Gui");
-            let open_with_public_ip = sub_commands.get_one::<bool>("Public_IP");
+            let launch_options = launch_options(sub_commands);
+            let enable_rcon = sub_commands.get_one::<bool>("Rcon");
+            let query_port = sub_commands.get_one::<u16>("Query_Port");
 
             let path = match check_server_path(path.cloned()) {
                 Ok(p) => p,
@@ -313,27 +938,34 @@ async fn main() -> Result<(), Error> {
                     panic!("❌ Error while checking the server path\n    ➡️ {}", e)
                 }
             };
+            if enable_rcon.is_some_and(|enable| *enable) {
+                rcon::enable_rcon(&path);
+            }
+            if let Some(query_port) = query_port {
+                query::enable_query(&path, *query_port);
+            }
+            apply_server_settings(&path, sub_commands);
+            if let Some(name) = sub_commands.get_one::<String>("Name") {
+                registry::register(name, &path);
+            }
+            if launch_options.tunnel.is_some()
+                && !exposure::guard(&path, sub_commands.get_flag("Force")).await
+            {
+                std::process::exit(1);
+            }
             match platform {
                 Some(p) if p.to_lowercase() == "paper" => {
                     let mut paper_server = PaperMCRequest::build();
                     match paper_server.check_data(path.clone()) {
                         Ok(_) => {
-                            if let Some(do_open) = open_with_public_ip {
-                                if *do_open {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
-                                }
-                            }
-                            paper_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
+                            paper_server
+                                .start_server(
+                                    xmx.cloned(),
+                                    xms.cloned(),
+                                    is_gui.cloned(),
+                                    launch_options.clone(),
+                                )
+                                .await;
                         }
                         Err(_) => {
                             paper_server
@@ -341,22 +973,14 @@ async fn main() -> Result<(), Error> {
                                 .await;
                             paper_server.download_build(path).await;
 
-                            if let Some(do_open) = open_with_public_ip {
-                                if *do_open {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
-                                }
-                            }
-                            paper_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
+                            paper_server
+                                .start_server(
+                                    xmx.cloned(),
+                                    xms.cloned(),
+                                    is_gui.cloned(),
+                                    launch_options.clone(),
+                                )
+                                .await;
                         }
                     }
                 }
@@ -364,22 +988,14 @@ async fn main() -> Result<(), Error> {
                     let mut fabric_server = FabricMCRequest::build(Some(path.clone()));
                     match fabric_server.check_data(Some(path.clone())) {
                         Ok(_) => {
-                            if let Some(do_open_public) = open_with_public_ip {
-                                if *do_open_public {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
-                                }
-                            }
-                            fabric_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
+                            fabric_server
+                                .start_server(
+                                    xmx.cloned(),
+                                    xms.cloned(),
+                                    is_gui.cloned(),
+                                    launch_options.clone(),
+                                )
+                                .await;
                         }
                         Err(_) => {
                             fabric_server
@@ -388,23 +1004,19 @@ async fn main() -> Result<(), Error> {
                             fabric_server.select_loader_version().await;
                             fabric_server.fetch_latest_installer_version().await;
                             fabric_server.generate_download_url();
-                            fabric_server.download_build(path).await;
-                            if let Some(do_open_public) = open_with_public_ip {
-                                if *do_open_public {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
+                            match fabric_server.download_build(path).await {
+                                Ok(_) => {
+                                    fabric_server
+                                        .start_server(
+                                            xmx.cloned(),
+                                            xms.cloned(),
+                                            is_gui.cloned(),
+                                            launch_options.clone(),
+                                        )
+                                        .await
                                 }
+                                Err(e) => println!("❌ {}", e),
                             }
-                            fabric_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
                         }
                     }
                 }
@@ -413,22 +1025,14 @@ async fn main() -> Result<(), Error> {
                     match paper_server.check_data(path.clone()) {
                         Ok(_) => {
                             println!("✅ MCA.json Found !");
-                            if let Some(do_open) = open_with_public_ip {
-                                if *do_open {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
-                                }
-                            }
-                            paper_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
+                            paper_server
+                                .start_server(
+                                    xmx.cloned(),
+                                    xms.cloned(),
+                                    is_gui.cloned(),
+                                    launch_options.clone(),
+                                )
+                                .await;
                         }
                         Err(_) => {
                             println!("➡️ No MCA.json Found");
@@ -437,24 +1041,390 @@ async fn main() -> Result<(), Error> {
                                 .await;
                             paper_server.download_build(path).await;
 
-                            if let Some(do_open) = open_with_public_ip {
-                                if *do_open {
-                                    let _ = std::process::Command::new("cmd")
-                                        .args([
-                                            "/C",
-                                            "ssh",
-                                            "-R",
-                                            "0:localhost:25565",
-                                            "serveo.net",
-                                        ])
-                                        .spawn()
-                                        .unwrap()
-                                        .wait();
-                                }
+                            paper_server
+                                .start_server(
+                                    xmx.cloned(),
+                                    xms.cloned(),
+                                    is_gui.cloned(),
+                                    launch_options.clone(),
+                                )
+                                .await;
+                        }
+                    }
+                }
+            }
+        }
+        Some(("Router", sub_commands)) => match sub_commands.subcommand() {
+            Some(("Start", args)) => {
+                Router::build(
+                    *args.get_one::<u16>("Port").unwrap(),
+                    args.get_flag("Accept_Proxy_Protocol"),
+                )
+                .run()
+                .await
+            }
+            Some(("Add", args)) => router::add_route(
+                args.get_one::<String>("Hostname").unwrap(),
+                Route {
+                    server: args.get_one::<String>("Name").unwrap().clone(),
+                    motd: args.get_one::<String>("Motd").cloned(),
+                    proxy_protocol: args.get_flag("Proxy_Protocol"),
+                },
+            ),
+            Some(("Remove", args)) => router::remove_route(args.get_one::<String>("Hostname").unwrap()),
+            _ => router::print_routes(),
+        },
+        Some(("Servers", sub_commands)) => match sub_commands.subcommand() {
+            Some(("Add", args)) => registry::register(
+                args.get_one::<String>("Name").unwrap(),
+                Path::new(args.get_one::<String>("Path").unwrap()),
+            ),
+            Some(("Remove", args)) => registry::unregister(args.get_one::<String>("Name").unwrap()),
+            _ => registry::print_list(),
+        },
+        Some(("Lan", sub_commands)) => {
+            if let Some(("Announce", args)) = sub_commands.subcommand() {
+                lan::announce(
+                    &server_target(args),
+                    args.get_one::<String>("Motd").cloned(),
+                    *args.get_one::<u16>("Port").unwrap(),
+                )
+                .await;
+            }
+        }
+        Some(("Modpack", sub_commands)) => {
+            if let Some(("Import", args)) = sub_commands.subcommand() {
+                let path = PathBuf::from(
+                    args.get_one::<String>("Path")
+                        .cloned()
+                        .unwrap_or_else(default_server_path),
+                );
+                match curseforge::import(Path::new(args.get_one::<String>("Pack").unwrap()), &path).await {
+                    Ok(_) => {
+                        if let Some(name) = args.get_one::<String>("Name") {
+                            registry::register(name, &path);
+                        }
+                    }
+                    Err(e) => println!("❌ {}", e),
+                }
+            }
+        }
+        Some(("Rcon", sub_commands)) => {
+            let target = server_target(sub_commands);
+            let password = sub_commands.get_one::<String>("Password").cloned();
+            if let Some(mut client) = rcon::open(&target, password).await {
+                match after_dir(sub_commands, "Command", "Target") {
+                    Some(command) => match client.command(command).await {
+                        Ok(output) => println!("{}", output),
+                        Err(e) => println!("❌ {}", e),
+                    },
+                    None => rcon::shell(&mut client).await,
+                }
+            }
+        }
+        Some(("Status", sub_commands)) => {
+            let as_json = sub_commands.get_flag("Json");
+            let target = server_status::StatusTarget::resolve(&server_target(sub_commands)).await;
+            match server_status::ping(&target).await {
+                Ok(status) => {
+                    if as_json {
+                        let mut status = serde_json::to_value(&status).unwrap();
+                        status["online"] = serde_json::Value::Bool(true);
+                        println!("{}", serde_json::to_string_pretty(&status).unwrap());
+                    } else {
+                        status.display();
+                    }
+                }
+                Err(e) => {
+                    if as_json {
+                        println!(
+                            "{}",
+                            serde_json::json!({ "address": target.connect_address, "online": false, "error": e })
+                        );
+                    } else {
+                        println!("❌ {} is offline : {}", target.connect_address, e);
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("Query", sub_commands)) => {
+            let address = query::resolve_target(&server_target(sub_commands));
+            let as_json = sub_commands.get_flag("Json");
+            let client = match query::QueryClient::connect(&address).await {
+                Ok(client) => client,
+                Err(e) => {
+                    println!("❌ Unable to query {} : {}", address, e);
+                    std::process::exit(1);
+                }
+            };
+            let result = if sub_commands.get_flag("Basic") {
+                client.basic_stat().await.map(|stat| {
+                    if as_json {
+                        println!("{}", serde_json::to_string_pretty(&stat).unwrap());
+                    } else {
+                        stat.display(&address);
+                    }
+                })
+            } else {
+                client.full_stat().await.map(|stat| {
+                    if as_json {
+                        println!("{}", serde_json::to_string_pretty(&stat).unwrap());
+                    } else {
+                        stat.display(&address);
+                    }
+                })
+            };
+            if let Err(e) = result {
+                println!("❌ Unable to query {} : {}", address, e);
+                std::process::exit(1);
+            }
+        }
+        Some(("Config", sub_commands)) => match sub_commands.subcommand() {
+            Some(("Get", args)) => {
+                let path = server_dir(args);
+                server_properties::print_value(&path, after_dir(args, "Key", "Path").unwrap());
+            }
+            Some(("Set", args)) => {
+                let path = server_dir(args);
+                let key = after_dir(args, "Key", "Path").unwrap();
+                let value = after_dir(args, "Value", "Key").unwrap();
+                match server_properties::apply(&path, &[(key, value.clone())]) {
+                    Ok(_) => println!("✅ {}={}", key, value),
+                    Err(e) => println!("❌ {}", e),
+                }
+            }
+            Some(("List", args)) => {
+                server_properties::print_list(&server_dir(args));
+            }
+            Some(("Show", _)) => settings::print_effective(),
+            _ => {}
+        },
+        Some(("Server", sub_commands)) => match sub_commands.subcommand() {
+            Some(("Start", args)) => {
+                let path = server_dir(args);
+                let xmx = args
+                    .get_one::<String>("Max_Ram")
+                    .cloned()
+                    .or(settings::get("server.max_ram"));
+                let xms = args
+                    .get_one::<String>("Min_Ram")
+                    .cloned()
+                    .or(settings::get("server.min_ram"));
+                let is_gui = args.get_one::<bool>("Gui").cloned();
+                let mut launch_options = launch_options(args);
+                if launch_options.tunnel.is_some()
+                    && !exposure::guard(&path, args.get_flag("Force")).await
+                {
+                    std::process::exit(1);
+                }
+
+                if args.get_flag("Detach") {
+                    // Everything but the path and --detach is given back to the background MCT
+                    let mut forwarded_args = vec![];
+                    if let Some(xmx) = xmx {
+                        forwarded_args.extend(["--max_ram".to_owned(), xmx]);
+                    }
+                    if let Some(xms) = xms {
+                        forwarded_args.extend(["--min_ram".to_owned(), xms]);
+                    }
+                    for (id, flag) in [
+                        ("Supervise", "--supervise"),
+                        ("Max_Crashes", "--max_crashes"),
+                        ("Crash_Window", "--crash_window"),
+                        ("Jvm_Profile", "--jvm_profile"),
+                        ("Tunnel", "--tunnel"),
+                        ("Tunnel_Host", "--tunnel_host"),
+                        ("Tunnel_Secret", "--tunnel_secret"),
+                        ("Tunnel_Command", "--tunnel_command"),
+                        ("Tunnel_Pattern", "--tunnel_pattern"),
+                    ] {
+                        if let Some(value) = args.get_raw(id).and_then(|mut v| v.next()) {
+                            forwarded_args.push(flag.to_owned());
+                            forwarded_args.push(value.to_string_lossy().into_owned());
+                        }
+                    }
+                    if launch_options.lan {
+                        forwarded_args.push("--lan".to_owned());
+                    }
+                    if launch_options.tunnel.is_some() {
+                        // Already checked, the background MCT cannot prompt
+                        forwarded_args.push("--force".to_owned());
+                    }
+                    session::detach(&path, forwarded_args);
+                } else {
+                    if args.get_flag("Daemon") {
+                        launch_options.console = ConsoleMode::Socket;
+                    }
+                    session::start_existing_server(path, xmx, xms, is_gui, launch_options).await;
+                }
+            }
+            Some(("Sleep", args)) => {
+                let xmx = args
+                    .get_one::<String>("Max_Ram")
+                    .cloned()
+                    .or(settings::get("server.max_ram"));
+                let xms = args
+                    .get_one::<String>("Min_Ram")
+                    .cloned()
+                    .or(settings::get("server.min_ram"));
+                match OnDemand::build(
+                    server_dir(args),
+                    args.get_one::<u16>("Backend_Port").cloned(),
+                    args.get_one::<String>("Motd").cloned(),
+                    *args.get_one::<u64>("Idle").unwrap(),
+                    xmx,
+                    xms,
+                    launch_options(args),
+                ) {
+                    Ok(on_demand) => {
+                        Arc::new(on_demand.with_proxy_protocol(args.get_flag("Proxy_Protocol")))
+                            .run()
+                            .await
+                    }
+                    Err(e) => println!("❌ {}", e),
+                }
+            }
+            Some(("Attach", args)) => {
+                session::attach(&server_dir(args)).await;
+            }
+            Some(("Send", args)) => {
+                let path = server_dir(args);
+                session::send(&path, after_dir(args, "Command", "Path").unwrap()).await;
+            }
+            Some(("Stop", args)) => {
+                let path = server_dir(args);
+                let countdown = *args.get_one::<u64>("Countdown").unwrap();
+                let timeout = Duration::from_secs(*args.get_one::<u64>("Timeout").unwrap());
+                shutdown::stop_server(&path, countdown, timeout).await;
+            }
+            Some(("Schedule", args)) => {
+                let path = server_dir(args);
+                if args.get_flag("Disable") {
+                    match RestartSchedule::save(&path, None) {
+                        Ok(_) => println!("✅ Daily restart disabled"),
+                        Err(e) => println!("❌ Error while writting MCA.json : {}", e),
+                    }
+                } else if let Some(daily) = args.get_one::<String>("Daily") {
+                    let schedule = RestartSchedule::parse(
+                        daily,
+                        args.get_one::<String>("Warnings").map(|w| w.as_str()),
+                    );
+                    match schedule.and_then(|schedule| {
+                        RestartSchedule::save(&path, Some(&schedule))
+                            .map_err(|e| format!("Error while writting MCA.json : {}", e))
+                    }) {
+                        Ok(_) => println!("✅ Server restarts every day at {}", daily),
+                        Err(e) => println!("❌ {}", e),
+                    }
+                } else {
+                    match RestartSchedule::load(&path) {
+                        Some(schedule) => println!(
+                            "➡️ Restarts every day at {}, warnings {}s before",
+                            schedule.daily_at,
+                            schedule
+                                .warnings
+                                .iter()
+                                .map(|w| w.to_string())
+                                .collect::<Vec<String>>()
+                                .join("s, ")
+                        ),
+                        None => println!("➡️ No daily restart"),
+                    }
+                }
+            }
+            _ => {}
+        },
+        Some(("Backup", sub_commands)) => match sub_commands.subcommand() {
+            Some(("Create", args)) => {
+                let path = server_dir(args);
+                match backup::create(&path).await {
+                    Ok(created) => {
+                        println!(
+                            "✅ Backup created : {} ({:.1} MB)",
+                            created.path.to_string_lossy(),
+                            created.size as f64 / 1_048_576.0
+                        );
+                        if let Some(policy) = retention_policy(args) {
+                            for removed in backup::prune(&path, &policy) {
+                                println!("➡️ Removed {}", removed.name());
                             }
-                            paper_server.start_server(xmx.cloned(), xms.cloned(), is_gui.cloned());
                         }
                     }
+                    Err(e) => println!("❌ {}", e),
+                }
+            }
+            Some(("List", args)) => {
+                backup::print_list(&server_dir(args));
+            }
+            Some(("Restore", args)) => {
+                let path = server_dir(args);
+                match backup::restore(&path, args.get_one::<String>("Backup").unwrap()) {
+                    Ok(archive) => println!("✅ Restored {}", archive.to_string_lossy()),
+                    Err(e) => println!("❌ {}", e),
+                }
+            }
+            Some(("Prune", args)) => {
+                let path = server_dir(args);
+                let policy = retention_policy(args).unwrap_or_default();
+                let removed = backup::prune(&path, &policy);
+                for backup in &removed {
+                    println!("➡️ Removed {}", backup.name());
+                }
+                println!("✅ {} backup(s) removed", removed.len());
+            }
+            _ => {}
+        },
+        Some(("Logs", sub_commands)) => {
+            let path = server_dir(sub_commands);
+            let time = |id: &str| sub_commands.get_one::<String>(id).map(|t| logs::parse_time(t));
+            let filter = (|| -> Result<logs::LogFilter, String> {
+                Ok(logs::LogFilter {
+                    level: sub_commands
+                        .get_one::<String>("Level")
+                        .and_then(|l| logs::Level::parse(l)),
+                    since: time("Since").transpose()?,
+                    until: time("Until").transpose()?,
+                    pattern: sub_commands
+                        .get_one::<String>("Grep")
+                        .map(|p| regex::Regex::new(p).map_err(|e| e.to_string()))
+                        .transpose()?,
+                })
+            })();
+            match filter {
+                Ok(filter) => {
+                    let lines = *sub_commands.get_one::<usize>("Lines").unwrap();
+                    logs::show(&path, &filter, lines, sub_commands.get_flag("Follow")).await;
+                }
+                Err(e) => println!("❌ {}", e),
+            }
+        }
+        Some(("Crash", sub_commands)) => {
+            let path = server_dir(sub_commands);
+            let file = sub_commands.get_one::<String>("File").map(PathBuf::from);
+            crash::triage(&path, file.as_deref()).await;
+        }
+        Some(("Players", sub_commands)) => {
+            if let Some((name, args)) = sub_commands.subcommand() {
+                let list = match name {
+                    "Whitelist" => PlayerList::Whitelist,
+                    "Op" => PlayerList::Ops,
+                    "Ban" => PlayerList::BannedPlayers,
+                    _ => PlayerList::BannedIps,
+                };
+                let path = server_dir(args);
+                let player = after_dir(args, "Name", "Path");
+                match (args.get_one::<String>("Action").unwrap().as_str(), player) {
+                    ("list", _) => players::print_list(&path, list),
+                    ("add", Some(player)) => {
+                        let options = EntryOptions {
+                            reason: args.try_get_one::<String>("Reason").ok().flatten().cloned(),
+                            level: args.try_get_one::<u8>("Level").ok().flatten().cloned(),
+                        };
+                        players::add(&path, list, player, &options).await;
+                    }
+                    ("remove", Some(player)) => players::remove(&path, list, player).await,
+                    _ => println!("❌ A player name is required"),
                 }
             }
         }
@@ -464,16 +1434,301 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
+fn retention_args() -> [Arg; 3] {
+    [
+        Arg::new("Keep_Last")
+            .long("keep_last")
+            .value_parser(clap::value_parser!(usize))
+            .help("Keep the N newest backups"),
+        Arg::new("Keep_Daily")
+            .long("keep_daily")
+            .value_parser(clap::value_parser!(usize))
+            .help("Keep the newest backup of each of the last N days with backups"),
+        Arg::new("Keep_Weekly")
+            .long("keep_weekly")
+            .value_parser(clap::value_parser!(usize))
+            .help("Keep the newest backup of each of the last N weeks with backups"),
+    ]
+}
+
+fn retention_policy(matches: &clap::ArgMatches) -> Option<backup::RetentionPolicy> {
+    backup::RetentionPolicy::new(
+        matches.get_one::<usize>("Keep_Last").cloned(),
+        matches.get_one::<usize>("Keep_Daily").cloned(),
+        matches.get_one::<usize>("Keep_Weekly").cloned(),
+    )
+}
+
+/// `<add|remove|list> <Path> [Name]` shared by every player list
+fn player_list_command(name: &'static str, about: &'static str) -> Command {
+    Command::new(name)
+        .about(about)
+        .arg(
+            Arg::new("Action")
+                .value_parser(["add", "remove", "list"])
+                .required(true))
+        .arg(dir_arg().required_unless_present("Server"))
+        .arg(
+            Arg::new("Name")
+                .help("Player name, or IP address for Ban_Ip")
+                .conflicts_with("Server"))
+}
+
+/// Arguments shared by every command starting a server
+fn supervisor_args() -> [Arg; 5] {
+    [
+        Arg::new("Supervise")
+            .long("supervise")
+            .short('s')
+            .value_parser(clap::value_parser!(bool))
+            .help("Keep MCT in the foreground and restart the server when it crashes ex : true | false")
+            .required(false),
+        Arg::new("Max_Crashes")
+            .long("max_crashes")
+            .value_parser(clap::value_parser!(usize))
+            .help("Number of crashes allowed in the crash window before giving up, default value : 5")
+            .required(false),
+        Arg::new("Crash_Window")
+            .long("crash_window")
+            .value_parser(clap::value_parser!(u64))
+            .help("Crash window in seconds used to detect crash loops, default value : 600")
+            .required(false),
+        Arg::new("Jvm_Profile")
+            .long("jvm_profile")
+            .value_parser(["default", "aikar"])
+            .ignore_case(true)
+            .help("JVM flags given to the server, default value : the server.jvm_profile setting")
+            .required(false),
+        Arg::new("Lan")
+            .long("lan")
+            .action(clap::ArgAction::SetTrue)
+            .help("Show the server in the LAN Worlds list of players on the local network while it runs"),
+    ]
+}
+
+/// Public tunnel of the commands starting a server
+fn tunnel_args() -> [Arg; 6] {
+    [
+        Arg::new("Tunnel")
+            .long("tunnel")
+            .value_parser(tunnel::PROVIDERS)
+            .ignore_case(true)
+            .help("Expose the server publicly while it runs, upnp forwards the port on the router ex : ssh | bore | upnp | custom")
+            .required(false),
+        Arg::new("Tunnel_Host")
+            .long("tunnel_host")
+            .help("ssh host (default serveo.net), bore server (default : the tunnel.bore_server setting) or upnp gateway (IGD description URL or NAT-PMP address, discovered by default) ex : bore.example.com:7835")
+            .required(false),
+        Arg::new("Tunnel_Secret")
+            .long("tunnel_secret")
+            .help("Secret of the bore server, prefer the MCT_TUNNEL_BORE_SECRET env var")
+            .required(false),
+        Arg::new("Tunnel_Command")
+            .long("tunnel_command")
+            .help("Command of the custom tunnel, {port} is replaced by the server port ex : \"playit --port {port}\"")
+            .required(false),
+        Arg::new("Tunnel_Pattern")
+            .long("tunnel_pattern")
+            .help("Regex finding the public address in the custom tunnel output, its first group is the address")
+            .required(false),
+        Arg::new("Force")
+            .long("force")
+            .action(clap::ArgAction::SetTrue)
+            .help("Open the tunnel even when the server is unsafe to expose (offline mode without a proxy, weak RCON password)"),
+    ]
+}
+
+/// server.properties settings that can be given when creating a server, (argument id, key)
+const SERVER_SETTINGS: &[(&str, &str)] = &[
+    ("Port", "server-port"),
+    ("Motd", "motd"),
+    ("Difficulty", "difficulty"),
+    ("Gamemode", "gamemode"),
+    ("Max_Players", "max-players"),
+    ("Online_Mode", "online-mode"),
+    ("View_Distance", "view-distance"),
+];
+
+fn server_settings_args() -> [Arg; 7] {
+    [
+        Arg::new("Port")
+            .long("port")
+            .help("Server port, default value : 25565")
+            .required(false),
+        Arg::new("Motd")
+            .long("motd")
+            .help("Message shown in the server list")
+            .required(false),
+        Arg::new("Difficulty")
+            .long("difficulty")
+            .help("ex : peaceful | easy | normal | hard")
+            .required(false),
+        Arg::new("Gamemode")
+            .long("gamemode")
+            .help("ex : survival | creative | adventure | spectator")
+            .required(false),
+        Arg::new("Max_Players")
+            .long("max_players")
+            .help("Maximum number of players, default value : 20")
+            .required(false),
+        Arg::new("Online_Mode")
+            .long("online_mode")
+            .help("Check players against Mojang accounts ex : true | false")
+            .required(false),
+        Arg::new("View_Distance")
+            .long("view_distance")
+            .help("View distance in chunks between 3 and 32, default value : 10")
+            .required(false),
+    ]
+}
+
+/// Writes the server.properties settings given to Create_Server
+fn apply_server_settings(path: &Path, sub_commands: &clap::ArgMatches) {
+    let values: Vec<(&str, String)> = SERVER_SETTINGS
+        .iter()
+        .filter_map(|(id, key)| {
+            sub_commands
+                .get_one::<String>(id)
+                .map(|value| (*key, value.clone()))
+        })
+        .collect();
+    if values.is_empty() {
+        return;
+    }
+    match server_properties::apply(path, &values) {
+        Ok(_) => println!("✅ server.properties updated"),
+        Err(e) => panic!("❌ Invalid server setting\n    ➡️ {}", e),
+    }
+}
+
+fn launch_options(sub_commands: &clap::ArgMatches) -> LaunchOptions {
+    let restart_policy = if sub_commands
+        .get_one::<bool>("Supervise")
+        .is_some_and(|supervise| *supervise)
+    {
+        Some(RestartPolicy::new(
+            sub_commands.get_one::<usize>("Max_Crashes").cloned(),
+            sub_commands.get_one::<u64>("Crash_Window").cloned(),
+        ))
+    } else {
+        None
+    };
+    let jvm_profile = sub_commands
+        .get_one::<String>("Jvm_Profile")
+        .cloned()
+        .or(settings::get("server.jvm_profile"))
+        .unwrap_or_default();
+    LaunchOptions {
+        restart_policy,
+        jvm_args: supervisor::jvm_profile_args(&jvm_profile),
+        tunnel: tunnel_provider(sub_commands),
+        lan: sub_commands.get_flag("Lan"),
+        ..Default::default()
+    }
+}
+
+/// `--tunnel <provider>`, `--public_ip true` being the bore tunnel, exits on invalid arguments
+fn tunnel_provider(sub_commands: &clap::ArgMatches) -> Option<Arc<dyn TunnelProvider>> {
+    let public_ip = sub_commands
+        .try_get_one::<bool>("Public_IP")
+        .ok()
+        .flatten()
+        .is_some_and(|public_ip| *public_ip);
+    let name = sub_commands
+        .try_get_one::<String>("Tunnel")
+        .ok()
+        .flatten()
+        .cloned()
+        .or(public_ip.then(|| String::from("bore")))?;
+    match tunnel::provider(
+        &name,
+        sub_commands.get_one::<String>("Tunnel_Host").cloned(),
+        sub_commands.get_one::<String>("Tunnel_Secret").cloned(),
+        sub_commands.get_one::<String>("Tunnel_Command").cloned(),
+        sub_commands.get_one::<String>("Tunnel_Pattern").map(|p| p.as_str()),
+    ) {
+        Ok(provider) => Some(provider),
+        Err(e) => {
+            println!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Server directory given to a command : `--server <name>`, the path argument or `server.path`
+fn server_dir(matches: &clap::ArgMatches) -> PathBuf {
+    if let Some(name) = server_name(matches) {
+        return registry::resolve(name);
+    }
+    PathBuf::from(
+        matches
+            .get_one::<String>("Path")
+            .cloned()
+            .unwrap_or_else(default_server_path),
+    )
+}
+
+fn server_name(matches: &clap::ArgMatches) -> Option<&String> {
+    matches.try_get_one::<String>("Server").ok().flatten()
+}
+
+/// Target of a network command, the directory of `--server <name>` when given
+fn server_target(matches: &clap::ArgMatches) -> String {
+    match server_name(matches) {
+        Some(name) => registry::resolve(name).to_string_lossy().into_owned(),
+        None => matches.get_one::<String>("Target").unwrap().clone(),
+    }
+}
+
+/// Positional `id` typed after the directory. `--server` stands for the directory,
+/// so clap stores what follows under the `previous` id : the last positional
+/// conflicts with `--server` and each value is read from the slot it landed in
+fn after_dir<'a>(matches: &'a clap::ArgMatches, id: &str, previous: &str) -> Option<&'a String> {
+    match server_name(matches) {
+        Some(_) => matches.get_one::<String>(previous),
+        None => matches.get_one::<String>(id),
+    }
+}
+
+
+/// Where entries are searched and downloaded from
+fn source_arg() -> Arg {
+    Arg::new("Source")
+        .long("source")
+        .value_parser(plugins::SOURCES)
+        .ignore_case(true)
+        .help("Repository of the entries, hangar and spigot only have plugins, github, maven and url are given by the entry id, default value : modrinth\nex : modrinth | hangar | spigot | github | maven | url")
+        .required(false)
+}
+
+/// Directory positional followed by other positionals, see `after_dir`
+fn dir_arg() -> Arg {
+    Arg::new("Path").help("Server path Directory, omitted with --server")
+}
+
+/// `--server <name>`, accepted by every command working on a server directory
+fn server_arg() -> Arg {
+    Arg::new("Server")
+        .long("server")
+        .help("Name of a registered server, used instead of the path")
+}
+
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
+/// `server.path` setting, `./MCT Server` by default
+fn default_server_path() -> String {
+    settings::get("server.path").unwrap_or_default()
+}
+
 /// Verifies if the given download path is valid.
 /// Returns `Some(&Path)` if the path exists and is a directory, otherwise `None`.
 fn verify_path(path: Option<String>) -> Option<PathBuf> {
-    if path.is_some() {
-        let dlpath = path.unwrap();
+    if let Some(dlpath) = path {
         let path = Path::new(&dlpath);
 
         // Check if the path exists and is a directory
@@ -512,7 +1767,7 @@ fn check_server_path(path: Option<String>) -> Result<PathBuf, std::io::Error> {
         }
     } else {
         // No path provided try default path if it does not work return error
-        let default_path = "./MCT Server";
+        let default_path = &default_server_path();
 
         if Path::new(default_path).exists() {
             println!("✅ Default Server directory Found");
@@ -0,0 +1,325 @@
+use crate::plugins::{Credentials, FileHash, Release};
+use crate::settings;
+
+/// `group:artifact[:version]`, the version defaults to `release`
+#[derive(Debug)]
+struct Coordinates {
+    group: String,
+    artifact: String,
+    version: String,
+}
+
+impl Coordinates {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        match parts.as_slice() {
+            [group, artifact] | [group, artifact, ""]
+                if !group.is_empty() && !artifact.is_empty() =>
+            {
+                Ok(Self {
+                    group: group.to_string(),
+                    artifact: artifact.to_string(),
+                    version: String::from("release"),
+                })
+            }
+            [group, artifact, version]
+                if !group.is_empty() && !artifact.is_empty() && !version.is_empty() =>
+            {
+                Ok(Self {
+                    group: group.to_string(),
+                    artifact: artifact.to_string(),
+                    version: version.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "'{}' is not a Maven artifact, ex : maven:com.example:my-mod:1.0.0",
+                spec
+            )),
+        }
+    }
+
+    /// Directory of the artifact in a repository, ex : `com/example/my-mod`
+    fn path(&self) -> String {
+        format!("{}/{}", self.group.replace('.', "/"), self.artifact)
+    }
+}
+
+/// Repositories searched in order, from the comma separated `maven.repositories` setting
+fn repositories() -> Vec<String> {
+    settings::get("maven.repositories")
+        .unwrap_or_default()
+        .split(',')
+        .map(|repository| repository.trim().trim_end_matches('/').to_owned())
+        .filter(|repository| !repository.is_empty())
+        .collect()
+}
+
+/// `maven.username` and `maven.password`, only sent to the `maven.auth_repository` repository
+fn credentials(repository: &str) -> Option<Credentials> {
+    let auth_repository = settings::get("maven.auth_repository")?;
+    if repository != auth_repository.trim().trim_end_matches('/') {
+        return None;
+    }
+    settings::get("maven.username")
+        .map(|username| Credentials::Basic(username, settings::get("maven.password")))
+}
+
+/// Content of `url`, `None` when the repository does not have it
+async fn fetch(url: &str, credentials: Option<&Credentials>) -> Result<Option<String>, String> {
+    let mut request = reqwest::Client::new().get(url).header(
+        reqwest::header::USER_AGENT,
+        concat!("MCT/", env!("CARGO_PKG_VERSION")),
+    );
+    if let Some(Credentials::Basic(username, password)) = credentials {
+        request = request.basic_auth(username, password.as_ref());
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed : {}", e))?;
+    match response.status() {
+        reqwest::StatusCode::NOT_FOUND => Ok(None),
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Err(format!(
+            "{} refused the credentials, check the maven.auth_repository, maven.username and maven.password settings",
+            url
+        )),
+        _ => response
+            .error_for_status()
+            .map_err(|e| format!("Request failed : {}", e))?
+            .text()
+            .await
+            .map(Some)
+            .map_err(|e| format!("Invalid response from {} : {}", url, e)),
+    }
+}
+
+/// Text of every `<tag>` of a maven-metadata.xml, in document order
+fn tag_values(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut values = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        values.push(rest[..end].trim().to_owned());
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+/// `release` or `latest` from the metadata of the artifact, the last listed version otherwise
+fn resolve_version(metadata: &str, version: &str) -> Option<String> {
+    tag_values(metadata, version)
+        .into_iter()
+        .next()
+        .filter(|resolved| !resolved.is_empty())
+        .or_else(|| tag_values(metadata, "version").pop())
+}
+
+/// Jar name of a version, snapshots are stored with the timestamp of their last build
+async fn jar_name(
+    base: &str,
+    coordinates: &Coordinates,
+    version: &str,
+    credentials: Option<&Credentials>,
+) -> Result<String, String> {
+    if !version.ends_with("-SNAPSHOT") {
+        return Ok(versioned_jar_name(&coordinates.artifact, version, None));
+    }
+    let metadata = fetch(
+        &format!("{}/{}/maven-metadata.xml", base, version),
+        credentials,
+    )
+    .await?;
+    Ok(versioned_jar_name(&coordinates.artifact, version, metadata.as_deref()))
+}
+
+/// `artifact-version.jar`, the `-SNAPSHOT` suffix is replaced by the timestamp and
+/// build number found in the metadata of the snapshot
+fn versioned_jar_name(artifact: &str, version: &str, snapshot_metadata: Option<&str>) -> String {
+    let snapshot = version
+        .strip_suffix("-SNAPSHOT")
+        .zip(snapshot_metadata)
+        .and_then(|(base_version, metadata)| {
+            Some((
+                base_version,
+                tag_values(metadata, "timestamp").into_iter().next()?,
+                tag_values(metadata, "buildNumber").into_iter().next()?,
+            ))
+        });
+    match snapshot {
+        Some((base_version, timestamp, build)) => {
+            format!("{}-{}-{}-{}.jar", artifact, base_version, timestamp, build)
+        }
+        None => format!("{}-{}.jar", artifact, version),
+    }
+}
+
+/// Checksum published next to a file, the stronger one first
+async fn checksum(
+    url: &str,
+    credentials: Option<&Credentials>,
+) -> Result<Option<FileHash>, String> {
+    for algorithm in ["sha256", "sha1"] {
+        if let Some(sidecar) = fetch(&format!("{}.{}", url, algorithm), credentials).await? {
+            // Some tools append the file name after the hash
+            let hash = sidecar.split_whitespace().next().unwrap_or_default();
+            return FileHash::parse(&format!("{}:{}", algorithm, hash))
+                .map(Some)
+                .ok_or(format!("Invalid checksum in {}.{}", url, algorithm));
+        }
+    }
+    Ok(None)
+}
+
+/// Jar of an artifact in the first configured repository having it with a checksum
+pub async fn release(spec: &str, version: Option<&str>) -> Result<Release, String> {
+    let coordinates = Coordinates::parse(spec)?;
+    let version = version.unwrap_or(&coordinates.version);
+    let repositories = repositories();
+    if repositories.is_empty() {
+        return Err(String::from(
+            "No Maven repository configured, set maven.repositories",
+        ));
+    }
+    if settings::get("maven.username").is_some() && settings::get("maven.auth_repository").is_none() {
+        println!("⚠️ maven.username is ignored until maven.auth_repository names the repository it is for");
+    }
+    for repository in &repositories {
+        let credentials = credentials(repository);
+        let base = format!("{}/{}", repository, coordinates.path());
+        let version = if version == "release" || version == "latest" {
+            let Some(metadata) = fetch(
+                &format!("{}/maven-metadata.xml", base),
+                credentials.as_ref(),
+            )
+            .await?
+            else {
+                continue;
+            };
+            match resolve_version(&metadata, version) {
+                Some(resolved) => resolved,
+                None => continue,
+            }
+        } else {
+            version.to_owned()
+        };
+        let file_name = jar_name(&base, &coordinates, &version, credentials.as_ref()).await?;
+        let url = format!("{}/{}/{}", base, version, file_name);
+        let Some(hash) = checksum(&url, credentials.as_ref()).await? else {
+            continue;
+        };
+        return Ok(Release {
+            project: format!("{}:{}", coordinates.group, coordinates.artifact),
+            name: coordinates.artifact.clone(),
+            version,
+            file_name,
+            url,
+            hash: Some(hash),
+            dependencies: Vec::new(),
+            credentials,
+        });
+    }
+    Err(format!(
+        "{}:{} {} not found with a .sha256 or .sha1 checksum in {}",
+        coordinates.group,
+        coordinates.artifact,
+        version,
+        repositories.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>com.example</groupId>
+  <artifactId>my-mod</artifactId>
+  <versioning>
+    <latest>2.0.0-SNAPSHOT</latest>
+    <release>1.2.0</release>
+    <versions>
+      <version>1.0.0</version>
+      <version>1.2.0</version>
+      <version>2.0.0-SNAPSHOT</version>
+    </versions>
+    <lastUpdated>20250103100000</lastUpdated>
+  </versioning>
+</metadata>"#;
+
+    const SNAPSHOT_METADATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata modelVersion="1.1.0">
+  <groupId>com.example</groupId>
+  <artifactId>my-mod</artifactId>
+  <version>2.0.0-SNAPSHOT</version>
+  <versioning>
+    <snapshot>
+      <timestamp>20250103.095812</timestamp>
+      <buildNumber>7</buildNumber>
+    </snapshot>
+    <lastUpdated>20250103095812</lastUpdated>
+    <snapshotVersions>
+      <snapshotVersion>
+        <extension>jar</extension>
+        <value>2.0.0-20250103.095812-7</value>
+        <updated>20250103095812</updated>
+      </snapshotVersion>
+    </snapshotVersions>
+  </versioning>
+</metadata>"#;
+
+    #[test]
+    fn coordinates() {
+        let coordinates = Coordinates::parse("com.example:my-mod:1.0.0").unwrap();
+        assert_eq!(
+            (coordinates.group.as_str(), coordinates.artifact.as_str(), coordinates.version.as_str()),
+            ("com.example", "my-mod", "1.0.0")
+        );
+        assert_eq!(coordinates.path(), "com/example/my-mod");
+        for spec in ["com.example:my-mod", "com.example:my-mod:"] {
+            assert_eq!(Coordinates::parse(spec).unwrap().version, "release", "{}", spec);
+        }
+        for spec in ["", "my-mod", ":my-mod", "com.example:", "com.example::1.0", "a:b:c:d"] {
+            assert!(Coordinates::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn tags_are_read_in_order() {
+        assert_eq!(tag_values(METADATA, "version"), ["1.0.0", "1.2.0", "2.0.0-SNAPSHOT"]);
+        assert_eq!(tag_values(METADATA, "release"), ["1.2.0"]);
+        assert!(tag_values(METADATA, "missing").is_empty());
+        assert!(tag_values("<version>1.0", "version").is_empty());
+    }
+
+    #[test]
+    fn versions_are_resolved() {
+        assert_eq!(resolve_version(METADATA, "release").as_deref(), Some("1.2.0"));
+        assert_eq!(resolve_version(METADATA, "latest").as_deref(), Some("2.0.0-SNAPSHOT"));
+        // Without <release> the last listed version is used
+        let without_release = METADATA.replace("<release>1.2.0</release>", "");
+        assert_eq!(resolve_version(&without_release, "release").as_deref(), Some("2.0.0-SNAPSHOT"));
+        let empty_release = METADATA.replace("<release>1.2.0</release>", "<release></release>");
+        assert_eq!(resolve_version(&empty_release, "release").as_deref(), Some("2.0.0-SNAPSHOT"));
+        assert_eq!(resolve_version("<metadata></metadata>", "release"), None);
+    }
+
+    #[test]
+    fn snapshot_jars_are_timestamped() {
+        assert_eq!(versioned_jar_name("my-mod", "1.2.0", None), "my-mod-1.2.0.jar");
+        assert_eq!(
+            versioned_jar_name("my-mod", "2.0.0-SNAPSHOT", Some(SNAPSHOT_METADATA)),
+            "my-mod-2.0.0-20250103.095812-7.jar"
+        );
+        // Repositories without snapshot metadata keep the plain name
+        assert_eq!(versioned_jar_name("my-mod", "2.0.0-SNAPSHOT", None), "my-mod-2.0.0-SNAPSHOT.jar");
+        assert_eq!(
+            versioned_jar_name("my-mod", "2.0.0-SNAPSHOT", Some(METADATA)),
+            "my-mod-2.0.0-SNAPSHOT.jar"
+        );
+    }
+}
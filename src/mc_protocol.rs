@@ -0,0 +1,225 @@
+use std::io::{Error, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Protocol number sent when pinging, -1 lets the server answer with its own version
+pub const ANY_PROTOCOL: i32 = -1;
+/// Packets bigger than this are refused, vanilla uses 2 MiB
+const MAX_PACKET_LENGTH: i32 = 2 * 1024 * 1024;
+
+pub fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+pub fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    write_varint(buffer, value.len() as i32);
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+/// Reads a VarInt from the start of `data`, advancing it
+pub fn read_varint_from(data: &mut &[u8]) -> Option<i32> {
+    let mut value: u32 = 0;
+    for position in 0..5 {
+        let (byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= ((byte & 0x7F) as u32) << (7 * position);
+        if byte & 0x80 == 0 {
+            return Some(value as i32);
+        }
+    }
+    None
+}
+
+pub fn read_string_from(data: &mut &[u8]) -> Option<String> {
+    let length = read_varint_from(data)?;
+    if length < 0 || length as usize > data.len() {
+        return None;
+    }
+    let (text, rest) = data.split_at(length as usize);
+    *data = rest;
+    String::from_utf8(text.to_vec()).ok()
+}
+
+pub async fn read_varint<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<i32> {
+    let mut value: u32 = 0;
+    for position in 0..5 {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7F) as u32) << (7 * position);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(Error::new(ErrorKind::InvalidData, "VarInt is too big"))
+}
+
+/// Writes an uncompressed packet : length, id, payload
+pub async fn write_packet<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    id: i32,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let mut body = Vec::with_capacity(payload.len() + 5);
+    write_varint(&mut body, id);
+    body.extend_from_slice(payload);
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(&body);
+    writer.write_all(&packet).await?;
+    writer.flush().await
+}
+
+/// Reads an uncompressed packet and returns its id and payload
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<(i32, Vec<u8>)> {
+    let length = read_varint(reader).await?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid packet length {}", length),
+        ));
+    }
+    let mut body = vec![0u8; length as usize];
+    reader.read_exact(&mut body).await?;
+    let mut data = body.as_slice();
+    let id = read_varint_from(&mut data)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid packet id"))?;
+    Ok((id, data.to_vec()))
+}
+
+/// First packet sent by a client, `next_state` is 1 for a status ping and 2 for a login
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    pub protocol: i32,
+    pub address: String,
+    pub port: u16,
+    pub next_state: i32,
+}
+
+impl Handshake {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = vec![];
+        write_varint(&mut payload, self.protocol);
+        write_string(&mut payload, &self.address);
+        payload.extend_from_slice(&self.port.to_be_bytes());
+        write_varint(&mut payload, self.next_state);
+        payload
+    }
+
+    pub fn decode(mut payload: &[u8]) -> Option<Self> {
+        let protocol = read_varint_from(&mut payload)?;
+        let address = read_string_from(&mut payload)?;
+        let port = u16::from_be_bytes([*payload.first()?, *payload.get(1)?]);
+        payload = &payload[2..];
+        let next_state = read_varint_from(&mut payload)?;
+        Some(Self {
+            protocol,
+            address,
+            port,
+            next_state,
+        })
+    }
+}
+
+/// Answers the status request and the ping following a status handshake
+pub async fn serve_status<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: &str,
+) -> std::io::Result<()> {
+    loop {
+        let (id, payload) = read_packet(stream).await?;
+        match id {
+            0x00 => {
+                let mut response = vec![];
+                write_string(&mut response, status);
+                write_packet(stream, 0x00, &response).await?;
+            }
+            0x01 => return write_packet(stream, 0x01, &payload).await,
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Refuses a login with a message shown to the player
+pub async fn disconnect<W: AsyncWrite + Unpin>(writer: &mut W, message: &str) -> std::io::Result<()> {
+    let reason = serde_json::json!({ "text": message }).to_string();
+    let mut payload = vec![];
+    write_string(&mut payload, &reason);
+    write_packet(writer, 0x00, &payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: i32) -> Vec<u8> {
+        let mut buffer = vec![];
+        write_varint(&mut buffer, value);
+        buffer
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 255, 25565, 2097151, i32::MAX, -1, i32::MIN] {
+            let buffer = encode(value);
+            let mut data = buffer.as_slice();
+            assert_eq!(read_varint_from(&mut data), Some(value));
+            assert!(data.is_empty());
+        }
+    }
+
+    #[test]
+    fn varint_known_encodings() {
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(127), [0x7F]);
+        assert_eq!(encode(128), [0x80, 0x01]);
+        assert_eq!(encode(25565), [0xDD, 0xC7, 0x01]);
+        assert_eq!(encode(i32::MAX), [0xFF, 0xFF, 0xFF, 0xFF, 0x07]);
+    }
+
+    #[test]
+    fn negative_varint_uses_five_bytes() {
+        assert_eq!(encode(-1), [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        assert_eq!(encode(i32::MIN), [0x80, 0x80, 0x80, 0x80, 0x08]);
+    }
+
+    #[test]
+    fn oversized_varint_is_refused() {
+        let mut data: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        assert_eq!(read_varint_from(&mut data), None);
+
+        let mut reader: &[u8] = &[0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        let error = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(read_varint(&mut reader))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_varint_is_refused() {
+        let mut data: &[u8] = &[0x80, 0x80];
+        assert_eq!(read_varint_from(&mut data), None);
+    }
+
+    #[test]
+    fn handshake_round_trip() {
+        let handshake = Handshake {
+            protocol: 767,
+            address: String::from("play.example.com"),
+            port: 25565,
+            next_state: 2,
+        };
+        let decoded = Handshake::decode(&handshake.encode()).unwrap();
+        assert_eq!(decoded.protocol, 767);
+        assert_eq!(decoded.address, "play.example.com");
+        assert_eq!(decoded.port, 25565);
+        assert_eq!(decoded.next_state, 2);
+    }
+}
@@ -1,14 +1,4 @@
 use inquire::Select;
-const SEARCH_API_END_POINT: &str = "https://api.modrinth.com/v2/search";
-const MOD_LOADERS: &[&str; 7] = &[
-    "fabric",
-    "forge",
-    "quilt",
-    "neoforge",
-    "liteloader",
-    "modloader",
-    "rift",
-];
 const FILTERS: &[&str; 5] = &["relevance", "downloads", "follows", "newest", "updated"];
 use std::path::PathBuf;
 
@@ -17,6 +7,8 @@ use serde_json::json;
 
 use serde_json::Value;
 
+use crate::settings;
+
 #[derive(Serialize, Debug, Clone)]
 #[derive(Default)]
 pub struct ModrinthEntry {
@@ -53,7 +45,7 @@ impl ModrinthSortingFilter {
             None => None,
         }
     }
-    fn get_filter(&self) -> &'static str {
+    pub fn get_filter(&self) -> &'static str {
         match self {
             ModrinthSortingFilter::Relevance => FILTERS[0],
             ModrinthSortingFilter::Downloads => FILTERS[1],
@@ -152,6 +144,7 @@ pub struct ModQuery {
 }
 
 impl ModQuery {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         mod_name : impl Into<String>,
         mod_version : Option<impl Into<String>>,
@@ -164,7 +157,7 @@ impl ModQuery {
         server_side : Option<ServerSide>,
     ) -> Self {
         Self { mod_name: mod_name.into(),
-            mod_version: mod_version.and_then(|version| {Some(version.into())}),
+            mod_version: mod_version.map(|version| version.into()),
             mod_loader,
             max_mod_number,
             project_type,
@@ -222,7 +215,7 @@ impl ModrinthEntry {
         let url = {
             format!(
                 "{}{}&facets={}{}{}{}",
-                SEARCH_API_END_POINT,
+                search_end_point(),
                 query,
                 json!(facets),
                 limit,
@@ -250,41 +243,7 @@ impl ModrinthEntry {
     pub fn display_entries(&self) {
         if let Some(response) = &self.response {
             if let Some(mods) = response.get("hits").and_then(|hits| hits.as_array()) {
-                println!("Found {} entries:", mods.len());
-                for (index, mod_entry) in mods.iter().enumerate() {
-                    let name = mod_entry
-                        .get("title")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Unknown");
-                    let id = mod_entry
-                        .get("project_id")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Unknown");
-                    let project_type = mod_entry
-                        .get("project_type")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Unknown");
-                    let desc = mod_entry
-                        .get("description")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("No description");
-                    let game_versions = mod_entry
-                        .get("versions")
-                        .and_then(|v| v.as_array())
-                        .map(|versions| {
-                            versions
-                                .iter()
-                                .filter_map(|ver| ver.as_str())
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        })
-                        .unwrap_or_else(|| "Unknown".to_string());
-
-                    println!(
-                        "\n{}. Name: {}\n   ID: {}\n   Type: {}\n   Game Versions: {}\n   Description: {}",
-                        index + 1, name, id, project_type, game_versions, desc.replace('\n',"" )
-                    );
-                }
+                print_entries(mods);
             } else {
                 println!("Nothing found in the response!");
             }
@@ -315,15 +274,16 @@ impl ModrinthEntry {
             // Construct the API endpoint
             let url = if mod_id.is_some() {
                 format!(
-                    "https://api.modrinth.com/v2/project/{}/version",
+                    "{}/v2/project/{}/version",
+                    settings::api("modrinth"),
                     mod_id_or_name
                 )
             } else {
                 match (mod_loader.is_some(), version.is_some()) {
-                    (true, true) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"categories:{}\"],[\"versions:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,mod_loader.clone().unwrap(),version.clone().unwrap()),
-                    (true, false) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"categories:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,mod_loader.clone().unwrap()),
-                    (false, true) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"versions:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,version.clone().unwrap()),
-                    (false, false) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"]]",SEARCH_API_END_POINT,mod_id_or_name),
+                    (true, true) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"categories:{}\"],[\"versions:{}\"]]",search_end_point(),mod_id_or_name,mod_loader.clone().unwrap(),version.clone().unwrap()),
+                    (true, false) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"categories:{}\"]]",search_end_point(),mod_id_or_name,mod_loader.clone().unwrap()),
+                    (false, true) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"],[\"versions:{}\"]]",search_end_point(),mod_id_or_name,version.clone().unwrap()),
+                    (false, false) => format!("{}?query=\"{}\"&facets=[[\"project_type:mod\"]]",search_end_point(),mod_id_or_name),
                 }
             };
 
@@ -423,9 +383,9 @@ impl ModrinthEntry {
                                                     "Downloaded: {}",
                                                     full_path.to_string_lossy()
                                                 );
-                                                if dependencies.is_some() {
-                                                    let do_download_dependencies =
-                                                        dependencies.unwrap();
+                                                if let Some(do_download_dependencies) =
+                                                    dependencies
+                                                {
                                                     if do_download_dependencies {
                                                         self.verify_dependencies(
                                                             download_path.clone(),
@@ -487,15 +447,16 @@ impl ModrinthEntry {
             // Construct the API endpoint
             let url = if mod_id.is_some() {
                 format!(
-                    "https://api.modrinth.com/v2/project/{}/version",
+                    "{}/v2/project/{}/version",
+                    settings::api("modrinth"),
                     mod_id_or_name
                 )
             } else {
                 match (mod_loader.is_some(), version.is_some()) {
-                    (true, true) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"categories:{}\"],[\"versions:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,mod_loader.clone().unwrap(),version.clone().unwrap()),
-                    (true, false) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"categories:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,mod_loader.clone().unwrap()),
-                    (false, true) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"versions:{}\"]]",SEARCH_API_END_POINT,mod_id_or_name,version.clone().unwrap()),
-                    (false, false) => format!("{}?query={}&facets=[[\"project_type:mod\"]]",SEARCH_API_END_POINT,mod_id_or_name),
+                    (true, true) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"categories:{}\"],[\"versions:{}\"]]",search_end_point(),mod_id_or_name,mod_loader.clone().unwrap(),version.clone().unwrap()),
+                    (true, false) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"categories:{}\"]]",search_end_point(),mod_id_or_name,mod_loader.clone().unwrap()),
+                    (false, true) => format!("{}?query={}&facets=[[\"project_type:mod\"],[\"versions:{}\"]]",search_end_point(),mod_id_or_name,version.clone().unwrap()),
+                    (false, false) => format!("{}?query={}&facets=[[\"project_type:mod\"]]",search_end_point(),mod_id_or_name),
                 }
             };
 
@@ -621,9 +582,9 @@ impl ModrinthEntry {
                                                     "Downloaded: {}",
                                                     full_path.to_string_lossy()
                                                 );
-                                                if dependencies.is_some() {
-                                                    let do_download_dependencies =
-                                                        dependencies.unwrap();
+                                                if let Some(do_download_dependencies) =
+                                                    dependencies
+                                                {
                                                     if do_download_dependencies {
                                                         self.verify_server_dependencies(
                                                             download_path.clone(),
@@ -661,19 +622,18 @@ impl ModrinthEntry {
         if let Some(projects) = data.as_array() {
             for project in projects {
                 let loaders = project["loaders"].as_array().unwrap();
-                if self.mod_loader.is_some() {
-                    if !loaders
+                if self.mod_loader.is_some()
+                    && !loaders
                         .iter()
                         .any(|l| l.as_str() == Some(self.mod_loader.clone().unwrap().as_str()))
-                    {
-                        continue;
-                    }
+                {
+                    continue;
                 }
                 if let Some(version_str) = version.clone() {
                     let game_versions = project["game_versions"].as_array().unwrap();
                     if game_versions
                         .iter()
-                        .any(|v| v.as_str().unwrap() != &version_str)
+                        .any(|v| v.as_str().unwrap() != version_str)
                     {
                         continue;
                     }
@@ -713,11 +673,7 @@ impl ModrinthEntry {
         }
         for project_id in project_ids {
             let mod_loader_copy = self.mod_loader.clone();
-            let mod_loader_target = if let Some(mod_loader) = mod_loader_copy {
-                Some(format!("{}", mod_loader))
-            } else {
-                None
-            };
+            let mod_loader_target = mod_loader_copy.map(|mod_loader| mod_loader.to_string());
 
             let target_id: &mut Option<String> = &mut Some(project_id.clone());
             // Box the future to allow recursion
@@ -754,11 +710,7 @@ impl ModrinthEntry {
         }
         for project_id in project_ids {
             let mod_loader_copy = self.mod_loader.clone();
-            let mod_loader_target = if let Some(mod_loader) = mod_loader_copy {
-                Some(format!("{}", mod_loader))
-            } else {
-                None
-            };
+            let mod_loader_target = mod_loader_copy.map(|mod_loader| mod_loader.to_string());
 
             let target_id: &mut Option<String> = &mut Some(project_id.clone());
             // Box the future to allow recursion
@@ -766,10 +718,7 @@ impl ModrinthEntry {
                 target_id,
                 None,
                 mod_loader_target,
-                match self.mod_version.clone() {
-                    Some(v) => Some(format!("{}", v)),
-                    None => None,
-                },
+                self.mod_version.clone().map(|v| format!("{}", v)),
                 download_path.clone(),
                 Some(true),
             ));
@@ -777,3 +726,72 @@ impl ModrinthEntry {
         }
     }
 }
+
+/// Finds the Modrinth project owning a file from its hash (sha1 or sha512)
+pub async fn project_from_hash(hash: &str, algorithm: &str) -> Option<Value> {
+    let version: Value = reqwest::get(format!(
+        "{}/v2/version_file/{}?algorithm={}",
+        settings::api("modrinth"),
+        hash,
+        algorithm
+    ))
+    .await
+    .ok()?
+    .error_for_status()
+    .ok()?
+    .json()
+    .await
+    .ok()?;
+    let project_id = version["project_id"].as_str()?;
+    reqwest::get(format!("{}/v2/project/{}", settings::api("modrinth"), project_id))
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// Prints search hits, other sources give hits with the same fields
+pub fn print_entries(mods: &[Value]) {
+    println!("Found {} entries:", mods.len());
+    for (index, mod_entry) in mods.iter().enumerate() {
+        let name = mod_entry
+            .get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown");
+        let id = mod_entry
+            .get("project_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown");
+        let project_type = mod_entry
+            .get("project_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown");
+        let desc = mod_entry
+            .get("description")
+            .and_then(|v| v.as_str())
+            .unwrap_or("No description");
+        let game_versions = mod_entry
+            .get("versions")
+            .and_then(|v| v.as_array())
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter_map(|ver| ver.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        println!(
+            "\n{}. Name: {}\n   ID: {}\n   Type: {}\n   Game Versions: {}\n   Description: {}",
+            index + 1, name, id, project_type, game_versions, desc.replace('\n',"" )
+        );
+    }
+}
+
+fn search_end_point() -> String {
+    format!("{}/v2/search", settings::api("modrinth"))
+}
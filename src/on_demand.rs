@@ -0,0 +1,323 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+use crate::mc_protocol::{self, Handshake};
+use crate::router;
+use crate::server_properties::ServerProperties;
+use crate::server_status::{self, StatusTarget, DEFAULT_SERVER_PORT};
+use crate::session;
+use crate::shutdown;
+use crate::supervisor::{ConsoleMode, LaunchOptions, ShutdownSignals};
+
+pub const DEFAULT_SLEEPING_MOTD: &str = "§7Sleeping, join to wake the server up";
+const STARTING_MOTD: &str = "§eStarting, join again in a moment";
+const STARTING_MESSAGE: &str = "The server is starting, join again in a moment";
+/// Clients opening a connection without a handshake are dropped after it
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const READY_POLL: Duration = Duration::from_secs(2);
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Sleeping,
+    Starting,
+    Running,
+}
+
+/// Answers on the public port while the server is stopped, starts it on the
+/// first login and stops it again once nobody played for `idle`
+#[derive(Debug)]
+pub struct OnDemand {
+    server_path: PathBuf,
+    public_port: u16,
+    /// Port given to the server with `--port`, only reached through the proxy
+    backend_port: u16,
+    motd: String,
+    idle: Duration,
+    xmx: Option<String>,
+    xms: Option<String>,
+    options: LaunchOptions,
+    /// Sends a PROXY protocol v2 header so the server sees the address of the players
+    proxy_protocol: bool,
+    state: Mutex<State>,
+    wake: Notify,
+    /// Asks the supervisor running the server in this process to stop it
+    stop_request: Arc<Notify>,
+    /// Proxied connections, the server is never stopped while one is open
+    connections: AtomicUsize,
+}
+
+impl OnDemand {
+    /// The public port is the `server-port` of the server, the server itself listens on `backend_port`
+    pub fn build(
+        server_path: PathBuf,
+        backend_port: Option<u16>,
+        motd: Option<String>,
+        idle_minutes: u64,
+        xmx: Option<String>,
+        xms: Option<String>,
+        options: LaunchOptions,
+    ) -> Result<Self, String> {
+        if session::read_metadata(&server_path).is_none() {
+            return Err(format!(
+                "No MCA.json found in '{}', create the server first",
+                server_path.to_string_lossy()
+            ));
+        }
+        if let Some(pid) = session::running_pid(&server_path) {
+            return Err(format!(
+                "Server '{}' is already running (pid {}), stop it first",
+                server_path.to_string_lossy(),
+                pid
+            ));
+        }
+        let public_port = ServerProperties::load(&server_path)
+            .get_u16("server-port")
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        let backend_port = backend_port.unwrap_or(public_port.wrapping_add(1));
+        if backend_port == public_port {
+            return Err(format!(
+                "The backend port must differ from the public port {}",
+                public_port
+            ));
+        }
+        let mut options = options;
+        options.console = ConsoleMode::Socket;
+        options.server_args = vec!["--port".to_owned(), backend_port.to_string()];
+        let stop_request = Arc::new(Notify::new());
+        options.stop_request = Some(stop_request.clone());
+        Ok(Self {
+            server_path,
+            public_port,
+            backend_port,
+            motd: motd.unwrap_or(DEFAULT_SLEEPING_MOTD.to_owned()),
+            idle: Duration::from_secs(idle_minutes * 60),
+            xmx,
+            xms,
+            options,
+            proxy_protocol: false,
+            state: Mutex::new(State::Sleeping),
+            wake: Notify::new(),
+            stop_request,
+            connections: AtomicUsize::new(0),
+        })
+    }
+
+    /// Sends a PROXY protocol v2 header before each forwarded connection
+    pub fn with_proxy_protocol(mut self, proxy_protocol: bool) -> Self {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
+    fn state(&self) -> State {
+        *self.state.lock().unwrap()
+    }
+
+    fn set_state(&self, state: State) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    /// Listens until Ctrl-C or until the server stops by itself
+    pub async fn run(self: Arc<Self>) {
+        let listener = match TcpListener::bind(("0.0.0.0", self.public_port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                println!("❌ Unable to listen on port {} : {}", self.public_port, e);
+                return;
+            }
+        };
+        println!(
+            "✅ Listening on port {}, the server will use port {}",
+            self.public_port, self.backend_port
+        );
+        let accepting = self.clone();
+        let accept_task = tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let proxy = accepting.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = proxy.handle(client).await {
+                        println!("⚠️ {}", e);
+                    }
+                });
+            }
+        });
+        self.cycle().await;
+        accept_task.abort();
+    }
+
+    /// Sleeps, starts the server when woken up and stops it when idle, until the server stops by itself
+    async fn cycle(&self) {
+        loop {
+            self.set_state(State::Sleeping);
+            println!("➡️ Server asleep, waiting for a player to join");
+            // Once the supervisor ran, SIGTERM no longer ends the process by itself
+            let mut signals = ShutdownSignals::new();
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = signals.recv() => return,
+            }
+
+            self.set_state(State::Starting);
+            println!("🚀 A player is joining, starting the server");
+            let mut server = tokio::spawn(session::start_existing_server(
+                self.server_path.clone(),
+                self.xmx.clone(),
+                self.xms.clone(),
+                Some(false),
+                self.options.clone(),
+            ));
+            // The server refuses connections without the header once proxy-protocol is on
+            let target = StatusTarget::direct("127.0.0.1", self.backend_port)
+                .with_proxy_protocol(self.proxy_protocol);
+            loop {
+                tokio::select! {
+                    _ = &mut server => {
+                        println!("❌ The server stopped before accepting players");
+                        return;
+                    }
+                    _ = tokio::time::sleep(READY_POLL) => {
+                        if server_status::ping(&target).await.is_ok() {
+                            break;
+                        }
+                    }
+                }
+            }
+            self.set_state(State::Running);
+            println!("✅ Server ready, players are forwarded to it");
+
+            let mut idle_since = Instant::now();
+            loop {
+                tokio::select! {
+                    _ = &mut server => {
+                        println!("➡️ The server stopped, no longer listening on port {}", self.public_port);
+                        return;
+                    }
+                    _ = tokio::time::sleep(IDLE_POLL) => {
+                        let online = match server_status::ping(&target).await {
+                            Ok(status) => status.online_players,
+                            // Restarting or hanging, leave it to the supervisor
+                            Err(_) => continue,
+                        };
+                        if online > 0 || self.connections.load(Ordering::SeqCst) > 0 {
+                            idle_since = Instant::now();
+                        } else if idle_since.elapsed() >= self.idle {
+                            break;
+                        }
+                    }
+                }
+            }
+            println!(
+                "➡️ No player for {}, stopping the server",
+                shutdown::format_delay(self.idle.as_secs())
+            );
+            // Refuse logins until the server is gone
+            self.set_state(State::Starting);
+            // The supervisor sends `stop` and kills the server if it hangs
+            self.stop_request.notify_one();
+            let _ = server.await;
+        }
+    }
+
+    async fn handle(&self, mut client: TcpStream) -> Result<(), String> {
+        let _ = client.set_nodelay(true);
+        let (id, payload) =
+            tokio::time::timeout(HANDSHAKE_TIMEOUT, mc_protocol::read_packet(&mut client))
+                .await
+                .map_err(|_| String::from("Connection without handshake dropped"))?
+                .map_err(|e| format!("Invalid handshake : {}", e))?;
+        let handshake = Handshake::decode(&payload)
+            .filter(|_| id == 0x00)
+            .ok_or(String::from("Invalid handshake"))?;
+
+        match (self.state(), handshake.next_state) {
+            (State::Running, _) => self.forward(client, &payload).await,
+            (state, 1) => self.answer_status(client, &handshake, state).await,
+            // 3 is a transfer from another server
+            (state, 2 | 3) => self.answer_login(client, state).await,
+            (_, other) => Err(format!("Unknown handshake state {}", other)),
+        }
+    }
+
+    /// Replays the handshake to the server and forwards the rest of the connection
+    async fn forward(&self, mut client: TcpStream, handshake: &[u8]) -> Result<(), String> {
+        let mut server = TcpStream::connect(("127.0.0.1", self.backend_port))
+            .await
+            .map_err(|e| {
+                format!(
+                    "Unable to reach the server on port {} : {}",
+                    self.backend_port, e
+                )
+            })?;
+        let _ = server.set_nodelay(true);
+        if self.proxy_protocol {
+            let addresses = client.peer_addr().and_then(|source| Ok((source, client.local_addr()?)));
+            if let Ok((source, destination)) = addresses {
+                server
+                    .write_all(&router::proxy_header(source, destination))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        mc_protocol::write_packet(&mut server, 0x00, handshake)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Status request then ping, answered with the sleeping MOTD
+    async fn answer_status(
+        &self,
+        mut client: TcpStream,
+        handshake: &Handshake,
+        state: State,
+    ) -> Result<(), String> {
+        let motd = if state == State::Sleeping {
+            self.motd.as_str()
+        } else {
+            STARTING_MOTD
+        };
+        let max_players = ServerProperties::load(&self.server_path)
+            .get_or_default("max-players")
+            .and_then(|max| max.parse::<i64>().ok())
+            .unwrap_or(20);
+        let status = json!({
+            // Same protocol as the client so it is not shown as incompatible
+            "version": { "name": "MCT", "protocol": handshake.protocol },
+            "players": { "max": max_players, "online": 0 },
+            "description": { "text": motd },
+        });
+        // Clients closing after the status response are not an error
+        let _ = mc_protocol::serve_status(&mut client, &status.to_string()).await;
+        Ok(())
+    }
+
+    /// Wakes the server up on a Login Start and asks the player to join again
+    async fn answer_login(&self, mut client: TcpStream, state: State) -> Result<(), String> {
+        // Port scanners stop at the handshake, a player sends its name
+        let (id, payload) =
+            tokio::time::timeout(HANDSHAKE_TIMEOUT, mc_protocol::read_packet(&mut client))
+                .await
+                .map_err(|_| String::from("Login without player name dropped"))?
+                .map_err(|e| format!("Invalid login : {}", e))?;
+        let name = mc_protocol::read_string_from(&mut payload.as_slice())
+            .filter(|_| id == 0x00)
+            .ok_or(String::from("Invalid login"))?;
+        if state == State::Sleeping {
+            println!("➡️ {} wants to join", name);
+            self.wake.notify_one();
+        }
+        mc_protocol::disconnect(&mut client, STARTING_MESSAGE)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
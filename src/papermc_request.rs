@@ -1,17 +1,20 @@
 use core::panic;
 use inquire::Select;
-use std::{fs, io::Read, path::PathBuf, process::Stdio};
+use std::{fs, io::Read, path::PathBuf};
+
+use crate::session;
+use crate::settings;
+use crate::supervisor::{LaunchOptions, Supervisor};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{Number, Value};
 
-const PAPERMC_API: &str = "https://api.papermc.io";
 /// arg 1 : Game version
 const PAPERMC_API_BUILDS: &[&str; 2] = &["/v2/projects/paper/versions/", "/builds"];
 /// arg 1 : Project | arg 2 : game version | arg 3 : build | arg 4 : download (ex : paper-1.21.4-1.jar)
 const PAPERMC_API_DOWNLOAD_BUILD: &[&str; 4] =
     &["/v2/projects/", "/versions/", "/builds/", "/downloads/"];
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct PaperMCRequest {
     project: Option<String>,
     game_version: Option<String>,
@@ -44,19 +47,6 @@ impl PaperMCRequest {
     }
 }
 
-impl Default for PaperMCRequest {
-    fn default() -> Self {
-        Self {
-            project: None,
-            game_version: None,
-            build: None,
-            download: None,
-            response: None,
-            server_path: None,
-            jar_path: None,
-        }
-    }
-}
 impl PaperMCRequest {
     pub async fn check_build(&mut self, game_version: Option<String>, build: Option<String>) {
         if game_version.is_none() {
@@ -66,7 +56,7 @@ impl PaperMCRequest {
         let url = if build.is_none() {
             format!(
                 "{}{}{}{}",
-                PAPERMC_API,
+                settings::api("papermc"),
                 PAPERMC_API_BUILDS[0],
                 game_version.clone().unwrap(),
                 PAPERMC_API_BUILDS[1]
@@ -74,7 +64,7 @@ impl PaperMCRequest {
         } else {
             format!(
                 "{}{}{}{}{}",
-                PAPERMC_API,
+                settings::api("papermc"),
                 PAPERMC_API_BUILDS[0],
                 game_version.clone().unwrap(),
                 PAPERMC_API_BUILDS[1],
@@ -109,18 +99,13 @@ impl PaperMCRequest {
                         )
                         .prompt()
                         .unwrap();
-                        let selected_build = builds.into_iter().find(|b| {
-                            if b["downloads"].as_object().unwrap()["application"]
+                        let selected_build = builds.iter().find(|b| {
+                            b["downloads"].as_object().unwrap()["application"]
                                 .as_object()
                                 .unwrap()["name"]
                                 .as_str()
                                 .unwrap()
                                 == selected_build
-                            {
-                                true
-                            } else {
-                                false
-                            }
                         });
                         selected_build.iter().for_each(|b| {
                             self.build = Some(b["build"].as_number().unwrap().as_i64().unwrap());
@@ -135,7 +120,6 @@ impl PaperMCRequest {
                                     .to_owned(),
                             );
                         });
-                        return;
                     } else {
                         panic!(
                             "❌ No builds found from response !\n    ⏬ Response from url {} ⏬\n{:#?}",
@@ -146,7 +130,6 @@ impl PaperMCRequest {
             }
             Err(e) => {
                 println!("{}", e);
-                return;
             }
         }
     }
@@ -156,17 +139,17 @@ impl PaperMCRequest {
     pub async fn download_build(&mut self, server_path: PathBuf) {
         if self.project.is_some() {
             if self.game_version.is_some() {
-                if self.build.is_some() {
+                if let Some(build) = self.build {
                     if self.download.is_some() {
                         let download_url = format!(
                             "{}{}{}{}{}{}{}{}{}",
-                            PAPERMC_API,
+                            settings::api("papermc"),
                             PAPERMC_API_DOWNLOAD_BUILD[0],
                             self.project.clone().unwrap(),
                             PAPERMC_API_DOWNLOAD_BUILD[1],
                             self.game_version.clone().unwrap(),
                             PAPERMC_API_DOWNLOAD_BUILD[2],
-                            self.build.clone().unwrap(),
+                            build,
                             PAPERMC_API_DOWNLOAD_BUILD[3],
                             self.download.clone().unwrap(),
                         );
@@ -210,7 +193,13 @@ impl PaperMCRequest {
 }
 
 impl PaperMCRequest {
-    pub fn start_server(&mut self, xmx: Option<String>, xms: Option<String>, is_gui: Option<bool>) {
+    pub async fn start_server(
+        &mut self,
+        xmx: Option<String>,
+        xms: Option<String>,
+        is_gui: Option<bool>,
+        options: LaunchOptions,
+    ) {
         let mut java_args: Vec<String> = vec!["-jar".to_owned()];
         java_args.push(self.download.clone().unwrap());
         if let Some(xms) = xms {
@@ -222,7 +211,7 @@ impl PaperMCRequest {
         // Ensure the EULA is accepted
         let eula_path = self.server_path.as_ref().unwrap().join("eula.txt");
 
-        if let Err(_) = fs::write(&eula_path, "eula=true") {
+        if fs::write(&eula_path, "eula=true").is_err() {
             if let Ok(eula_content) = fs::read_to_string(&eula_path) {
                 let updated_eula = eula_content.replace("false", "true");
                 let _ = fs::write(&eula_path, updated_eula);
@@ -233,26 +222,11 @@ impl PaperMCRequest {
             java_args.push("-nogui".to_owned());
         }
 
-        let data_path = self.server_path.clone().unwrap().join("MCA.json");
-        fs::write(
-            data_path,
-            serde_json::to_string_pretty(&self).unwrap().as_bytes(),
-        )
-        .unwrap();
-        match std::process::Command::new("java")
-            .args(&java_args)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .current_dir(self.server_path.clone().unwrap())
-            .spawn()
-        {
-            Ok(_) => {}
-            Err(e) => {
-                println!("➡️ Command: java {:?}", java_args);
-                panic!("    ❌➡️{}", e);
-            }
-        };
+        session::write_metadata(self.server_path.as_ref().unwrap(), &self).unwrap();
+        Supervisor::build(self.server_path.clone().unwrap(), java_args)
+            .with_options(options)
+            .run()
+            .await;
     }
 }
 /// Check MCA.json and sets the Paper server values if found
@@ -276,3 +250,20 @@ impl PaperMCRequest {
         }
     }
 }
+
+/// Highest Paper build of a game version
+pub async fn latest_build(game_version: &str) -> Option<i64> {
+    let url = format!(
+        "{}{}{}{}",
+        settings::api("papermc"),
+        PAPERMC_API_BUILDS[0],
+        game_version,
+        PAPERMC_API_BUILDS[1]
+    );
+    let json: Value = reqwest::get(url).await.ok()?.json().await.ok()?;
+    json["builds"]
+        .as_array()?
+        .iter()
+        .filter_map(|b| b["build"].as_i64())
+        .max()
+}
@@ -0,0 +1,364 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::server_properties::ServerProperties;
+use crate::session;
+use crate::settings;
+
+const DEFAULT_OP_LEVEL: u8 = 4;
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+/// Date format used by the server in ban lists
+const BAN_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerList {
+    Whitelist,
+    Ops,
+    BannedPlayers,
+    BannedIps,
+}
+
+impl PlayerList {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            PlayerList::Whitelist => "whitelist.json",
+            PlayerList::Ops => "ops.json",
+            PlayerList::BannedPlayers => "banned-players.json",
+            PlayerList::BannedIps => "banned-ips.json",
+        }
+    }
+
+    /// Field identifying an entry
+    fn key(&self) -> &'static str {
+        match self {
+            PlayerList::BannedIps => "ip",
+            _ => "name",
+        }
+    }
+
+    fn add_command(&self, name: &str, options: &EntryOptions) -> String {
+        match self {
+            PlayerList::Whitelist => format!("whitelist add {}", name),
+            PlayerList::Ops => format!("op {}", name),
+            PlayerList::BannedPlayers => format!("ban {} {}", name, options.reason()).trim().to_owned(),
+            PlayerList::BannedIps => format!("ban-ip {} {}", name, options.reason()).trim().to_owned(),
+        }
+    }
+
+    fn remove_command(&self, name: &str) -> String {
+        match self {
+            PlayerList::Whitelist => format!("whitelist remove {}", name),
+            PlayerList::Ops => format!("deop {}", name),
+            PlayerList::BannedPlayers => format!("pardon {}", name),
+            PlayerList::BannedIps => format!("pardon-ip {}", name),
+        }
+    }
+}
+
+/// Optional fields of ops and ban entries
+#[derive(Debug, Clone, Default)]
+pub struct EntryOptions {
+    pub reason: Option<String>,
+    pub level: Option<u8>,
+}
+
+impl EntryOptions {
+    fn reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or(DEFAULT_BAN_REASON)
+    }
+
+    /// The reason ends up in a console command, a line break would run a second one
+    fn validate(&self) -> Result<(), String> {
+        match &self.reason {
+            Some(reason) if reason.chars().any(char::is_control) => {
+                Err(String::from("The reason cannot contain control characters"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A player name with its UUID
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub uuid: String,
+    pub name: String,
+}
+
+impl Profile {
+    /// Uses the Mojang API in online mode, the offline UUID otherwise
+    pub async fn resolve(server_path: &Path, name: &str) -> Result<Self, String> {
+        if ServerProperties::load(server_path).get_bool("online-mode") {
+            Self::fetch(name).await
+        } else {
+            Ok(Self::offline(name))
+        }
+    }
+
+    pub async fn fetch(name: &str) -> Result<Self, String> {
+        if !is_valid_name(name) {
+            return Err(format!("'{}' is not a valid player name", name));
+        }
+        let url = format!("{}/users/profiles/minecraft/{}", settings::api("mojang"), name);
+        let response = reqwest::get(&url)
+            .await
+            .map_err(|e| format!("Error while reaching the Mojang API : {}", e))?;
+        if response.status() == reqwest::StatusCode::NO_CONTENT
+            || response.status() == reqwest::StatusCode::NOT_FOUND
+        {
+            return Err(format!("No Minecraft account named '{}'", name));
+        }
+        let profile: Value = response
+            .error_for_status()
+            .map_err(|e| format!("Error while reaching the Mojang API : {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid Mojang API response : {}", e))?;
+        match (profile["id"].as_str(), profile["name"].as_str()) {
+            (Some(id), Some(name)) if id.len() == 32 => Ok(Self {
+                uuid: hyphenate(id),
+                name: name.to_owned(),
+            }),
+            _ => Err(String::from("Invalid Mojang API response")),
+        }
+    }
+
+    /// UUID given by the server to players when online-mode is false
+    pub fn offline(name: &str) -> Self {
+        let mut bytes = md5::compute(format!("OfflinePlayer:{}", name)).0;
+        // Name based UUID, version 3 with the IETF variant
+        bytes[6] = (bytes[6] & 0x0f) | 0x30;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        Self {
+            uuid: hyphenate(&hex),
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// Minecraft account names, 1 to 16 letters, digits or underscores
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Refuses what could not be a player (or an IP) before it reaches an URL or the console
+fn validate(list: PlayerList, name: &str) -> Result<(), String> {
+    match list {
+        PlayerList::BannedIps if name.parse::<std::net::IpAddr>().is_err() => {
+            Err(format!("'{}' is not an IP address", name))
+        }
+        PlayerList::BannedIps => Ok(()),
+        _ if !is_valid_name(name) => Err(format!("'{}' is not a valid player name", name)),
+        _ => Ok(()),
+    }
+}
+
+fn hyphenate(id: &str) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        &id[0..8],
+        &id[8..12],
+        &id[12..16],
+        &id[16..20],
+        &id[20..32]
+    )
+}
+
+fn read_list(server_path: &Path, list: PlayerList) -> Result<Vec<Value>, String> {
+    let path = server_path.join(list.file_name());
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Error while reading {} : {}", list.file_name(), e))?;
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    serde_json::from_str::<Vec<Value>>(&content)
+        .map_err(|e| format!("Invalid {} : {}", list.file_name(), e))
+}
+
+fn write_list(server_path: &Path, list: PlayerList, entries: &[Value]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(entries).unwrap();
+    fs::write(server_path.join(list.file_name()), content)
+        .map_err(|e| format!("Error while writting {} : {}", list.file_name(), e))
+}
+
+fn matches(entry: &Value, list: PlayerList, name: &str) -> bool {
+    entry[list.key()]
+        .as_str()
+        .is_some_and(|value| value.eq_ignore_ascii_case(name))
+}
+
+async fn new_entry(
+    server_path: &Path,
+    list: PlayerList,
+    name: &str,
+    options: &EntryOptions,
+) -> Result<Value, String> {
+    let ban = || {
+        json!({
+            "created": chrono::Local::now().format(BAN_DATE_FORMAT).to_string(),
+            "source": "Server",
+            "expires": "forever",
+            "reason": options.reason(),
+        })
+    };
+    if list == PlayerList::BannedIps {
+        let mut entry = json!({ "ip": name });
+        entry.as_object_mut().unwrap().extend(ban().as_object().unwrap().clone());
+        return Ok(entry);
+    }
+    let profile = Profile::resolve(server_path, name).await?;
+    let mut entry = json!({ "uuid": profile.uuid, "name": profile.name });
+    let object = entry.as_object_mut().unwrap();
+    match list {
+        PlayerList::Ops => {
+            object.insert(
+                String::from("level"),
+                json!(options.level.unwrap_or(DEFAULT_OP_LEVEL)),
+            );
+            object.insert(String::from("bypassesPlayerLimit"), json!(false));
+        }
+        PlayerList::BannedPlayers => object.extend(ban().as_object().unwrap().clone()),
+        _ => {}
+    }
+    Ok(entry)
+}
+
+/// Adds a player (or an IP) to a list, through the console when the server is running
+pub async fn add(server_path: &Path, list: PlayerList, name: &str, options: &EntryOptions) {
+    if let Err(e) = validate(list, name).and_then(|_| options.validate()) {
+        println!("❌ {}", e);
+        return;
+    }
+    if session::is_running(server_path).await {
+        // The op command always gives the op-permission-level of server.properties
+        if list == PlayerList::Ops && options.level.is_some() {
+            println!("❌ Server is running, the op level can only be set while it is stopped");
+            return;
+        }
+        run_command(server_path, &list.add_command(name, options)).await;
+        return;
+    }
+    let result = async {
+        let mut entries = read_list(server_path, list)?;
+        let entry = new_entry(server_path, list, name, options).await?;
+        // The UUID is the real identity, a renamed player replaces its old entry
+        entries.retain(|e| {
+            !matches(e, list, name) && (e["uuid"].is_null() || e["uuid"] != entry["uuid"])
+        });
+        entries.push(entry);
+        write_list(server_path, list, &entries)
+    }
+    .await;
+    match result {
+        Ok(_) => println!("✅ {} added to {}", name, list.file_name()),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+pub async fn remove(server_path: &Path, list: PlayerList, name: &str) {
+    if let Err(e) = validate(list, name) {
+        println!("❌ {}", e);
+        return;
+    }
+    if session::is_running(server_path).await {
+        run_command(server_path, &list.remove_command(name)).await;
+        return;
+    }
+    let result = read_list(server_path, list).and_then(|mut entries| {
+        let count = entries.len();
+        entries.retain(|e| !matches(e, list, name));
+        if entries.len() == count {
+            return Err(format!("{} is not in {}", name, list.file_name()));
+        }
+        write_list(server_path, list, &entries)
+    });
+    match result {
+        Ok(_) => println!("✅ {} removed from {}", name, list.file_name()),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+pub fn print_list(server_path: &Path, list: PlayerList) {
+    let entries = match read_list(server_path, list) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    if entries.is_empty() {
+        println!("➡️ {} is empty", list.file_name());
+        return;
+    }
+    for entry in entries {
+        let mut line = entry[list.key()].as_str().unwrap_or_default().to_owned();
+        if let Some(uuid) = entry["uuid"].as_str() {
+            line.push_str(&format!(" ({})", uuid));
+        }
+        if let Some(level) = entry["level"].as_u64() {
+            line.push_str(&format!(" level {}", level));
+        }
+        if let Some(reason) = entry["reason"].as_str() {
+            line.push_str(&format!(" : {}", reason));
+        }
+        println!("{}", line);
+    }
+}
+
+async fn run_command(server_path: &Path, command: &str) {
+    match session::send_command(server_path, command).await {
+        Ok(output) => {
+            println!("✅ Server is running, sent `{}`", command);
+            if !output.trim().is_empty() {
+                println!("{}", output.trim());
+            }
+        }
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_names() {
+        for name in ["Notch", "a", "jeb_", "Player_123456789"] {
+            assert!(is_valid_name(name), "{}", name);
+        }
+        for name in ["", "Player_1234567890", "bad name", "x\nstop", "../x", "é"] {
+            assert!(!is_valid_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn banned_ips_need_an_address() {
+        assert!(validate(PlayerList::BannedIps, "192.168.1.2").is_ok());
+        assert!(validate(PlayerList::BannedIps, "::1").is_ok());
+        assert!(validate(PlayerList::BannedIps, "Notch").is_err());
+    }
+
+    #[test]
+    fn offline_uuids_match_the_server() {
+        assert_eq!(Profile::offline("Notch").uuid, "b50ad385-829d-3141-a216-7e7d7539ba7f");
+    }
+
+    #[test]
+    fn ban_reasons_stay_on_one_line() {
+        let options = |reason: &str| EntryOptions {
+            reason: Some(reason.to_owned()),
+            level: None,
+        };
+        assert!(options("Griefing the spawn").validate().is_ok());
+        assert!(EntryOptions::default().validate().is_ok());
+        for reason in ["x\nop Griefer", "x\rstop", "x\0", "x\u{1b}[2J"] {
+            assert!(options(reason).validate().is_err(), "{:?}", reason);
+        }
+    }
+}
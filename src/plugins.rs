@@ -0,0 +1,659 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use inquire::Select;
+use serde_json::{json, Value};
+
+use crate::content::{self, ContentManifest, InstalledContent};
+use crate::github;
+use crate::hangar;
+use crate::maven;
+use crate::session;
+use crate::settings;
+use crate::spiget;
+
+pub const PLUGINS_DIR: &str = "plugins";
+pub const MODS_DIR: &str = "mods";
+pub const SOURCES: [&str; 6] = ["modrinth", "hangar", "spigot", "github", "maven", "url"];
+
+/// Where plugins, and mods for the sources not tied to a loader, are downloaded from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginSource {
+    Modrinth,
+    Hangar,
+    /// SpigotMC resources, through the Spiget API
+    Spigot,
+    /// Release assets of a repository, ex : `github:owner/repo`
+    GitHub,
+    /// Artifacts of the configured repositories, ex : `maven:group:artifact:version`
+    Maven,
+    /// A file and its expected hash
+    Url,
+}
+
+impl PluginSource {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "modrinth" => Some(PluginSource::Modrinth),
+            "hangar" => Some(PluginSource::Hangar),
+            "spigot" | "spiget" => Some(PluginSource::Spigot),
+            "github" => Some(PluginSource::GitHub),
+            "maven" => Some(PluginSource::Maven),
+            "url" => Some(PluginSource::Url),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PluginSource::Modrinth => "modrinth",
+            PluginSource::Hangar => "hangar",
+            PluginSource::Spigot => "spigot",
+            PluginSource::GitHub => "github",
+            PluginSource::Maven => "maven",
+            PluginSource::Url => "url",
+        }
+    }
+
+    /// Source given by the id itself, ex : `github:owner/repo` or `https://host/file.jar`
+    pub fn from_id(id: &str) -> Option<(Self, String)> {
+        if let Some(repository) = id.strip_prefix("github:") {
+            Some((PluginSource::GitHub, repository.to_owned()))
+        } else if let Some(artifact) = id.strip_prefix("maven:") {
+            Some((PluginSource::Maven, artifact.to_owned()))
+        } else if id.starts_with("https://") || id.starts_with("http://") {
+            Some((PluginSource::Url, id.to_owned()))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the files depend on the platform of the server, others install as is
+    fn has_loaders(&self) -> bool {
+        !matches!(
+            self,
+            PluginSource::GitHub | PluginSource::Maven | PluginSource::Url
+        )
+    }
+}
+
+/// Expected hash of a downloaded file
+#[derive(Debug, Clone)]
+pub enum FileHash {
+    Sha1(String),
+    Sha256(String),
+    Sha512(String),
+}
+
+impl FileHash {
+    /// `sha1:<hex>`, `sha256:<hex>` or `sha512:<hex>`, the algorithm is guessed from the length without prefix
+    pub fn parse(hash: &str) -> Option<Self> {
+        let (algorithm, hex) = hash.split_once(':').unwrap_or(("", hash));
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match (algorithm.to_lowercase().as_str(), hex.len()) {
+            ("sha1", 40) | ("", 40) => Some(FileHash::Sha1(hex.to_lowercase())),
+            ("sha256", 64) | ("", 64) => Some(FileHash::Sha256(hex.to_lowercase())),
+            ("sha512", 128) | ("", 128) => Some(FileHash::Sha512(hex.to_lowercase())),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, content: &[u8]) -> bool {
+        match self {
+            FileHash::Sha1(hash) => content::sha1_hex(content) == hash.to_lowercase(),
+            FileHash::Sha256(hash) => content::sha256_hex(content) == hash.to_lowercase(),
+            FileHash::Sha512(hash) => content::sha512_hex(content) == hash.to_lowercase(),
+        }
+    }
+}
+
+impl std::fmt::Display for FileHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileHash::Sha1(hash) => write!(f, "sha1:{}", hash),
+            FileHash::Sha256(hash) => write!(f, "sha256:{}", hash),
+            FileHash::Sha512(hash) => write!(f, "sha512:{}", hash),
+        }
+    }
+}
+
+/// Sent with the requests of a download
+#[derive(Clone)]
+pub enum Credentials {
+    Bearer(String),
+    /// Username and password
+    Basic(String, Option<String>),
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Credentials::Bearer(_) => write!(f, "Bearer(********)"),
+            Credentials::Basic(username, _) => write!(f, "Basic({}, ********)", username),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Dependency {
+    /// Project of the same source, pinned to a version when given
+    Project(String, Option<String>),
+    /// Only available elsewhere, ex : a download page
+    External(String),
+}
+
+/// The file of a plugin version, whatever its source
+#[derive(Debug, Clone)]
+pub struct Release {
+    /// Id of the project in its source
+    pub project: String,
+    pub name: String,
+    pub version: String,
+    pub file_name: String,
+    pub url: String,
+    pub hash: Option<FileHash>,
+    /// Required dependencies
+    pub dependencies: Vec<Dependency>,
+    pub credentials: Option<Credentials>,
+}
+
+/// File of a release picked by the user, for sources without version resolution
+#[derive(Debug, Clone, Default)]
+pub struct FileSelection {
+    /// Release tag, the latest release when not given
+    pub tag: Option<String>,
+    /// Asset name pattern, ex : `*-fabric-*.jar`
+    pub asset: Option<String>,
+    /// Expected hash of a direct URL
+    pub hash: Option<String>,
+}
+
+/// Directory of the content of a platform, mods for modded servers
+pub fn content_dir(platform: &str) -> &'static str {
+    if plugin_loaders(platform).is_some() {
+        PLUGINS_DIR
+    } else {
+        MODS_DIR
+    }
+}
+
+/// Modrinth loaders whose plugins run on a platform, most specific first
+pub fn plugin_loaders(platform: &str) -> Option<&'static [&'static str]> {
+    match platform.to_lowercase().as_str() {
+        "paper" => Some(&["paper", "spigot", "bukkit"]),
+        "purpur" => Some(&["purpur", "paper", "spigot", "bukkit"]),
+        // Folia only loads plugins declaring support for its regions
+        "folia" => Some(&["folia"]),
+        "spigot" => Some(&["spigot", "bukkit"]),
+        "bukkit" => Some(&["bukkit"]),
+        _ => None,
+    }
+}
+
+/// GET returning JSON, with the User-Agent the plugin repositories ask for
+pub async fn get_json(url: &str, query: &[(&str, String)]) -> Result<Value, String> {
+    reqwest::Client::new()
+        .get(url)
+        .query(query)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("MCT/", env!("CARGO_PKG_VERSION")),
+        )
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Request failed : {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {} : {}", url, e))
+}
+
+/// Installs plugins matching the platform and game version of a server in its plugins directory,
+/// or files of the sources not tied to a loader in its plugins or mods directory
+#[derive(Debug)]
+pub struct ContentInstaller {
+    server_path: PathBuf,
+    source: PluginSource,
+    platform: String,
+    game_version: Option<String>,
+    loaders: &'static [&'static str],
+    selection: FileSelection,
+    manifest: ContentManifest,
+    /// Projects handled during this run, dependency cycles stop here
+    visited: HashSet<String>,
+}
+
+impl ContentInstaller {
+    pub fn build(
+        server_path: &Path,
+        source: PluginSource,
+        selection: FileSelection,
+    ) -> Result<Self, String> {
+        let metadata = session::read_metadata(server_path).ok_or(format!(
+            "No MCA.json found in '{}', create the server first",
+            server_path.to_string_lossy()
+        ))?;
+        let platform = metadata["project"].as_str().unwrap_or_default().to_owned();
+        let loaders = match plugin_loaders(&platform) {
+            Some(loaders) => loaders,
+            None if !source.has_loaders() => &[],
+            None => {
+                return Err(format!(
+                    "'{}' servers use mods, plugins need a Paper, Purpur, Folia or Spigot server",
+                    platform
+                ))
+            }
+        };
+        Ok(Self {
+            server_path: server_path.to_path_buf(),
+            source,
+            platform,
+            game_version: metadata["game_version"].as_str().map(|v| v.to_owned()),
+            loaders,
+            selection,
+            manifest: ContentManifest::load(server_path),
+            visited: HashSet::new(),
+        })
+    }
+
+    /// Installs a project by id or slug, or the one picked among the search results for `name`
+    pub async fn install(
+        &mut self,
+        id: Option<String>,
+        name: Option<String>,
+        dependencies: bool,
+    ) -> Result<(), String> {
+        let project = match (id, name) {
+            (Some(id), _) => id,
+            (None, Some(name)) => self.search(&name).await?,
+            (None, None) => return Err(String::from("A plugin id or name is required")),
+        };
+        let result = self.install_project(&project, None, dependencies).await;
+        self.manifest
+            .save(&self.server_path)
+            .map_err(|e| format!("Error while writting {} : {}", content::CONTENT_FILE, e))?;
+        result
+    }
+
+    fn hangar_platform(&self) -> &'static str {
+        hangar::platform_of(&self.platform)
+    }
+
+    /// Id of the project picked among the search results
+    async fn search(&self, name: &str) -> Result<String, String> {
+        let limit = settings::get_usize("search.limit").unwrap_or(10);
+        let hits = match self.source {
+            PluginSource::Modrinth => {
+                modrinth_search(name, self.loaders, self.game_version.as_deref(), limit).await?
+            }
+            PluginSource::Hangar => {
+                hangar::search(
+                    name,
+                    self.hangar_platform(),
+                    self.game_version.as_deref(),
+                    limit,
+                    0,
+                    None,
+                )
+                .await?
+            }
+            PluginSource::Spigot => spiget::search(name, limit, 0, None).await?,
+            PluginSource::GitHub | PluginSource::Maven | PluginSource::Url => {
+                return Err(format!(
+                    "{} entries are not searchable, give their id ex : github:owner/repo, maven:group:artifact:version or https://host/file.jar",
+                    self.source.name()
+                ))
+            }
+        };
+        match hits.len() {
+            0 => Err(format!(
+                "No {} plugin named '{}' found on {} for {}",
+                self.platform,
+                name,
+                self.source.name(),
+                self.game_version.as_deref().unwrap_or("this server")
+            )),
+            1 => Ok(hits[0]["project_id"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned()),
+            _ => {
+                let options: Vec<String> = hits
+                    .iter()
+                    .map(|hit| match hit["author"].as_str() {
+                        Some(author) => format!(
+                            "{} by {} : {}",
+                            hit["title"].as_str().unwrap_or_default(),
+                            author,
+                            hit["description"].as_str().unwrap_or_default()
+                        ),
+                        None => format!(
+                            "{} : {}",
+                            hit["title"].as_str().unwrap_or_default(),
+                            hit["description"].as_str().unwrap_or_default()
+                        ),
+                    })
+                    .collect();
+                let selected = Select::new("➡️ Please select a plugin", options.clone())
+                    .prompt()
+                    .map_err(|e| e.to_string())?;
+                let index = options.iter().position(|o| *o == selected).unwrap_or(0);
+                Ok(hits[index]["project_id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned())
+            }
+        }
+    }
+
+    async fn release(&self, project: &str, version: Option<&str>) -> Result<Release, String> {
+        match self.source {
+            PluginSource::Modrinth => {
+                modrinth_release(project, version, self.loaders, self.game_version.as_deref()).await
+            }
+            PluginSource::Hangar => {
+                hangar::release(
+                    project,
+                    version,
+                    self.hangar_platform(),
+                    self.game_version.as_deref(),
+                )
+                .await
+            }
+            PluginSource::Spigot => {
+                spiget::release(project, version, self.game_version.as_deref()).await
+            }
+            PluginSource::GitHub => {
+                github::release(
+                    project,
+                    version.or(self.selection.tag.as_deref()),
+                    self.asset_pattern(project).as_deref(),
+                )
+                .await
+            }
+            PluginSource::Maven => maven::release(project, version).await,
+            PluginSource::Url => url_release(project, self.selection.hash.as_deref()),
+        }
+    }
+
+    /// Asset pattern given, or the one of the installed version so updates pick the same asset
+    fn asset_pattern(&self, project: &str) -> Option<String> {
+        if self.source != PluginSource::GitHub {
+            return None;
+        }
+        self.selection.asset.clone().or_else(|| {
+            self.manifest
+                .find(self.source.name(), project)
+                .and_then(|(_, installed)| installed.asset.clone())
+        })
+    }
+
+    async fn install_project(
+        &mut self,
+        project: &str,
+        version: Option<&str>,
+        dependencies: bool,
+    ) -> Result<(), String> {
+        let release = self.release(project, version).await?;
+        if !self.visited.insert(release.project.clone()) {
+            return Ok(());
+        }
+        let source = self.source.name();
+        match self.manifest.find(source, &release.project) {
+            Some((path, installed))
+                if installed.version == release.version
+                    && self.server_path.join(path).is_file() =>
+            {
+                println!(
+                    "✅ {} {} is already installed",
+                    release.name, release.version
+                );
+            }
+            _ => {
+                let file_name = checked_file_name(&release.file_name)?;
+                let relative = format!("{}/{}", content_dir(&self.platform), file_name);
+                let content = download(
+                    &release.url,
+                    &self.server_path.join(&relative),
+                    release.hash.as_ref(),
+                    release.credentials.as_ref(),
+                )
+                .await?;
+                println!(
+                    "✅ {} {} installed in {}",
+                    release.name, release.version, relative
+                );
+                self.manifest.record(
+                    &self.server_path,
+                    relative,
+                    InstalledContent {
+                        source: source.to_owned(),
+                        project: release.project.clone(),
+                        name: release.name.clone(),
+                        version: release.version.clone(),
+                        sha512: Some(content::sha512_hex(&content)),
+                        asset: self.asset_pattern(&release.project),
+                    },
+                );
+            }
+        }
+
+        if !dependencies {
+            return Ok(());
+        }
+        for dependency in &release.dependencies {
+            match dependency {
+                Dependency::Project(project, version) => {
+                    // Box the future to allow recursion
+                    let installed =
+                        Box::pin(self.install_project(project, version.as_deref(), true)).await;
+                    if let Err(e) = installed {
+                        println!("⚠️ Dependency of {} not installed : {}", release.name, e);
+                    }
+                }
+                Dependency::External(name) => {
+                    println!("⚠️ {} needs {}, install it manually", release.name, name)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn modrinth_search(
+    name: &str,
+    loaders: &[&str],
+    game_version: Option<&str>,
+    limit: usize,
+) -> Result<Vec<Value>, String> {
+    let mut facets = vec![json!(loaders
+        .iter()
+        .map(|loader| format!("categories:{}", loader))
+        .collect::<Vec<String>>())];
+    if let Some(game_version) = game_version {
+        facets.push(json!([format!("versions:{}", game_version)]));
+    }
+    let data = get_json(
+        &format!("{}/v2/search", settings::api("modrinth")),
+        &[
+            ("query", name.to_owned()),
+            ("facets", json!(facets).to_string()),
+            ("limit", limit.to_string()),
+        ],
+    )
+    .await?;
+    Ok(data["hits"].as_array().cloned().unwrap_or_default())
+}
+
+/// Given version of a Modrinth project, or its newest one for the server, releases before betas
+async fn modrinth_release(
+    project: &str,
+    version: Option<&str>,
+    loaders: &[&str],
+    game_version: Option<&str>,
+) -> Result<Release, String> {
+    let api = settings::api("modrinth");
+    let details = get_json(&format!("{}/v2/project/{}", api, project), &[]).await?;
+    let project_id = details["id"].as_str().unwrap_or(project).to_owned();
+    let title = details["title"].as_str().unwrap_or(project).to_owned();
+    let version = match version {
+        Some(version) => get_json(&format!("{}/v2/version/{}", api, version), &[]).await?,
+        None => {
+            let mut query = vec![("loaders", json!(loaders).to_string())];
+            if let Some(game_version) = game_version {
+                query.push(("game_versions", json!([game_version]).to_string()));
+            }
+            let versions = get_json(
+                &format!("{}/v2/project/{}/version", api, project_id),
+                &query,
+            )
+            .await?;
+            let versions = versions.as_array().cloned().unwrap_or_default();
+            versions
+                .iter()
+                .find(|version| version["version_type"] == "release")
+                .or(versions.first())
+                .cloned()
+                .ok_or(format!(
+                    "No version of {} for {} {}",
+                    title,
+                    loaders.join(" | "),
+                    game_version.unwrap_or_default()
+                ))?
+        }
+    };
+    let files = version["files"].as_array().cloned().unwrap_or_default();
+    let file = files
+        .iter()
+        .find(|file| file["primary"].as_bool() == Some(true))
+        .or(files.first())
+        .ok_or(format!("No file in the version of {}", title))?;
+    let dependencies = version["dependencies"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .filter(|dependency| dependency["dependency_type"] == "required")
+        .filter_map(|dependency| match dependency["project_id"].as_str() {
+            Some(project) => Some(Dependency::Project(
+                project.to_owned(),
+                dependency["version_id"].as_str().map(|v| v.to_owned()),
+            )),
+            None => dependency["file_name"]
+                .as_str()
+                .map(|file_name| Dependency::External(file_name.to_owned())),
+        })
+        .collect();
+    Ok(Release {
+        project: project_id,
+        name: title,
+        version: version["version_number"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+        file_name: file["filename"].as_str().unwrap_or_default().to_owned(),
+        url: file["url"].as_str().unwrap_or_default().to_owned(),
+        hash: file["hashes"]["sha512"]
+            .as_str()
+            .map(|hash| FileHash::Sha512(hash.to_owned())),
+        dependencies,
+        credentials: None,
+    })
+}
+
+/// A direct URL, its hash stands for the version since the file behind it can change
+fn url_release(url: &str, hash: Option<&str>) -> Result<Release, String> {
+    let hash = hash.ok_or(String::from(
+        "A direct URL needs the expected hash of its file, ex : --hash sha256:<hex>",
+    ))?;
+    let hash = FileHash::parse(hash).ok_or(format!("'{}' is not a sha1, sha256 or sha512 hash", hash))?;
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .ok_or(format!("No file name in {}", url))?
+        .to_owned();
+    Ok(Release {
+        project: url.to_owned(),
+        name: file_name.clone(),
+        version: hash.to_string().chars().take(19).collect(),
+        file_name,
+        url: url.to_owned(),
+        hash: Some(hash),
+        dependencies: Vec::new(),
+        credentials: None,
+    })
+}
+
+/// A file name given by a remote API, refused when it could leave the content directory
+pub fn checked_file_name(name: &str) -> Result<&str, String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\', ':'])
+        || name.chars().any(|c| c.is_control())
+    {
+        return Err(format!("Unsafe file name '{}', download refused", name.escape_debug()));
+    }
+    Ok(name)
+}
+
+/// Downloads `url` to `path`, checking its hash when known, and returns the content
+pub async fn download(
+    url: &str,
+    path: &Path,
+    hash: Option<&FileHash>,
+    credentials: Option<&Credentials>,
+) -> Result<Vec<u8>, String> {
+    println!("➡️ Downloading {}", url);
+    let mut request = reqwest::Client::new()
+        .get(url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("MCT/", env!("CARGO_PKG_VERSION")),
+        )
+        // Asks the GitHub API for the file instead of its metadata
+        .header(reqwest::header::ACCEPT, "application/octet-stream");
+    match credentials {
+        Some(Credentials::Bearer(token)) => request = request.bearer_auth(token),
+        Some(Credentials::Basic(username, password)) => {
+            request = request.basic_auth(username, password.as_ref())
+        }
+        None => {}
+    }
+    let content = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Download failed : {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Download failed : {}", e))?;
+    if hash.is_some_and(|hash| !hash.matches(&content)) {
+        return Err(format!("Hash mismatch for {}, download refused", url));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, &content)
+        .map_err(|e| format!("Error while writting {} : {}", path.to_string_lossy(), e))?;
+    Ok(content.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_file_names() {
+        assert_eq!(checked_file_name("sodium-fabric-0.6.0.jar"), Ok("sodium-fabric-0.6.0.jar"));
+        for name in ["", "..", ".hidden.jar", "../server.jar", "mods/x.jar", "..\\x.jar", "C:x.jar", "x\n.jar"] {
+            assert!(checked_file_name(name).is_err(), "{:?}", name);
+        }
+    }
+}
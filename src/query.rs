@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::net::UdpSocket;
+
+use crate::server_properties::ServerProperties;
+use crate::server_status::{split_host_port, strip_formatting, DEFAULT_SERVER_PORT};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+/// Constant padding before the key/value section of a full stat
+const FULL_STAT_PADDING: usize = 11;
+/// Constant padding before the player list of a full stat
+const PLAYER_SECTION: &[u8] = b"\x01player_\x00\x00";
+
+/// Answer of a basic stat request
+#[derive(Debug, Clone, Serialize)]
+pub struct BasicStat {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub online_players: String,
+    pub max_players: String,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+/// Answer of a full stat request
+#[derive(Debug, Clone, Serialize)]
+pub struct FullStat {
+    /// Every key sent by the server (hostname, version, plugins, map, numplayers, ...)
+    pub values: BTreeMap<String, String>,
+    pub players: Vec<String>,
+}
+
+impl FullStat {
+    pub fn get(&self, key: &str) -> &str {
+        self.values.get(key).map(|v| v.as_str()).unwrap_or_default()
+    }
+
+    /// The `plugins` value looks like `Paper on Bukkit 1.21: PluginA 1.0; PluginB 2.1`
+    pub fn plugins(&self) -> (String, Vec<String>) {
+        let raw = self.get("plugins");
+        match raw.split_once(':') {
+            Some((server, plugins)) => (
+                server.trim().to_owned(),
+                plugins
+                    .split(';')
+                    .map(|p| p.trim().to_owned())
+                    .filter(|p| !p.is_empty())
+                    .collect(),
+            ),
+            None => (raw.trim().to_owned(), vec![]),
+        }
+    }
+
+    pub fn display(&self, address: &str) {
+        println!("✅ {} answered the query", address);
+        println!("   MOTD: {}", strip_formatting(self.get("hostname")));
+        println!("   Version: {}", self.get("version"));
+        println!("   Map: {}", self.get("map"));
+        println!(
+            "   Players: {}/{}",
+            self.get("numplayers"),
+            self.get("maxplayers")
+        );
+        if !self.players.is_empty() {
+            println!("   Online: {}", self.players.join(", "));
+        }
+        let (server, plugins) = self.plugins();
+        if !server.is_empty() {
+            println!("   Server: {}", server);
+        }
+        if !plugins.is_empty() {
+            println!("   Plugins: {}", plugins.join(", "));
+        }
+    }
+}
+
+impl BasicStat {
+    pub fn display(&self, address: &str) {
+        println!("✅ {} answered the query", address);
+        println!("   MOTD: {}", strip_formatting(&self.motd));
+        println!("   Game type: {}", self.game_type);
+        println!("   Map: {}", self.map);
+        println!("   Players: {}/{}", self.online_players, self.max_players);
+        println!("   Host: {}:{}", self.host_ip, self.host_port);
+    }
+}
+
+/// A server directory (reads `query.port`) or `host[:port]`
+pub fn resolve_target(target: &str) -> String {
+    let path = Path::new(target);
+    if path.is_dir() {
+        let properties = ServerProperties::load(path);
+        let port = properties
+            .get("query.port")
+            .and_then(|p| p.parse::<u16>().ok())
+            .or(properties.get_u16("server-port"))
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        return format!("127.0.0.1:{}", port);
+    }
+    match split_host_port(target) {
+        Some(_) => target.to_owned(),
+        None => format!("{}:{}", target, DEFAULT_SERVER_PORT),
+    }
+}
+
+pub struct QueryClient {
+    socket: UdpSocket,
+    session_id: i32,
+}
+
+impl QueryClient {
+    pub async fn connect(address: &str) -> std::io::Result<Self> {
+        let address = tokio::net::lookup_host(address)
+            .await?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no address found"))?;
+        // An IPv4 socket cannot reach an IPv6 server
+        let local = if address.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(address).await?;
+        // Only the lower 4 bits of each byte are used by the server
+        let session_id = rand::random::<i32>() & 0x0F0F0F0F;
+        Ok(Self { socket, session_id })
+    }
+
+    pub async fn basic_stat(&self) -> std::io::Result<BasicStat> {
+        let token = self.handshake().await?;
+        let response = self.request(TYPE_STAT, &token.to_be_bytes()).await?;
+        parse_basic_stat(&response)
+    }
+
+    pub async fn full_stat(&self) -> std::io::Result<FullStat> {
+        let token = self.handshake().await?;
+        let mut payload = token.to_be_bytes().to_vec();
+        payload.extend_from_slice(&[0, 0, 0, 0]);
+        let response = self.request(TYPE_STAT, &payload).await?;
+        parse_full_stat(&response)
+    }
+
+    /// Asks the server for a challenge token, valid for 30 seconds
+    async fn handshake(&self) -> std::io::Result<i32> {
+        let response = self.request(TYPE_HANDSHAKE, &[]).await?;
+        parse_token(&response)
+    }
+
+    /// Sends a request and returns the response payload after the type and session id
+    async fn request(&self, packet_type: u8, payload: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut packet = MAGIC.to_vec();
+        packet.push(packet_type);
+        packet.extend_from_slice(&self.session_id.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.socket.send(&packet).await?;
+
+        let mut buffer = vec![0u8; 65535];
+        loop {
+            let length = tokio::time::timeout(QUERY_TIMEOUT, self.socket.recv(&mut buffer))
+                .await
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "query timed out"))??;
+            let response = &buffer[..length];
+            if response.len() < 5 || response[0] != packet_type {
+                continue;
+            }
+            if i32::from_be_bytes(response[1..5].try_into().unwrap()) != self.session_id {
+                continue;
+            }
+            return Ok(response[5..].to_vec());
+        }
+    }
+}
+
+/// The token is sent as a decimal string, it does not always fit in an i32
+fn parse_token(response: &[u8]) -> std::io::Result<i32> {
+    let mut data = response;
+    read_cstring(&mut data)
+        .and_then(|token| token.trim().parse::<i64>().ok())
+        .map(|token| token as i32)
+        .ok_or_else(|| invalid("invalid challenge token"))
+}
+
+fn parse_basic_stat(response: &[u8]) -> std::io::Result<BasicStat> {
+    let mut data = response;
+    let mut next = || read_cstring(&mut data);
+    let motd = next();
+    let game_type = next();
+    let map = next();
+    let online_players = next();
+    let max_players = next();
+    let (motd, game_type, map, online_players, max_players) =
+        match (motd, game_type, map, online_players, max_players) {
+            (Some(a), Some(b), Some(c), Some(d), Some(e)) => (a, b, c, d, e),
+            _ => return Err(invalid("truncated basic stat")),
+        };
+    if data.len() < 2 {
+        return Err(invalid("truncated basic stat"));
+    }
+    let host_port = u16::from_le_bytes([data[0], data[1]]);
+    data = &data[2..];
+    let host_ip = read_cstring(&mut data).unwrap_or_default();
+    Ok(BasicStat {
+        motd,
+        game_type,
+        map,
+        online_players,
+        max_players,
+        host_port,
+        host_ip,
+    })
+}
+
+fn parse_full_stat(response: &[u8]) -> std::io::Result<FullStat> {
+    if response.len() < FULL_STAT_PADDING {
+        return Err(invalid("truncated full stat"));
+    }
+    let mut data = &response[FULL_STAT_PADDING..];
+
+    let mut values = BTreeMap::new();
+    loop {
+        let key = read_cstring(&mut data).ok_or_else(|| invalid("truncated full stat"))?;
+        if key.is_empty() {
+            break;
+        }
+        let value = read_cstring(&mut data).ok_or_else(|| invalid("truncated full stat"))?;
+        values.insert(key, value);
+    }
+
+    let mut players = vec![];
+    if let Some(rest) = data.strip_prefix(PLAYER_SECTION) {
+        data = rest;
+        while let Some(player) = read_cstring(&mut data) {
+            if player.is_empty() {
+                break;
+            }
+            players.push(player);
+        }
+    }
+    Ok(FullStat { values, players })
+}
+
+fn read_cstring(data: &mut &[u8]) -> Option<String> {
+    let end = data.iter().position(|b| *b == 0)?;
+    let text = String::from_utf8_lossy(&data[..end]).into_owned();
+    *data = &data[end + 1..];
+    Some(text)
+}
+
+fn invalid(reason: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_owned())
+}
+
+/// Enables the query listener in server.properties on the given port
+pub fn enable_query(server_path: &Path, port: u16) {
+    let mut properties = ServerProperties::load(server_path);
+    properties.set("enable-query", "true");
+    properties.set("query.port", port.to_string());
+    match properties.save() {
+        Ok(_) => println!("✅ Query enabled on port {}", port),
+        Err(e) => println!("❌ Error while writting server.properties : {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_tokens() {
+        assert_eq!(parse_token(b"9513307\0").unwrap(), 9513307);
+        // Tokens above i32::MAX are sent back as their 32 low bits
+        assert_eq!(
+            parse_token(b"3000000000\0").unwrap().to_be_bytes(),
+            3000000000u32.to_be_bytes()
+        );
+        assert_eq!(parse_token(b"-1296\0").unwrap(), -1296);
+        for response in [&b""[..], b"9513307", b"token\0"] {
+            assert!(parse_token(response).is_err(), "{:?}", response);
+        }
+    }
+
+    #[test]
+    fn basic_stat() {
+        let response = b"A Server\0SMP\0world\x002\x0020\0\xdd\x63127.0.0.1\0";
+        let stat = parse_basic_stat(response).unwrap();
+        assert_eq!(stat.motd, "A Server");
+        assert_eq!(stat.game_type, "SMP");
+        assert_eq!(stat.map, "world");
+        assert_eq!((stat.online_players.as_str(), stat.max_players.as_str()), ("2", "20"));
+        // The port is the only little endian field
+        assert_eq!(stat.host_port, 25565);
+        assert_eq!(stat.host_ip, "127.0.0.1");
+        assert!(parse_basic_stat(b"A Server\0SMP\0world\x002\x0020\0\xdd").is_err());
+    }
+
+    #[test]
+    fn full_stat() {
+        let mut response = b"splitnum\0\x80\0".to_vec();
+        response.extend_from_slice(b"hostname\0A Server\0gametype\0SMP\0version\x001.21.4\0");
+        response.extend_from_slice(b"plugins\0Paper on Bukkit 1.21.4: WorldEdit 7.3; LuckPerms 5.4\0");
+        response.extend_from_slice(b"map\0world\0numplayers\x002\0maxplayers\x0020\0\0");
+        response.extend_from_slice(b"\x01player_\0\0Alex\0Steve\0\0");
+        let stat = parse_full_stat(&response).unwrap();
+        assert_eq!(stat.get("hostname"), "A Server");
+        assert_eq!(stat.get("version"), "1.21.4");
+        assert_eq!(stat.get("numplayers"), "2");
+        assert_eq!(stat.get("missing"), "");
+        assert_eq!(stat.players, ["Alex", "Steve"]);
+        let (server, plugins) = stat.plugins();
+        assert_eq!(server, "Paper on Bukkit 1.21.4");
+        assert_eq!(plugins, ["WorldEdit 7.3", "LuckPerms 5.4"]);
+    }
+
+    #[test]
+    fn truncated_full_stat() {
+        assert!(parse_full_stat(b"splitnum\0").is_err());
+        assert!(parse_full_stat(b"splitnum\0\x80\0hostname\0A Ser").is_err());
+        // Nobody online
+        let stat = parse_full_stat(b"splitnum\0\x80\0map\0world\0\0\x01player_\0\0\0").unwrap();
+        assert!(stat.players.is_empty());
+        assert_eq!(stat.plugins(), (String::new(), vec![]));
+    }
+}
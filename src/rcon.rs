@@ -0,0 +1,334 @@
+use std::fmt;
+use std::path::Path;
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::server_properties::ServerProperties;
+
+const DEFAULT_RCON_PORT: u16 = 25575;
+const GENERATED_PASSWORD_LENGTH: usize = 24;
+
+/// Packet types of the Source RCON protocol
+const PACKET_RESPONSE: i32 = 0;
+const PACKET_COMMAND: i32 = 2;
+const PACKET_LOGIN: i32 = 3;
+/// Invalid type sent after a command, its answer marks the end of a multi-packet response
+const PACKET_END_MARKER: i32 = 200;
+/// Largest packet accepted from the server (the protocol limits responses to 4096 bytes of body)
+const MAX_PACKET_SIZE: i32 = 4096 + 10;
+
+#[derive(Debug)]
+pub enum RconError {
+    Io(std::io::Error),
+    AuthenticationFailed,
+    Protocol(String),
+}
+
+impl fmt::Display for RconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RconError::Io(e) => write!(f, "{}", e),
+            RconError::AuthenticationFailed => write!(f, "wrong RCON password"),
+            RconError::Protocol(e) => write!(f, "invalid RCON packet : {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for RconError {
+    fn from(e: std::io::Error) -> Self {
+        RconError::Io(e)
+    }
+}
+
+/// Address and password needed to reach a server over RCON
+#[derive(Debug, Clone)]
+pub struct RconTarget {
+    pub address: String,
+    pub password: Option<String>,
+}
+
+impl RconTarget {
+    /// Reads `rcon.port` / `rcon.password` from the server.properties of a server directory,
+    /// `None` if RCON is not enabled
+    pub fn from_server_dir(server_path: &Path) -> Option<Self> {
+        let properties = ServerProperties::load(server_path);
+        if !properties.get_bool("enable-rcon") {
+            return None;
+        }
+        let port = properties.get_u16("rcon.port").unwrap_or(DEFAULT_RCON_PORT);
+        let host = match properties.get("server-ip") {
+            Some(ip) if !ip.is_empty() && ip != "0.0.0.0" => ip.to_owned(),
+            _ => String::from("127.0.0.1"),
+        };
+        Some(Self {
+            address: format!("{}:{}", host, port),
+            password: properties
+                .get("rcon.password")
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_owned()),
+        })
+    }
+
+    /// A server directory or `host[:port]`
+    pub fn parse(target: &str) -> Result<Self, String> {
+        let path = Path::new(target);
+        if path.is_dir() {
+            return Self::from_server_dir(path).ok_or(format!(
+                "RCON is not enabled in '{}/server.properties'",
+                target
+            ));
+        }
+        let address = if target.contains(':') {
+            target.to_owned()
+        } else {
+            format!("{}:{}", target, DEFAULT_RCON_PORT)
+        };
+        Ok(Self {
+            address,
+            password: None,
+        })
+    }
+}
+
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    /// Connects and logs in
+    pub async fn connect(address: &str, password: &str) -> Result<Self, RconError> {
+        let stream = TcpStream::connect(address).await?;
+        let mut client = Self { stream, next_id: 1 };
+        let id = client.next_id();
+        client.write_packet(id, PACKET_LOGIN, password).await?;
+        loop {
+            let (response_id, packet_type, _) = client.read_packet().await?;
+            // Some servers send an empty response before the login answer
+            if packet_type == PACKET_RESPONSE && response_id == id {
+                continue;
+            }
+            if response_id == -1 {
+                return Err(RconError::AuthenticationFailed);
+            }
+            if response_id == id {
+                return Ok(client);
+            }
+        }
+    }
+
+    pub async fn connect_target(target: &RconTarget) -> Result<Self, RconError> {
+        Self::connect(&target.address, target.password.as_deref().unwrap_or("")).await
+    }
+
+    /// Runs a console command and returns its output, joining multi-packet responses
+    pub async fn command(&mut self, command: &str) -> Result<String, RconError> {
+        let id = self.next_id();
+        let marker = self.next_id();
+        self.write_packet(id, PACKET_COMMAND, command).await?;
+        self.write_packet(marker, PACKET_END_MARKER, "").await?;
+
+        let mut output = String::new();
+        loop {
+            let (response_id, _, body) = self.read_packet().await?;
+            if response_id == marker {
+                return Ok(output);
+            }
+            if response_id == id {
+                output.push_str(&body);
+            }
+        }
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        id
+    }
+
+    async fn write_packet(&mut self, id: i32, packet_type: i32, body: &str) -> Result<(), RconError> {
+        self.stream.write_all(&encode_packet(id, packet_type, body)).await?;
+        Ok(())
+    }
+
+    async fn read_packet(&mut self) -> Result<(i32, i32, String), RconError> {
+        read_packet(&mut self.stream).await
+    }
+}
+
+/// Length, id, type, body and two null bytes, integers are little endian
+fn encode_packet(id: i32, packet_type: i32, body: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(body.len() + 14);
+    packet.extend_from_slice(&(body.len() as i32 + 10).to_le_bytes());
+    packet.extend_from_slice(&id.to_le_bytes());
+    packet.extend_from_slice(&packet_type.to_le_bytes());
+    packet.extend_from_slice(body.as_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet
+}
+
+/// Reads one packet and returns its id, type and body
+async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(i32, i32, String), RconError> {
+    let length = reader.read_i32_le().await?;
+    if !(10..=MAX_PACKET_SIZE).contains(&length) {
+        return Err(RconError::Protocol(format!("length {}", length)));
+    }
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload).await?;
+    let id = i32::from_le_bytes(payload[0..4].try_into().unwrap());
+    let packet_type = i32::from_le_bytes(payload[4..8].try_into().unwrap());
+    let body = String::from_utf8_lossy(&payload[8..payload.len() - 2]).into_owned();
+    Ok((id, packet_type, body))
+}
+
+/// Connects to the target, asking for the password when it is unknown
+pub async fn open(target: &str, password: Option<String>) -> Option<RconClient> {
+    let mut target = match RconTarget::parse(target) {
+        Ok(target) => target,
+        Err(e) => {
+            println!("❌ {}", e);
+            return None;
+        }
+    };
+    if password.is_some() {
+        target.password = password;
+    }
+    if target.password.is_none() {
+        target.password = inquire::Password::new("➡️ RCON password")
+            .without_confirmation()
+            .prompt()
+            .ok();
+    }
+    match RconClient::connect_target(&target).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            println!("❌ Unable to reach {} with RCON : {}", target.address, e);
+            None
+        }
+    }
+}
+
+/// Interactive RCON shell, `exit` or Ctrl-D leaves
+pub async fn shell(client: &mut RconClient) {
+    println!("✅ Connected, type `exit` to quit");
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("rcon> ");
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            _ => return,
+        };
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+        if command == "exit" || command == "quit" {
+            return;
+        }
+        match client.command(command).await {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                println!("❌ {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Enables RCON in server.properties, generating a password if none is set
+pub fn enable_rcon(server_path: &Path) {
+    let mut properties = ServerProperties::load(server_path);
+    properties.set("enable-rcon", "true");
+    if properties.get("rcon.port").is_none() {
+        properties.set("rcon.port", DEFAULT_RCON_PORT.to_string());
+    }
+    if properties.get("rcon.password").is_none_or(|p| p.is_empty()) {
+        let password: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(GENERATED_PASSWORD_LENGTH)
+            .map(char::from)
+            .collect();
+        properties.set("rcon.password", password.clone());
+        println!("🔑 RCON password : {}", password);
+    }
+    match properties.save() {
+        Ok(_) => println!("✅ RCON enabled on port {}", properties.get("rcon.port").unwrap()),
+        Err(e) => println!("❌ Error while writting server.properties : {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const PASSWORD: &str = "secret";
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    /// Answers like a vanilla server : -1 for a wrong password, long outputs split in
+    /// several packets and an "Unknown request" answer to the end marker
+    async fn serve(listener: TcpListener) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        while let Ok((id, packet_type, body)) = read_packet(&mut stream).await {
+            let answers = match packet_type {
+                PACKET_LOGIN if body == PASSWORD => vec![encode_packet(id, PACKET_COMMAND, "")],
+                PACKET_LOGIN => vec![encode_packet(-1, PACKET_COMMAND, "")],
+                PACKET_COMMAND => vec![
+                    encode_packet(id, PACKET_RESPONSE, "There are 2 of a max of 20 players online: "),
+                    encode_packet(id, PACKET_RESPONSE, "Notch, jeb_"),
+                ],
+                _ => vec![encode_packet(id, PACKET_RESPONSE, "Unknown request c8")],
+            };
+            for answer in answers {
+                stream.write_all(&answer).await.unwrap();
+            }
+        }
+    }
+
+    async fn connect(password: &str) -> Result<RconClient, RconError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(serve(listener));
+        RconClient::connect(&address, password).await
+    }
+
+    #[test]
+    fn packets_are_encoded() {
+        assert_eq!(
+            encode_packet(7, PACKET_COMMAND, "list"),
+            [14, 0, 0, 0, 7, 0, 0, 0, 2, 0, 0, 0, b'l', b'i', b's', b't', 0, 0]
+        );
+    }
+
+    #[test]
+    fn packets_are_decoded() {
+        let packet = encode_packet(-1, PACKET_RESPONSE, "héllo");
+        let (id, packet_type, body) = block_on(read_packet(&mut packet.as_slice())).unwrap();
+        assert_eq!((id, packet_type, body.as_str()), (-1, PACKET_RESPONSE, "héllo"));
+
+        let mut too_long = encode_packet(1, PACKET_RESPONSE, "");
+        too_long[0..4].copy_from_slice(&(MAX_PACKET_SIZE + 1).to_le_bytes());
+        assert!(matches!(
+            block_on(read_packet(&mut too_long.as_slice())),
+            Err(RconError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn multi_packet_responses_are_merged() {
+        let output = block_on(async { connect(PASSWORD).await.unwrap().command("list").await.unwrap() });
+        assert_eq!(output, "There are 2 of a max of 20 players online: Notch, jeb_");
+    }
+
+    #[test]
+    fn wrong_password_is_reported() {
+        assert!(matches!(block_on(connect("wrong")), Err(RconError::AuthenticationFailed)));
+    }
+}
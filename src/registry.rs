@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::router::Route;
+use crate::server_properties::ServerProperties;
+use crate::server_status::DEFAULT_SERVER_PORT;
+use crate::session;
+
+const REGISTRY_FILE: &str = "servers.json";
+
+/// `$XDG_CONFIG_HOME/mct`, `~/.config/mct` when it is not set, `%APPDATA%\mct` on Windows
+pub fn config_dir() -> Result<PathBuf, String> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|dir| !dir.is_empty());
+    non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| non_empty("APPDATA").map(PathBuf::from))
+        .map(|dir| dir.join("mct"))
+        .ok_or(String::from(
+            "No config directory found, set XDG_CONFIG_HOME, HOME or APPDATA",
+        ))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredServer {
+    pub path: PathBuf,
+}
+
+/// Named server directories shared by every MCT invocation
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    pub servers: BTreeMap<String, RegisteredServer>,
+    /// Hostnames forwarded by `MCT Router Start`, `*` is the default backend
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub routes: BTreeMap<String, Route>,
+}
+
+impl Registry {
+    pub fn load() -> Self {
+        config_dir()
+            .ok()
+            .and_then(|dir| fs::read_to_string(dir.join(REGISTRY_FILE)).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let dir = config_dir().map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join(REGISTRY_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+
+    pub fn get(&self, name: &str) -> Result<&RegisteredServer, String> {
+        self.servers.get(name).ok_or(format!(
+            "No server named '{}', see `MCT Servers List`",
+            name
+        ))
+    }
+
+    /// Registers `path` (made absolute) under `name`, replacing a previous entry
+    pub fn add(&mut self, name: &str, path: &Path) -> Result<PathBuf, String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid server name '{}'", name));
+        }
+        let path = fs::canonicalize(path)
+            .map_err(|e| format!("Invalid server path '{}' : {}", path.to_string_lossy(), e))?;
+        self.servers
+            .insert(name.to_owned(), RegisteredServer { path: path.clone() });
+        Ok(path)
+    }
+}
+
+/// Registers a server directory, printing the outcome
+pub fn register(name: &str, path: &Path) {
+    let mut registry = Registry::load();
+    match registry.add(name, path).and_then(|path| {
+        registry
+            .save()
+            .map(|_| path)
+            .map_err(|e| format!("Error while writting the registry : {}", e))
+    }) {
+        Ok(path) => println!("✅ Server '{}' registered : {}", name, path.to_string_lossy()),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+pub fn unregister(name: &str) {
+    let mut registry = Registry::load();
+    if registry.servers.remove(name).is_none() {
+        println!("❌ No server named '{}'", name);
+        return;
+    }
+    match registry.save() {
+        Ok(_) => println!("✅ Server '{}' removed from the registry, its files are kept", name),
+        Err(e) => println!("❌ Error while writting the registry : {}", e),
+    }
+}
+
+/// Directory of a registered server, exits when the name is unknown
+pub fn resolve(name: &str) -> PathBuf {
+    match Registry::load().get(name) {
+        Ok(server) => server.path.clone(),
+        Err(e) => {
+            println!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn print_list() {
+    let registry = Registry::load();
+    if registry.servers.is_empty() {
+        println!("➡️ No registered server, add one with `MCT Servers Add <Name> <Path>`");
+        return;
+    }
+    println!(
+        "{:<16} {:<8} {:<10} {:<6} {:<10} PATH",
+        "NAME", "PLATFORM", "VERSION", "PORT", "STATE"
+    );
+    for (name, server) in &registry.servers {
+        let metadata = session::read_metadata(&server.path).unwrap_or_default();
+        let port = ServerProperties::load(&server.path)
+            .get_u16("server-port")
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        let state = if !server.path.is_dir() {
+            String::from("missing")
+        } else {
+            match session::running_pid(&server.path) {
+                Some(pid) => format!("running ({})", pid),
+                None => String::from("stopped"),
+            }
+        };
+        println!(
+            "{:<16} {:<8} {:<10} {:<6} {:<10} {}",
+            name,
+            metadata["project"].as_str().unwrap_or("-"),
+            metadata["game_version"].as_str().unwrap_or("-"),
+            port,
+            state,
+            server.path.to_string_lossy()
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::mc_protocol::{self, Handshake};
+use crate::registry::Registry;
+use crate::server_properties::ServerProperties;
+use crate::server_status::{self, StatusTarget, DEFAULT_SERVER_PORT};
+
+/// Route used when no hostname matches
+pub const DEFAULT_ROUTE: &str = "*";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest PROXY protocol v1 line, CRLF included
+const PROXY_V1_MAX_LENGTH: usize = 107;
+const PROXY_V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// Backend of a hostname, stored in the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    /// Name of a registered server
+    pub server: String,
+    /// MOTD answered by the router instead of the server's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motd: Option<String>,
+    /// Sends a PROXY protocol v2 header so the server sees the player address
+    #[serde(default)]
+    pub proxy_protocol: bool,
+}
+
+/// Routes `hostname` to a registered server, replacing a previous route
+pub fn add_route(hostname: &str, route: Route) {
+    let mut registry = Registry::load();
+    if let Err(e) = registry.get(&route.server) {
+        println!("❌ {}", e);
+        return;
+    }
+    let hostname = normalize_hostname(hostname);
+    let server = route.server.clone();
+    registry.routes.insert(hostname.clone(), route);
+    match registry.save() {
+        Ok(_) if hostname == DEFAULT_ROUTE => {
+            println!("✅ Unknown hostnames are routed to '{}'", server)
+        }
+        Ok(_) => println!("✅ {} is routed to '{}'", hostname, server),
+        Err(e) => println!("❌ Error while writting the registry : {}", e),
+    }
+}
+
+pub fn remove_route(hostname: &str) {
+    let mut registry = Registry::load();
+    let hostname = normalize_hostname(hostname);
+    if registry.routes.remove(&hostname).is_none() {
+        println!("❌ No route for '{}'", hostname);
+        return;
+    }
+    match registry.save() {
+        Ok(_) => println!("✅ Route for '{}' removed", hostname),
+        Err(e) => println!("❌ Error while writting the registry : {}", e),
+    }
+}
+
+pub fn print_routes() {
+    let registry = Registry::load();
+    if registry.routes.is_empty() {
+        println!("➡️ No route, add one with `MCT Router Add <Hostname> <Server>`");
+        return;
+    }
+    println!(
+        "{:<28} {:<16} {:<8} {:<6} MOTD",
+        "HOSTNAME", "SERVER", "PROXY", "PORT"
+    );
+    for (hostname, route) in &registry.routes {
+        let port = registry
+            .get(&route.server)
+            .map(|server| backend_port(&server.path).to_string())
+            .unwrap_or(String::from("-"));
+        println!(
+            "{:<28} {:<16} {:<8} {:<6} {}",
+            hostname,
+            route.server,
+            if route.proxy_protocol { "v2" } else { "-" },
+            port,
+            route.motd.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+/// `Play.Example.com.` and Forge's `play.example.com\0FML3\0` both become `play.example.com`
+fn normalize_hostname(address: &str) -> String {
+    address
+        .split('\0')
+        .next()
+        .unwrap_or_default()
+        // TCPShield and similar services append `///<ip>///<timestamp>`
+        .split("///")
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_lowercase()
+}
+
+/// Exact hostname, then `*.domain` wildcards from the most specific, then the default route
+fn find_route<'a>(registry: &'a Registry, hostname: &str) -> Option<&'a Route> {
+    if let Some(route) = registry.routes.get(hostname) {
+        return Some(route);
+    }
+    let mut suffix = hostname;
+    while let Some((_, parent)) = suffix.split_once('.') {
+        if let Some(route) = registry.routes.get(&format!("*.{}", parent)) {
+            return Some(route);
+        }
+        suffix = parent;
+    }
+    registry.routes.get(DEFAULT_ROUTE)
+}
+
+fn backend_port(server_path: &std::path::Path) -> u16 {
+    ServerProperties::load(server_path)
+        .get_u16("server-port")
+        .unwrap_or(DEFAULT_SERVER_PORT)
+}
+
+/// PROXY protocol v2 header of a TCP connection from `source` to `destination`
+pub fn proxy_header(source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+    let mut header = PROXY_V2_SIGNATURE.to_vec();
+    // Version 2, PROXY command
+    header.push(0x21);
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+            header.push(0x11);
+            header.extend_from_slice(&12u16.to_be_bytes());
+            header.extend_from_slice(&source_ip.octets());
+            header.extend_from_slice(&destination_ip.octets());
+        }
+        (source_ip, destination_ip) => {
+            let v6 = |ip: IpAddr| match ip {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+            header.push(0x21);
+            header.extend_from_slice(&36u16.to_be_bytes());
+            header.extend_from_slice(&v6(source_ip).octets());
+            header.extend_from_slice(&v6(destination_ip).octets());
+        }
+    }
+    header.extend_from_slice(&source.port().to_be_bytes());
+    header.extend_from_slice(&destination.port().to_be_bytes());
+    header
+}
+
+/// Reads the PROXY protocol v1 or v2 header sent by a load balancer in front of the router,
+/// `None` for health checks and unknown address families
+async fn read_proxy_header<R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> Result<Option<SocketAddr>, String> {
+    let invalid = |reason: &str| format!("Invalid PROXY protocol header : {}", reason);
+    let mut start = [0u8; 12];
+    stream
+        .read_exact(&mut start)
+        .await
+        .map_err(|e| invalid(&e.to_string()))?;
+    if start == PROXY_V2_SIGNATURE {
+        let mut fixed = [0u8; 4];
+        stream
+            .read_exact(&mut fixed)
+            .await
+            .map_err(|e| invalid(&e.to_string()))?;
+        let mut addresses = vec![0u8; u16::from_be_bytes([fixed[2], fixed[3]]) as usize];
+        stream
+            .read_exact(&mut addresses)
+            .await
+            .map_err(|e| invalid(&e.to_string()))?;
+        // LOCAL command
+        if fixed[0] & 0x0F == 0 {
+            return Ok(None);
+        }
+        let source = match fixed[1] >> 4 {
+            0x1 if addresses.len() >= 12 => {
+                let ip: [u8; 4] = addresses[0..4].try_into().unwrap();
+                SocketAddr::new(
+                    IpAddr::from(ip),
+                    u16::from_be_bytes([addresses[8], addresses[9]]),
+                )
+            }
+            0x2 if addresses.len() >= 36 => {
+                let ip: [u8; 16] = addresses[0..16].try_into().unwrap();
+                SocketAddr::new(
+                    IpAddr::from(ip),
+                    u16::from_be_bytes([addresses[32], addresses[33]]),
+                )
+            }
+            _ => return Ok(None),
+        };
+        return Ok(Some(source));
+    }
+    if !start.starts_with(b"PROXY ") {
+        return Err(String::from("Missing PROXY protocol header"));
+    }
+    let mut line = start.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= PROXY_V1_MAX_LENGTH {
+            return Err(invalid("line too long"));
+        }
+        line.push(
+            stream
+                .read_u8()
+                .await
+                .map_err(|e| invalid(&e.to_string()))?,
+        );
+    }
+    // PROXY TCP4 <source> <destination> <source port> <destination port>
+    let line = String::from_utf8_lossy(&line);
+    let fields: Vec<&str> = line.trim_end().split(' ').collect();
+    match fields.as_slice() {
+        ["PROXY", "TCP4" | "TCP6", source, _, source_port, _] => Ok(Some(SocketAddr::new(
+            source.parse().map_err(|_| invalid("source address"))?,
+            source_port.parse().map_err(|_| invalid("source port"))?,
+        ))),
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        _ => Err(invalid(&line)),
+    }
+}
+
+/// Forwards Minecraft connections to the server registered for the hostname players connect to
+#[derive(Debug)]
+pub struct Router {
+    port: u16,
+    /// Connections start with a PROXY protocol header giving the player address
+    accept_proxy_protocol: bool,
+}
+
+impl Router {
+    pub fn build(port: u16, accept_proxy_protocol: bool) -> Self {
+        Self {
+            port,
+            accept_proxy_protocol,
+        }
+    }
+
+    /// Routes are read from the registry on each connection, changes apply without a restart
+    pub async fn run(self) {
+        let listener = match TcpListener::bind(("0.0.0.0", self.port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                println!("❌ Unable to listen on port {} : {}", self.port, e);
+                return;
+            }
+        };
+        println!("✅ Router listening on port {}, Ctrl-C to stop", self.port);
+        let router = Arc::new(self);
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let Ok((client, address)) = accepted else {
+                        continue;
+                    };
+                    let router = router.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = router.handle(client, address).await {
+                            println!("⚠️ {} : {}", address, e);
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => {
+                    println!("➡️ Router stopped");
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, mut client: TcpStream, address: SocketAddr) -> Result<(), String> {
+        let _ = client.set_nodelay(true);
+        let (source, (id, payload)) = tokio::time::timeout(HANDSHAKE_TIMEOUT, async {
+            let source = if self.accept_proxy_protocol {
+                read_proxy_header(&mut client).await?.unwrap_or(address)
+            } else {
+                address
+            };
+            let packet = mc_protocol::read_packet(&mut client)
+                .await
+                .map_err(|e| format!("Invalid handshake : {}", e))?;
+            Ok::<_, String>((source, packet))
+        })
+        .await
+        .map_err(|_| String::from("Connection without handshake dropped"))??;
+        let handshake = Handshake::decode(&payload)
+            .filter(|_| id == 0x00)
+            .ok_or(String::from("Invalid handshake"))?;
+        let is_login = handshake.next_state != 1;
+        let hostname = normalize_hostname(&handshake.address);
+
+        let registry = Registry::load();
+        let Some(route) = find_route(&registry, &hostname) else {
+            if is_login {
+                let _ = mc_protocol::disconnect(&mut client, "Unknown server address").await;
+            }
+            return Err(format!("No route for '{}'", hostname));
+        };
+        let server = match registry.get(&route.server) {
+            Ok(server) => server,
+            Err(e) => {
+                if is_login {
+                    let _ = mc_protocol::disconnect(&mut client, "Unknown server address").await;
+                }
+                return Err(e);
+            }
+        };
+        let port = backend_port(&server.path);
+
+        if let (false, Some(motd)) = (is_login, &route.motd) {
+            let status = StatusTarget::direct("127.0.0.1", port);
+            let backend = server_status::ping(&status).await.ok();
+            let status = json!({
+                "version": {
+                    "name": backend.as_ref().and_then(|s| s.version.clone()).unwrap_or(String::from("MCT")),
+                    "protocol": backend.as_ref().and_then(|s| s.protocol).unwrap_or(handshake.protocol as i64),
+                },
+                "players": {
+                    "max": backend.as_ref().map(|s| s.max_players).unwrap_or(0),
+                    "online": backend.as_ref().map(|s| s.online_players).unwrap_or(0),
+                },
+                "description": { "text": motd },
+            });
+            let _ = mc_protocol::serve_status(&mut client, &status.to_string()).await;
+            return Ok(());
+        }
+
+        let mut backend = match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(backend) => backend,
+            Err(e) => {
+                if is_login {
+                    let _ = mc_protocol::disconnect(&mut client, "The server is offline").await;
+                }
+                return Err(format!(
+                    "Server '{}' unreachable on port {} : {}",
+                    route.server, port, e
+                ));
+            }
+        };
+        let _ = backend.set_nodelay(true);
+        if route.proxy_protocol {
+            let destination = client.local_addr().map_err(|e| e.to_string())?;
+            backend
+                .write_all(&proxy_header(source, destination))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        mc_protocol::write_packet(&mut backend, 0x00, &payload)
+            .await
+            .map_err(|e| e.to_string())?;
+        if is_login {
+            println!("➡️ {} joined {} ({})", source, hostname, route.server);
+        }
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut backend).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(header: &[u8]) -> Result<Option<SocketAddr>, String> {
+        let mut stream = header;
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(read_proxy_header(&mut stream))
+    }
+
+    fn registry(hostnames: &[&str]) -> Registry {
+        let mut registry = Registry::default();
+        for hostname in hostnames {
+            registry.routes.insert(
+                hostname.to_string(),
+                Route {
+                    server: hostname.to_string(),
+                    motd: None,
+                    proxy_protocol: false,
+                },
+            );
+        }
+        registry
+    }
+
+    fn route_of(registry: &Registry, hostname: &str) -> Option<String> {
+        find_route(registry, hostname).map(|route| route.server.clone())
+    }
+
+    #[test]
+    fn hostnames_are_normalized() {
+        assert_eq!(normalize_hostname("Play.Example.com."), "play.example.com");
+        assert_eq!(normalize_hostname("play.example.com\0FML\0"), "play.example.com");
+        assert_eq!(normalize_hostname("play.example.com\0FML3\0"), "play.example.com");
+        assert_eq!(
+            normalize_hostname("play.example.com///203.0.113.7:51234///1700000000"),
+            "play.example.com"
+        );
+    }
+
+    #[test]
+    fn exact_route_wins_over_wildcards() {
+        let registry = registry(&["play.example.com", "*.example.com", "*.eu.example.com", "*"]);
+        assert_eq!(route_of(&registry, "play.example.com").as_deref(), Some("play.example.com"));
+        assert_eq!(route_of(&registry, "lobby.eu.example.com").as_deref(), Some("*.eu.example.com"));
+        assert_eq!(route_of(&registry, "a.b.example.com").as_deref(), Some("*.example.com"));
+        assert_eq!(route_of(&registry, "example.com").as_deref(), Some("*"));
+        assert_eq!(route_of(&registry, "other.net").as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn no_route_without_default() {
+        let registry = registry(&["*.example.com"]);
+        assert_eq!(route_of(&registry, "example.org"), None);
+    }
+
+    #[test]
+    fn proxy_v1_header() {
+        assert_eq!(
+            read(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 25565\r\n"),
+            Ok(Some("203.0.113.7:51234".parse().unwrap()))
+        );
+        assert_eq!(
+            read(b"PROXY TCP6 2001:db8::7 2001:db8::1 51234 25565\r\n"),
+            Ok(Some("[2001:db8::7]:51234".parse().unwrap()))
+        );
+        assert_eq!(read(b"PROXY UNKNOWN\r\n"), Ok(None));
+        assert_eq!(
+            read(b"PROXY UNKNOWN 203.0.113.7 10.0.0.1 51234 25565\r\n"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn proxy_v1_line_too_long() {
+        let mut line = b"PROXY TCP4 ".to_vec();
+        line.extend(std::iter::repeat_n(b'1', PROXY_V1_MAX_LENGTH));
+        line.extend_from_slice(b"\r\n");
+        assert!(read(&line).unwrap_err().contains("too long"));
+    }
+
+    #[test]
+    fn missing_proxy_header() {
+        assert!(read(b"\x10\x00\xff\x05\x09localhost").is_err());
+    }
+
+    #[test]
+    fn proxy_v2_ipv4_round_trip() {
+        let source: SocketAddr = "203.0.113.7:51234".parse().unwrap();
+        let header = proxy_header(source, "10.0.0.1:25565".parse().unwrap());
+        assert_eq!(header.len(), 28);
+        assert_eq!(header[12..16], [0x21, 0x11, 0x00, 0x0C]);
+        assert_eq!(read(&header), Ok(Some(source)));
+    }
+
+    #[test]
+    fn proxy_v2_ipv6_bytes() {
+        let source: SocketAddr = "[2001:db8::7]:51234".parse().unwrap();
+        let header = proxy_header(source, "[2001:db8::1]:25565".parse().unwrap());
+        let mut expected = PROXY_V2_SIGNATURE.to_vec();
+        expected.extend_from_slice(&[0x21, 0x21, 0x00, 0x24]);
+        expected.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x07]);
+        expected.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+        expected.extend_from_slice(&51234u16.to_be_bytes());
+        expected.extend_from_slice(&25565u16.to_be_bytes());
+        assert_eq!(header, expected);
+        assert_eq!(read(&header), Ok(Some(source)));
+    }
+
+    #[test]
+    fn proxy_v2_mixed_families_use_ipv6() {
+        let header = proxy_header(
+            "203.0.113.7:51234".parse().unwrap(),
+            "[2001:db8::1]:25565".parse().unwrap(),
+        );
+        assert_eq!(header[13], 0x21);
+        assert_eq!(
+            read(&header),
+            Ok(Some("[::ffff:203.0.113.7]:51234".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn proxy_v2_local_command() {
+        // Health check of a load balancer, no address
+        let mut header = PROXY_V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(read(&header), Ok(None));
+
+        // LOCAL with addresses, they must be skipped
+        let mut header = proxy_header(
+            "203.0.113.7:51234".parse().unwrap(),
+            "10.0.0.1:25565".parse().unwrap(),
+        );
+        header[12] = 0x20;
+        assert_eq!(read(&header), Ok(None));
+    }
+}
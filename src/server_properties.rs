@@ -0,0 +1,448 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SERVER_PROPERTIES: &str = "server.properties";
+
+/// Type of the value of a known server.properties key
+#[derive(Debug, Clone, Copy)]
+pub enum PropertyKind {
+    Bool,
+    Int { min: i64, max: i64 },
+    Choice(&'static [&'static str]),
+    Text,
+}
+
+/// A vanilla server.properties key with its type and default value
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyDefinition {
+    pub key: &'static str,
+    pub kind: PropertyKind,
+    pub default: &'static str,
+}
+
+const fn property(key: &'static str, kind: PropertyKind, default: &'static str) -> PropertyDefinition {
+    PropertyDefinition { key, kind, default }
+}
+
+const BOOL: PropertyKind = PropertyKind::Bool;
+const TEXT: PropertyKind = PropertyKind::Text;
+const PORT: PropertyKind = PropertyKind::Int { min: 1, max: 65535 };
+const fn int(min: i64, max: i64) -> PropertyKind {
+    PropertyKind::Int { min, max }
+}
+
+/// Keys written by a vanilla 1.21 server
+pub const KNOWN_PROPERTIES: &[PropertyDefinition] = &[
+    property("accepts-transfers", BOOL, "false"),
+    property("allow-flight", BOOL, "false"),
+    property("allow-nether", BOOL, "true"),
+    property("broadcast-console-to-ops", BOOL, "true"),
+    property("broadcast-rcon-to-ops", BOOL, "true"),
+    property("bug-report-link", TEXT, ""),
+    property(
+        "difficulty",
+        PropertyKind::Choice(&["peaceful", "easy", "normal", "hard"]),
+        "easy",
+    ),
+    property("enable-command-block", BOOL, "false"),
+    property("enable-jmx-monitoring", BOOL, "false"),
+    property("enable-query", BOOL, "false"),
+    property("enable-rcon", BOOL, "false"),
+    property("enable-status", BOOL, "true"),
+    property("enforce-secure-profile", BOOL, "true"),
+    property("enforce-whitelist", BOOL, "false"),
+    property("entity-broadcast-range-percentage", int(10, 1000), "100"),
+    property("force-gamemode", BOOL, "false"),
+    property("function-permission-level", int(1, 4), "2"),
+    property(
+        "gamemode",
+        PropertyKind::Choice(&["survival", "creative", "adventure", "spectator"]),
+        "survival",
+    ),
+    property("generate-structures", BOOL, "true"),
+    property("generator-settings", TEXT, "{}"),
+    property("hardcore", BOOL, "false"),
+    property("hide-online-players", BOOL, "false"),
+    property("initial-disabled-packs", TEXT, ""),
+    property("initial-enabled-packs", TEXT, "vanilla"),
+    property("level-name", TEXT, "world"),
+    property("level-seed", TEXT, ""),
+    property("level-type", TEXT, "minecraft:normal"),
+    property("log-ips", BOOL, "true"),
+    property("max-chained-neighbor-updates", int(i32::MIN as i64, i32::MAX as i64), "1000000"),
+    property("max-players", int(0, i32::MAX as i64), "20"),
+    property("max-tick-time", int(-1, i64::MAX), "60000"),
+    property("max-world-size", int(1, 29999984), "29999984"),
+    property("motd", TEXT, "A Minecraft Server"),
+    property("network-compression-threshold", int(-1, i32::MAX as i64), "256"),
+    property("online-mode", BOOL, "true"),
+    property("op-permission-level", int(0, 4), "4"),
+    property("pause-when-empty-seconds", int(0, i32::MAX as i64), "60"),
+    property("player-idle-timeout", int(0, i32::MAX as i64), "0"),
+    property("prevent-proxy-connections", BOOL, "false"),
+    property("pvp", BOOL, "true"),
+    property("query.port", PORT, "25565"),
+    property("rate-limit", int(0, i32::MAX as i64), "0"),
+    property("rcon.password", TEXT, ""),
+    property("rcon.port", PORT, "25575"),
+    property(
+        "region-file-compression",
+        PropertyKind::Choice(&["deflate", "lz4", "none"]),
+        "deflate",
+    ),
+    property("require-resource-pack", BOOL, "false"),
+    property("resource-pack", TEXT, ""),
+    property("resource-pack-id", TEXT, ""),
+    property("resource-pack-prompt", TEXT, ""),
+    property("resource-pack-sha1", TEXT, ""),
+    property("server-ip", TEXT, ""),
+    property("server-port", PORT, "25565"),
+    property("simulation-distance", int(3, 32), "10"),
+    property("spawn-monsters", BOOL, "true"),
+    property("spawn-protection", int(0, i32::MAX as i64), "16"),
+    property("sync-chunk-writes", BOOL, "true"),
+    property("text-filtering-config", TEXT, ""),
+    property("text-filtering-version", int(0, 1), "0"),
+    property("use-native-transport", BOOL, "true"),
+    property("view-distance", int(3, 32), "10"),
+    property("white-list", BOOL, "false"),
+];
+
+pub fn definition(key: &str) -> Option<&'static PropertyDefinition> {
+    KNOWN_PROPERTIES.iter().find(|p| p.key == key)
+}
+
+impl PropertyDefinition {
+    /// Checks a value against the type of the key, booleans are normalized to lowercase
+    pub fn validate(&self, value: &str) -> Result<String, String> {
+        match self.kind {
+            PropertyKind::Bool => match value.to_lowercase().as_str() {
+                v @ ("true" | "false") => Ok(v.to_owned()),
+                _ => Err(format!("{} expects true | false", self.key)),
+            },
+            PropertyKind::Int { min, max } => match value.trim().parse::<i64>() {
+                Ok(v) if (min..=max).contains(&v) => Ok(v.to_string()),
+                _ => Err(format!("{} expects a number between {} and {}", self.key, min, max)),
+            },
+            PropertyKind::Choice(choices) => {
+                let lowercase = value.to_lowercase();
+                if choices.contains(&lowercase.as_str()) {
+                    Ok(lowercase)
+                } else {
+                    Err(format!("{} expects {}", self.key, choices.join(" | ")))
+                }
+            }
+            PropertyKind::Text => Ok(value.to_owned()),
+        }
+    }
+
+    pub fn type_name(&self) -> String {
+        match self.kind {
+            PropertyKind::Bool => String::from("bool"),
+            PropertyKind::Int { .. } => String::from("int"),
+            PropertyKind::Choice(choices) => choices.join("|"),
+            PropertyKind::Text => String::from("text"),
+        }
+    }
+}
+
+/// A line of server.properties, untouched lines are written back as they were read
+#[derive(Debug, Clone)]
+enum PropertyLine {
+    Entry {
+        key: String,
+        value: String,
+        raw: Option<String>,
+    },
+    Other(String),
+}
+
+/// server.properties of a server directory, keeping comments and ordering
+#[derive(Debug, Clone)]
+pub struct ServerProperties {
+    path: PathBuf,
+    lines: Vec<PropertyLine>,
+}
+
+impl ServerProperties {
+    /// Reads server.properties from the server directory, an absent file gives empty properties
+    pub fn load(server_path: &Path) -> Self {
+        let path = server_path.join(SERVER_PROPERTIES);
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        Self {
+            path,
+            lines: content.lines().map(parse_line).collect(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            PropertyLine::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Value of the key, or its vanilla default when absent
+    pub fn get_or_default(&self, key: &str) -> Option<String> {
+        self.get(key)
+            .map(|v| v.to_owned())
+            .or_else(|| definition(key).map(|d| d.default.to_owned()))
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        self.get_or_default(key).is_some_and(|v| v == "true")
+    }
+
+    pub fn get_u16(&self, key: &str) -> Option<u16> {
+        self.get_or_default(key).and_then(|v| v.trim().parse().ok())
+    }
+
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                PropertyLine::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+                PropertyLine::Other(_) => None,
+            })
+            .collect()
+    }
+
+    /// Validates known keys before setting them, unknown keys are accepted as text
+    pub fn set_checked(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = match definition(key) {
+            Some(definition) => definition.validate(value)?,
+            None => value.to_owned(),
+        };
+        self.set(key, value);
+        Ok(())
+    }
+
+    /// Sets a value, keeping its position if the key already exists
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        for line in self.lines.iter_mut() {
+            if let PropertyLine::Entry { key: k, value: v, raw } = line {
+                if k == key {
+                    *v = value;
+                    *raw = None;
+                    return;
+                }
+            }
+        }
+        self.lines.push(PropertyLine::Entry {
+            key: key.to_owned(),
+            value,
+            raw: None,
+        });
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut content = String::new();
+        for line in &self.lines {
+            match line {
+                PropertyLine::Entry {
+                    raw: Some(raw), ..
+                } => content.push_str(raw),
+                PropertyLine::Entry { key, value, .. } => {
+                    content.push_str(&escape(key, true));
+                    content.push('=');
+                    content.push_str(&escape(value, false));
+                }
+                PropertyLine::Other(other) => content.push_str(other),
+            }
+            content.push('\n');
+        }
+        fs::write(&self.path, content)
+    }
+}
+
+fn parse_line(line: &str) -> PropertyLine {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+        return PropertyLine::Other(line.to_owned());
+    }
+    // The key ends at the first unescaped '=' or ':'
+    let mut separator = None;
+    let mut escaped = false;
+    for (index, c) in trimmed.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' {
+            separator = Some(index);
+            break;
+        }
+    }
+    let (key, value) = match separator {
+        Some(index) => (&trimmed[..index], &trimmed[index + 1..]),
+        None => (trimmed, ""),
+    };
+    PropertyLine::Entry {
+        key: unescape(key.trim_end()),
+        value: unescape(value.trim_start()),
+        raw: Some(line.to_owned()),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    result.push(c);
+                }
+            }
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+/// Same escaping as java.util.Properties#store(Writer), the file is written as UTF-8
+fn escape(text: &str, is_key: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (index, c) in text.chars().enumerate() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '=' | ':' | '#' | '!' => {
+                result.push('\\');
+                result.push(c);
+            }
+            ' ' if is_key || index == 0 => result.push_str("\\ "),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// `MCT Config List` : every key of the file, then the known keys left to their default
+pub fn print_list(server_path: &Path) {
+    let properties = ServerProperties::load(server_path);
+    if !properties.path.is_file() {
+        println!(
+            "➡️ No server.properties in '{}' yet, showing the defaults",
+            server_path.to_string_lossy()
+        );
+    }
+    for (key, value) in properties.entries() {
+        match definition(key) {
+            Some(definition) => println!("{}={}    [{}]", key, value, definition.type_name()),
+            None => println!("{}={}    [unknown key]", key, value),
+        }
+    }
+    for definition in KNOWN_PROPERTIES {
+        if properties.get(definition.key).is_none() {
+            println!(
+                "{}={}    [{}, default]",
+                definition.key,
+                definition.default,
+                definition.type_name()
+            );
+        }
+    }
+}
+
+/// `MCT Config Get`
+pub fn print_value(server_path: &Path, key: &str) {
+    let properties = ServerProperties::load(server_path);
+    match (properties.get(key), definition(key)) {
+        (Some(value), _) => println!("{}", value),
+        (None, Some(definition)) => println!("{}    (default)", definition.default),
+        (None, None) => println!("❌ Unknown key '{}'", key),
+    }
+}
+
+/// Sets several keys at once and saves, stops at the first invalid value
+pub fn apply(server_path: &Path, values: &[(&str, String)]) -> Result<(), String> {
+    let mut properties = ServerProperties::load(server_path);
+    for (key, value) in values {
+        properties.set_checked(key, value)?;
+    }
+    properties
+        .save()
+        .map_err(|e| format!("Error while writting server.properties : {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `content` as the server.properties of a fresh directory, sets `values` and returns the saved file
+    fn round_trip(name: &str, content: &str, values: &[(&str, &str)]) -> (ServerProperties, String) {
+        let dir = std::env::temp_dir().join(format!("mct-properties-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(SERVER_PROPERTIES), content).unwrap();
+        let mut properties = ServerProperties::load(&dir);
+        for (key, value) in values {
+            properties.set_checked(key, value).unwrap();
+        }
+        properties.save().unwrap();
+        let saved = fs::read_to_string(dir.join(SERVER_PROPERTIES)).unwrap();
+        let reloaded = ServerProperties::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        (reloaded, saved)
+    }
+
+    #[test]
+    fn comments_and_order_are_kept() {
+        let content = "#Minecraft server properties\n#Fri Jan 03 10:00:00 CET 2025\nmotd=A Minecraft Server\n\n! legacy comment\nserver-port=25565\npvp=true\n";
+        let (properties, saved) = round_trip("order", content, &[("server-port", "25570")]);
+        assert_eq!(
+            saved,
+            "#Minecraft server properties\n#Fri Jan 03 10:00:00 CET 2025\nmotd=A Minecraft Server\n\n! legacy comment\nserver-port=25570\npvp=true\n"
+        );
+        assert_eq!(properties.get_u16("server-port"), Some(25570));
+    }
+
+    #[test]
+    fn untouched_lines_keep_their_escapes() {
+        let content = "motd=\\u00A7aWelcome\\: \\=friends\\=\nlevel-seed=a\\:b\nresource-pack=https\\://example.com/pack.zip\n";
+        let (properties, saved) = round_trip("escapes", content, &[("level-seed", "c:d=e")]);
+        assert_eq!(properties.get("motd"), Some("§aWelcome: =friends="));
+        assert_eq!(properties.get("resource-pack"), Some("https://example.com/pack.zip"));
+        assert_eq!(properties.get("level-seed"), Some("c:d=e"));
+        assert_eq!(
+            saved,
+            "motd=\\u00A7aWelcome\\: \\=friends\\=\nlevel-seed=c\\:d\\=e\nresource-pack=https\\://example.com/pack.zip\n"
+        );
+    }
+
+    #[test]
+    fn set_values_are_escaped() {
+        let (properties, saved) = round_trip("set", "motd=old\n", &[("motd", " Été #1 \\ ok")]);
+        assert_eq!(saved, "motd=\\ Été \\#1 \\\\ ok\n");
+        assert_eq!(properties.get("motd"), Some(" Été #1 \\ ok"));
+    }
+
+    #[test]
+    fn unknown_keys_are_appended() {
+        let (properties, saved) = round_trip("unknown", "pvp=true\nmy-plugin\\:key=1\n", &[("custom-key", "x=y")]);
+        assert_eq!(saved, "pvp=true\nmy-plugin\\:key=1\ncustom-key=x\\=y\n");
+        assert_eq!(properties.get("my-plugin:key"), Some("1"));
+        assert_eq!(properties.get("custom-key"), Some("x=y"));
+    }
+
+    #[test]
+    fn known_keys_are_validated() {
+        let mut properties = ServerProperties::load(Path::new("/nonexistent"));
+        assert!(properties.set_checked("server-port", "70000").is_err());
+        assert!(properties.set_checked("difficulty", "insane").is_err());
+        assert!(properties.set_checked("pvp", "yes").is_err());
+        assert_eq!(properties.get("server-port"), None);
+    }
+}
@@ -0,0 +1,344 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::dns;
+use crate::mc_protocol::{self, Handshake, ANY_PROTOCOL};
+use crate::router;
+use crate::server_properties::ServerProperties;
+
+pub const DEFAULT_SERVER_PORT: u16 = 25565;
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a server answers to a Server List Ping
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub address: String,
+    pub version: Option<String>,
+    pub protocol: Option<i64>,
+    pub motd: String,
+    pub online_players: i64,
+    pub max_players: i64,
+    /// Player names from the sample list, servers may hide or fake it
+    pub players: Vec<String>,
+    /// `None` when the server did not answer the ping packet
+    pub latency_ms: Option<u128>,
+    /// True when the server only answered the pre-1.7 ping
+    pub legacy: bool,
+}
+
+/// Host and port to ping, resolved from a server directory, `host:port` or a hostname with SRV record
+#[derive(Debug, Clone)]
+pub struct StatusTarget {
+    /// Hostname as given by the user, sent in the handshake
+    pub host: String,
+    pub port: u16,
+    /// Address to connect to, differs from host:port when a SRV record exists
+    pub connect_address: String,
+    /// Send a PROXY protocol v2 header first, for servers with proxy-protocol enabled
+    pub proxy_protocol: bool,
+}
+
+impl StatusTarget {
+    pub async fn resolve(target: &str) -> Self {
+        let path = Path::new(target);
+        if path.is_dir() {
+            let port = ServerProperties::load(path)
+                .get_u16("server-port")
+                .unwrap_or(DEFAULT_SERVER_PORT);
+            return Self::direct("127.0.0.1", port);
+        }
+        if let Some((host, port)) = split_host_port(target) {
+            return Self::direct(host, port);
+        }
+        if target.parse::<std::net::IpAddr>().is_err() {
+            if let Some((srv_host, srv_port)) = dns::resolve_minecraft_srv(target).await {
+                return Self {
+                    host: target.to_owned(),
+                    port: srv_port,
+                    connect_address: format!("{}:{}", srv_host.trim_end_matches('.'), srv_port),
+                    proxy_protocol: false,
+                };
+            }
+        }
+        Self::direct(target, DEFAULT_SERVER_PORT)
+    }
+
+    pub fn direct(host: &str, port: u16) -> Self {
+        let connect_address = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+        Self {
+            host: host.to_owned(),
+            port,
+            connect_address,
+            proxy_protocol: false,
+        }
+    }
+
+    pub fn with_proxy_protocol(mut self, proxy_protocol: bool) -> Self {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
+    async fn connect(&self) -> std::io::Result<TcpStream> {
+        let mut stream = TcpStream::connect(&self.connect_address).await?;
+        if self.proxy_protocol {
+            let header = router::proxy_header(stream.local_addr()?, stream.peer_addr()?);
+            stream.write_all(&header).await?;
+        }
+        Ok(stream)
+    }
+}
+
+/// Splits `host:port` and `[ipv6]:port`, `None` when no port is given
+pub fn split_host_port(target: &str) -> Option<(&str, u16)> {
+    if let Some(rest) = target.strip_prefix('[') {
+        let (host, port) = rest.split_once("]:")?;
+        return Some((host, port.parse().ok()?));
+    }
+    let (host, port) = target.rsplit_once(':')?;
+    if host.contains(':') {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
+
+/// Pings the server, falling back to the legacy ping for servers older than 1.7
+pub async fn ping(target: &StatusTarget) -> Result<ServerStatus, String> {
+    match tokio::time::timeout(STATUS_TIMEOUT, modern_ping(target)).await {
+        Ok(Ok(status)) => Ok(status),
+        modern_error => {
+            let modern_error = match modern_error {
+                Ok(Err(e)) => e.to_string(),
+                _ => String::from("timed out"),
+            };
+            match tokio::time::timeout(STATUS_TIMEOUT, legacy_ping(target)).await {
+                Ok(Ok(status)) => Ok(status),
+                _ => Err(modern_error),
+            }
+        }
+    }
+}
+
+async fn modern_ping(target: &StatusTarget) -> std::io::Result<ServerStatus> {
+    let mut stream = target.connect().await?;
+    let handshake = Handshake {
+        protocol: ANY_PROTOCOL,
+        address: target.host.clone(),
+        port: target.port,
+        next_state: 1,
+    };
+    mc_protocol::write_packet(&mut stream, 0x00, &handshake.encode()).await?;
+    mc_protocol::write_packet(&mut stream, 0x00, &[]).await?;
+
+    let (id, payload) = mc_protocol::read_packet(&mut stream).await?;
+    let invalid = |reason: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_owned());
+    if id != 0x00 {
+        return Err(invalid("unexpected status response"));
+    }
+    let json = mc_protocol::read_string_from(&mut payload.as_slice())
+        .ok_or_else(|| invalid("invalid status response"))?;
+    let status: Value = serde_json::from_str(&json).map_err(|e| invalid(&e.to_string()))?;
+
+    let sent = Instant::now();
+    let payload = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+        .to_be_bytes();
+    mc_protocol::write_packet(&mut stream, 0x01, &payload).await?;
+    // Some servers close the connection instead of answering the ping
+    let latency_ms = match mc_protocol::read_packet(&mut stream).await {
+        Ok(_) => Some(sent.elapsed().as_millis()),
+        Err(_) => None,
+    };
+
+    Ok(ServerStatus {
+        address: target.connect_address.clone(),
+        version: status["version"]["name"].as_str().map(|v| v.to_owned()),
+        protocol: status["version"]["protocol"].as_i64(),
+        motd: chat_to_text(&status["description"]),
+        online_players: status["players"]["online"].as_i64().unwrap_or_default(),
+        max_players: status["players"]["max"].as_i64().unwrap_or_default(),
+        players: status["players"]["sample"]
+            .as_array()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .filter_map(|p| p["name"].as_str().map(|n| n.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        latency_ms,
+        legacy: false,
+    })
+}
+
+/// 0xFE 0x01 ping understood by servers from beta 1.8 to 1.6
+async fn legacy_ping(target: &StatusTarget) -> std::io::Result<ServerStatus> {
+    let sent = Instant::now();
+    let mut stream = target.connect().await?;
+    stream.write_all(&[0xFE, 0x01]).await?;
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid legacy response");
+    if stream.read_u8().await? != 0xFF {
+        return Err(invalid());
+    }
+    let length = stream.read_u16().await? as usize;
+    let mut raw = vec![0u8; length * 2];
+    stream.read_exact(&mut raw).await?;
+    let latency_ms = Some(sent.elapsed().as_millis());
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    let text = String::from_utf16_lossy(&units);
+
+    let mut status = ServerStatus {
+        address: target.connect_address.clone(),
+        version: None,
+        protocol: None,
+        motd: String::new(),
+        online_players: 0,
+        max_players: 0,
+        players: vec![],
+        latency_ms,
+        legacy: true,
+    };
+    if let Some(fields) = text.strip_prefix("§1\0") {
+        // 1.4 - 1.6 : protocol, version, motd, online, max
+        let fields: Vec<&str> = fields.split('\0').collect();
+        if fields.len() < 5 {
+            return Err(invalid());
+        }
+        status.protocol = fields[0].parse().ok();
+        status.version = Some(fields[1].to_owned());
+        status.motd = fields[2].to_owned();
+        status.online_players = fields[3].parse().unwrap_or_default();
+        status.max_players = fields[4].parse().unwrap_or_default();
+    } else {
+        // beta 1.8 - 1.3 : motd§online§max
+        let fields: Vec<&str> = text.rsplitn(3, '§').collect();
+        if fields.len() < 3 {
+            return Err(invalid());
+        }
+        status.max_players = fields[0].parse().unwrap_or_default();
+        status.online_players = fields[1].parse().unwrap_or_default();
+        status.motd = fields[2].to_owned();
+    }
+    Ok(status)
+}
+
+/// Flattens a chat component (string, object with text/extra or array) to plain text
+pub fn chat_to_text(component: &Value) -> String {
+    match component {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(chat_to_text).collect(),
+        Value::Object(object) => {
+            let mut text = object
+                .get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_owned();
+            if let Some(extra) = object.get("extra") {
+                text.push_str(&chat_to_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+impl ServerStatus {
+    pub fn display(&self) {
+        println!("✅ {} is online", self.address);
+        println!(
+            "   Version: {}{}",
+            self.version.as_deref().unwrap_or("Unknown"),
+            self.protocol
+                .map(|p| format!(" (protocol {})", p))
+                .unwrap_or_default()
+        );
+        println!("   MOTD: {}", strip_formatting(&self.motd).replace('\n', "\n         "));
+        println!("   Players: {}/{}", self.online_players, self.max_players);
+        if !self.players.is_empty() {
+            println!("   Online: {}", self.players.join(", "));
+        }
+        match self.latency_ms {
+            Some(latency) => println!("   Latency: {} ms", latency),
+            None => println!("   Latency: Unknown"),
+        }
+        if self.legacy {
+            println!("   ➡️ Answered the legacy ping only");
+        }
+    }
+}
+
+/// Removes § formatting codes
+pub fn strip_formatting(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Answers status requests like a server with proxy-protocol enabled,
+    /// connections without a PROXY v2 header are closed
+    async fn proxied_server(listener: TcpListener) {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut header = [0u8; 16];
+            // The legacy ping sends 2 bytes only, do not wait for the rest of a header
+            let read = tokio::time::timeout(Duration::from_millis(200), stream.read_exact(&mut header)).await;
+            if !matches!(read, Ok(Ok(_))) || &header[..12] != b"\r\n\r\n\0\r\nQUIT\n" {
+                continue;
+            }
+            let mut addresses = vec![0u8; u16::from_be_bytes([header[14], header[15]]) as usize];
+            stream.read_exact(&mut addresses).await.unwrap();
+            let (_, handshake) = mc_protocol::read_packet(&mut stream).await.unwrap();
+            assert!(Handshake::decode(&handshake).is_some());
+            mc_protocol::read_packet(&mut stream).await.unwrap();
+            let mut response = vec![];
+            mc_protocol::write_string(
+                &mut response,
+                r#"{"version":{"name":"1.21.4","protocol":769},"players":{"online":2,"max":20},"description":"A server"}"#,
+            );
+            mc_protocol::write_packet(&mut stream, 0x00, &response).await.unwrap();
+        }
+    }
+
+    fn ping_proxied_server(proxy_protocol: bool) -> Result<ServerStatus, String> {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(proxied_server(listener));
+            let status = ping(&StatusTarget::direct("127.0.0.1", port).with_proxy_protocol(proxy_protocol)).await;
+            server.abort();
+            status
+        })
+    }
+
+    #[test]
+    fn ping_sends_proxy_header() {
+        let status = ping_proxied_server(true).unwrap();
+        assert_eq!(status.version.as_deref(), Some("1.21.4"));
+        assert_eq!(status.online_players, 2);
+        assert!(ping_proxied_server(false).is_err());
+    }
+}
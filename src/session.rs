@@ -0,0 +1,633 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc::UnboundedSender};
+
+use crate::fabric_request::FabricMCRequest;
+use crate::papermc_request::PaperMCRequest;
+use crate::supervisor::LaunchOptions;
+
+/// Platform and settings of a server, written by MCT
+pub const METADATA_FILE: &str = "MCA.json";
+/// Pid of the MCT process running the server of a directory, then the pid of the java process
+pub const PID_FILE: &str = "MCA.pid";
+/// Unix socket exposing the console of a detached server
+pub const CONSOLE_SOCKET: &str = "MCA_console.sock";
+/// Output of a detached MCT process
+pub const DAEMON_LOG: &str = "MCA_daemon.log";
+/// Number of console lines replayed when attaching
+const CONSOLE_HISTORY: usize = 200;
+/// Locked by the server while it runs, in the world folder
+const WORLD_LOCK: &str = "session.lock";
+const RCON_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Returns the pid written in the pidfile of `server_path` if that process is still alive
+pub fn running_pid(server_path: &Path) -> Option<u32> {
+    read_pid(server_path, 0)
+}
+
+/// Returns the pid of the java process started by the MCT of `server_path` if it is still alive,
+/// it leads its own process group
+pub fn server_pid(server_path: &Path) -> Option<u32> {
+    read_pid(server_path, 1)
+}
+
+fn read_pid(server_path: &Path, line: usize) -> Option<u32> {
+    let pid = fs::read_to_string(server_path.join(PID_FILE))
+        .ok()?
+        .lines()
+        .nth(line)?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+    if is_alive(pid) {
+        Some(pid)
+    } else {
+        None
+    }
+}
+
+pub fn is_process_alive(pid: u32) -> bool {
+    is_alive(pid)
+}
+
+/// Whether a server runs in `server_path`, started by MCT or not : the pidfile,
+/// the lock the server keeps on its world or RCON answering
+pub async fn is_running(server_path: &Path) -> bool {
+    if running_pid(server_path).is_some() || is_world_locked(server_path) {
+        return true;
+    }
+    let Some(target) = crate::rcon::RconTarget::from_server_dir(server_path) else {
+        return false;
+    };
+    // A wrong password still means something answers on the RCON port
+    matches!(
+        tokio::time::timeout(RCON_PROBE_TIMEOUT, crate::rcon::RconClient::connect_target(&target)).await,
+        Ok(Ok(_)) | Ok(Err(crate::rcon::RconError::AuthenticationFailed))
+    )
+}
+
+fn world_lock(server_path: &Path) -> PathBuf {
+    let level_name = crate::server_properties::ServerProperties::load(server_path)
+        .get_or_default("level-name")
+        .unwrap_or(String::from("world"));
+    server_path.join(level_name).join(WORLD_LOCK)
+}
+
+#[cfg(unix)]
+fn is_world_locked(server_path: &Path) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let file = match fs::File::open(world_lock(server_path)) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    // Java locks with fcntl, F_GETLK reports the lock of another process
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    let checked = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) } == 0;
+    checked && lock.l_type != libc::F_UNLCK as libc::c_short
+}
+
+#[cfg(not(unix))]
+fn is_world_locked(server_path: &Path) -> bool {
+    use std::io::Read;
+    // Windows refuses to read a locked region
+    match fs::File::open(world_lock(server_path)) {
+        Ok(mut file) => file.read(&mut [0u8; 1]).is_err(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    // A handle can outlive its process, the exit code tells if it still runs
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0;
+        let alive = GetExitCodeProcess(handle, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        alive
+    }
+}
+
+/// Sends SIGTERM, or SIGKILL when `force` is set, to a process
+#[cfg(unix)]
+pub fn signal_process(pid: u32, force: bool) -> bool {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    unsafe { libc::kill(pid as libc::pid_t, signal) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn signal_process(_pid: u32, _force: bool) -> bool {
+    false
+}
+
+/// Sends SIGKILL to every process of the group led by `pgid`
+#[cfg(unix)]
+pub fn kill_process_group(pgid: u32) -> bool {
+    unsafe { libc::kill(-(pgid as libc::pid_t), libc::SIGKILL) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn kill_process_group(_pgid: u32) -> bool {
+    false
+}
+
+pub fn read_metadata(server_path: &Path) -> Option<Value> {
+    fs::read_to_string(server_path.join(METADATA_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+}
+
+/// Writes the fields of `data` in MCA.json, keys it does not know about are kept
+pub fn write_metadata(server_path: &Path, data: &impl serde::Serialize) -> std::io::Result<()> {
+    let mut metadata = read_metadata(server_path)
+        .filter(|m| m.is_object())
+        .unwrap_or(Value::Object(Default::default()));
+    if let Value::Object(fields) = serde_json::to_value(data)? {
+        metadata.as_object_mut().unwrap().extend(fields);
+    }
+    fs::write(
+        server_path.join(METADATA_FILE),
+        serde_json::to_string_pretty(&metadata).unwrap(),
+    )
+}
+
+/// Pidfiles held by this process, their lock lasts as long as the file stays open
+static PIDFILE_LOCKS: Mutex<Vec<(PathBuf, fs::File)>> = Mutex::new(Vec::new());
+
+/// Writes our pid in the server directory, fails if another MCT already runs this server.
+/// The pidfile stays locked until released, so two concurrent starts cannot both get it
+/// and the lock of a killed MCT goes away with it
+#[cfg(unix)]
+pub fn acquire_pidfile(server_path: &Path) -> Result<(), String> {
+    use std::io::Write;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    let path = server_path.join(PID_FILE);
+    let mut locks = PIDFILE_LOCKS.lock().unwrap();
+    if locks.iter().any(|(locked, _)| *locked == path) {
+        return Ok(());
+    }
+    let error = |e: std::io::Error| format!("Error while writting {} : {}", PID_FILE, e);
+    loop {
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(error)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            return Err(format!(
+                "Server '{}' is already running{}",
+                server_path.to_string_lossy(),
+                running_pid(server_path)
+                    .map(|pid| format!(" (pid {})", pid))
+                    .unwrap_or_default()
+            ));
+        }
+        // The previous owner may have removed the file between our open and our lock
+        let same_file = match (file.metadata(), fs::metadata(&path)) {
+            (Ok(locked), Ok(current)) => locked.ino() == current.ino() && locked.dev() == current.dev(),
+            _ => false,
+        };
+        if !same_file {
+            continue;
+        }
+        file.set_len(0).map_err(error)?;
+        file.write_all(std::process::id().to_string().as_bytes())
+            .map_err(error)?;
+        locks.push((path, file));
+        return Ok(());
+    }
+}
+
+#[cfg(not(unix))]
+pub fn acquire_pidfile(server_path: &Path) -> Result<(), String> {
+    let path = server_path.join(PID_FILE);
+    loop {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                use std::io::Write;
+                return file
+                    .write_all(std::process::id().to_string().as_bytes())
+                    .map_err(|e| format!("Error while writting {} : {}", PID_FILE, e));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                match running_pid(server_path) {
+                    Some(pid) if pid == std::process::id() => return Ok(()),
+                    Some(pid) => {
+                        return Err(format!(
+                            "Server '{}' is already running (pid {})",
+                            server_path.to_string_lossy(),
+                            pid
+                        ))
+                    }
+                    None => {
+                        let _ = fs::remove_file(&path);
+                    }
+                }
+            }
+            Err(e) => return Err(format!("Error while writting {} : {}", PID_FILE, e)),
+        }
+    }
+}
+
+/// Writes the pid of the running java process under ours in the pidfile, `None` once it exited
+pub fn record_server_pid(server_path: &Path, server_pid: Option<u32>) {
+    use std::io::{Seek, Write};
+
+    let path = server_path.join(PID_FILE);
+    let mut content = std::process::id().to_string();
+    if let Some(server_pid) = server_pid {
+        content.push_str(&format!("\n{}", server_pid));
+    }
+    let mut locks = PIDFILE_LOCKS.lock().unwrap();
+    let written = match locks.iter_mut().find(|(locked, _)| *locked == path) {
+        Some((_, file)) => file
+            .set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| file.write_all(content.as_bytes())),
+        None => fs::write(&path, content),
+    };
+    if let Err(e) = written {
+        println!("⚠️ Error while writting {} : {}", PID_FILE, e);
+    }
+}
+
+pub fn release_pidfile(server_path: &Path) {
+    let path = server_path.join(PID_FILE);
+    let mut locks = PIDFILE_LOCKS.lock().unwrap();
+    let held = locks.iter().position(|(locked, _)| *locked == path);
+    if held.is_some() || running_pid(server_path).is_none_or(|pid| pid == std::process::id()) {
+        // Removed before unlocking, a waiting start then sees a new file
+        let _ = fs::remove_file(&path);
+    }
+    if let Some(index) = held {
+        locks.remove(index);
+    }
+}
+
+/// Starts the server found in `server_path` using the platform recorded in MCA.json
+pub async fn start_existing_server(
+    server_path: PathBuf,
+    xmx: Option<String>,
+    xms: Option<String>,
+    is_gui: Option<bool>,
+    options: LaunchOptions,
+) {
+    let project = read_metadata(&server_path)
+        .and_then(|data| data["project"].as_str().map(|p| p.to_owned()));
+    match project.as_deref() {
+        Some("fabric") => {
+            let mut fabric_server = FabricMCRequest::build(Some(server_path.clone()));
+            if fabric_server.check_data(Some(server_path)).is_ok() {
+                fabric_server.start_server(xmx, xms, is_gui, options).await;
+            }
+        }
+        Some(_) => {
+            let mut paper_server = PaperMCRequest::build();
+            if paper_server.check_data(server_path).is_ok() {
+                paper_server.start_server(xmx, xms, is_gui, options).await;
+            }
+        }
+        None => println!(
+            "❌ No MCA.json found in '{}', create the server first",
+            server_path.to_string_lossy()
+        ),
+    }
+}
+
+/// Runs `MCT Server Start` again in the background with its console bound to a socket
+pub fn detach(server_path: &Path, forwarded_args: Vec<String>) {
+    if let Some(pid) = running_pid(server_path) {
+        println!("❌ Server is already running (pid {})", pid);
+        return;
+    }
+    let log = match fs::File::create(server_path.join(DAEMON_LOG)) {
+        Ok(log) => log,
+        Err(e) => {
+            println!("❌ Error while creating {} : {}", DAEMON_LOG, e);
+            return;
+        }
+    };
+    let executable = std::env::current_exe().expect("❌ Unable to find the MCT executable");
+    let mut command = std::process::Command::new(executable);
+    command
+        .args(["Server", "Start"])
+        .arg(server_path)
+        .arg("--daemon")
+        .args(forwarded_args)
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone().unwrap())
+        .stderr(log);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    match command.spawn() {
+        Ok(child) => {
+            println!("✅ Server started in the background (pid {})", child.id());
+            println!(
+                "➡️ Attach to its console with : MCT Server Attach \"{}\"",
+                server_path.to_string_lossy()
+            );
+        }
+        Err(e) => println!("❌ Error while detaching the server : {}", e),
+    }
+}
+
+/// Shares the server output with every attached client and keeps the last lines
+#[derive(Clone)]
+pub struct ConsoleHub {
+    output: broadcast::Sender<String>,
+    history: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl ConsoleHub {
+    pub fn build() -> Self {
+        let (output, _) = broadcast::channel(1024);
+        Self {
+            output,
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(CONSOLE_HISTORY))),
+        }
+    }
+
+    pub fn publish(&self, line: String) {
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.len() == CONSOLE_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(line.clone());
+        }
+        // Nobody attached is not an error
+        let _ = self.output.send(line);
+    }
+
+    /// Publishes every line read from a server output pipe
+    pub fn pump<R>(&self, reader: R)
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        use tokio::io::AsyncBufReadExt;
+        let hub = self.clone();
+        tokio::spawn(async move {
+            let mut lines = tokio::io::BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                hub.publish(line);
+            }
+        });
+    }
+
+    /// Accepts console clients on the socket of `server_path`, their input goes to `input`
+    #[cfg(unix)]
+    pub fn listen(&self, server_path: &Path, input: UnboundedSender<String>) -> std::io::Result<()> {
+        let socket_path = server_path.join(CONSOLE_SOCKET);
+        let _ = fs::remove_file(&socket_path);
+        let listener = tokio::net::UnixListener::bind(&socket_path)?;
+        let hub = self.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(hub.clone().serve_client(stream, input.clone()));
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen(&self, _server_path: &Path, _input: UnboundedSender<String>) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "detached servers are only supported on unix",
+        ))
+    }
+
+    #[cfg(unix)]
+    async fn serve_client(self, stream: tokio::net::UnixStream, input: UnboundedSender<String>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        let mut output = self.output.subscribe();
+
+        // The first line tells if the client wants the console history
+        if let Ok(Some(mode)) = lines.next_line().await {
+            if mode == "attach" {
+                let history: Vec<String> = self.history.lock().unwrap().iter().cloned().collect();
+                for line in history {
+                    if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        } else {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => {
+                        if input.send(line).is_err() {
+                            return;
+                        }
+                    }
+                    _ => return,
+                },
+                out = output.recv() => match out {
+                    Ok(line) => {
+                        if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            }
+        }
+    }
+
+    pub fn close(&self, server_path: &Path) {
+        let _ = fs::remove_file(server_path.join(CONSOLE_SOCKET));
+    }
+}
+
+/// Interactive console of a detached server, Ctrl-D (or Ctrl-C) detaches
+#[cfg(unix)]
+pub async fn attach(server_path: &Path) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    let stream = match connect(server_path).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    let (sender, mut terminal) = tokio::sync::mpsc::unbounded_channel();
+    crate::supervisor::spawn_console_reader(sender);
+
+    if writer.write_all(b"attach\n").await.is_err() {
+        return;
+    }
+    println!("✅ Attached, press Ctrl-D to detach");
+    loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => println!("{}", line),
+                _ => {
+                    println!("➡️ Server session ended");
+                    return;
+                }
+            },
+            line = terminal.recv() => match line {
+                Some(line) => {
+                    if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+                None => {
+                    println!("➡️ Detached");
+                    return;
+                }
+            },
+        }
+    }
+}
+
+/// Sends one command to a detached server and prints what it answered
+pub async fn send(server_path: &Path, command: &str) {
+    match console_command(server_path, command, None).await {
+        Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+        Err(e) => println!("❌ {}", e),
+    }
+}
+
+/// Runs a command on a running server through RCON when enabled, else through
+/// the console socket of a detached server
+pub async fn send_command(server_path: &Path, command: &str) -> Result<String, String> {
+    if let Some(target) = crate::rcon::RconTarget::from_server_dir(server_path) {
+        if let Ok(mut client) = crate::rcon::RconClient::connect_target(&target).await {
+            return client.command(command).await.map_err(|e| e.to_string());
+        }
+    }
+    console_command(server_path, command, None)
+        .await
+        .map(|lines| lines.join("\n"))
+        .map_err(|_| {
+            format!(
+                "Unable to reach the console of '{}', enable RCON or start the server detached",
+                server_path.to_string_lossy()
+            )
+        })
+}
+
+/// Runs a command and waits for a line containing `done` in its output. RCON
+/// answers once the command completed, the console has to be watched.
+pub async fn send_command_until(
+    server_path: &Path,
+    command: &str,
+    done: &str,
+    timeout: std::time::Duration,
+) -> Result<String, String> {
+    if let Some(target) = crate::rcon::RconTarget::from_server_dir(server_path) {
+        if let Ok(mut client) = crate::rcon::RconClient::connect_target(&target).await {
+            return client.command(command).await.map_err(|e| e.to_string());
+        }
+    }
+    console_command(server_path, command, Some((done, timeout)))
+        .await
+        .map(|lines| lines.join("\n"))
+}
+
+/// Writes a command to the console socket and collects the output for a moment,
+/// or until a line contains the expected text
+#[cfg(unix)]
+async fn console_command(
+    server_path: &Path,
+    command: &str,
+    until: Option<(&str, std::time::Duration)>,
+) -> Result<Vec<String>, String> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    let stream = connect(server_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    writer
+        .write_all(format!("send\n{}\n", command).as_bytes())
+        .await
+        .map_err(|e| format!("Error while sending the command : {}", e))?;
+    // The console has no request/response framing, keep what comes back for a moment
+    let mut output = vec![];
+    let window = until.map_or(std::time::Duration::from_millis(1500), |(_, timeout)| timeout);
+    let deadline = tokio::time::sleep(window);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => {
+                    let finished = until.is_some_and(|(done, _)| line.contains(done));
+                    output.push(line);
+                    if finished {
+                        return Ok(output);
+                    }
+                }
+                _ if until.is_some() => return Err(String::from("The console closed before the command completed")),
+                _ => return Ok(output),
+            },
+            _ = &mut deadline => match until {
+                Some((done, timeout)) => {
+                    return Err(format!("No '{}' from the server after {}s", done, timeout.as_secs()))
+                }
+                None => return Ok(output),
+            },
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn console_command(
+    _server_path: &Path,
+    _command: &str,
+    _until: Option<(&str, std::time::Duration)>,
+) -> Result<Vec<String>, String> {
+    Err(String::from("Detached servers are only supported on unix"))
+}
+
+#[cfg(unix)]
+async fn connect(server_path: &Path) -> Result<tokio::net::UnixStream, String> {
+    tokio::net::UnixStream::connect(server_path.join(CONSOLE_SOCKET))
+        .await
+        .map_err(|e| {
+            format!(
+                "No detached server console in '{}' : {}",
+                server_path.to_string_lossy(),
+                e
+            )
+        })
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_server_path: &Path) {
+    println!("❌ Detached servers are only supported on unix");
+}
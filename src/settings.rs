@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::registry;
+
+/// Global file, in the MCT config directory
+const GLOBAL_FILE: &str = "config.toml";
+/// Project file, in the working directory
+const PROJECT_FILE: &str = "mct.toml";
+const ENV_PREFIX: &str = "MCT_";
+/// Env vars read before the settings existed, the current name wins when both are set
+const LEGACY_ENV: &[(&str, &str)] = &[("MCT_MOJANG_API", "api.mojang")];
+
+#[derive(Debug, Clone, Copy)]
+pub enum SettingKind {
+    Text,
+    Integer,
+    Choice(&'static [&'static str]),
+}
+
+/// A known setting, its value is a string in every layer
+pub struct SettingDefinition {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    pub default: Option<&'static str>,
+}
+
+const fn setting(
+    key: &'static str,
+    kind: SettingKind,
+    default: Option<&'static str>,
+) -> SettingDefinition {
+    SettingDefinition { key, kind, default }
+}
+
+pub const SETTINGS: &[SettingDefinition] = &[
+    setting("search.limit", SettingKind::Integer, Some("10")),
+    setting(
+        "search.sorting",
+        SettingKind::Choice(&["relevance", "downloads", "follows", "newest", "updated"]),
+        Some("relevance"),
+    ),
+    setting("server.path", SettingKind::Text, Some("./MCT Server")),
+    setting("server.platform", SettingKind::Choice(&["paper", "fabric"]), Some("paper")),
+    setting("server.game_version", SettingKind::Text, None),
+    setting("server.max_ram", SettingKind::Text, None),
+    setting("server.min_ram", SettingKind::Text, None),
+    setting("server.jvm_profile", SettingKind::Choice(&["default", "aikar"]), Some("default")),
+    setting("download.loader", SettingKind::Text, None),
+    setting("download.game_version", SettingKind::Text, None),
+    setting("download.dir", SettingKind::Text, None),
+    setting("download.concurrency", SettingKind::Integer, Some("4")),
+    setting("tunnel.bore_server", SettingKind::Text, Some("bore.pub")),
+    setting("tunnel.bore_secret", SettingKind::Text, None),
+    setting("github.token", SettingKind::Text, None),
+    setting(
+        "maven.repositories",
+        SettingKind::Text,
+        Some("https://repo.maven.apache.org/maven2"),
+    ),
+    setting("maven.auth_repository", SettingKind::Text, None),
+    setting("maven.username", SettingKind::Text, None),
+    setting("maven.password", SettingKind::Text, None),
+    setting("curseforge.api_key", SettingKind::Text, None),
+    setting("api.modrinth", SettingKind::Text, Some("https://api.modrinth.com")),
+    setting("api.papermc", SettingKind::Text, Some("https://api.papermc.io")),
+    setting("api.fabric", SettingKind::Text, Some("https://meta.fabricmc.net")),
+    setting("api.hangar", SettingKind::Text, Some("https://hangar.papermc.io")),
+    setting("api.spiget", SettingKind::Text, Some("https://api.spiget.org/v2")),
+    setting("api.github", SettingKind::Text, Some("https://api.github.com")),
+    setting("api.curseforge", SettingKind::Text, Some("https://api.curseforge.com")),
+    setting("api.mojang", SettingKind::Text, Some("https://api.mojang.com")),
+];
+
+/// Layer an effective value comes from, CLI flags are applied by each command on top
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    Env(String),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Global(path) | Source::Project(path) => write!(f, "{}", path.to_string_lossy()),
+            Source::Env(name) => write!(f, "env {}", name),
+        }
+    }
+}
+
+/// Effective settings : defaults < global config.toml < project mct.toml < MCT_* env vars
+#[derive(Debug, Default)]
+pub struct Settings {
+    values: BTreeMap<&'static str, (String, Source)>,
+    warnings: Vec<String>,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        for definition in SETTINGS {
+            if let Some(default) = definition.default {
+                settings
+                    .values
+                    .insert(definition.key, (default.to_owned(), Source::Default));
+            }
+        }
+        match registry::config_dir() {
+            Ok(dir) => {
+                let global = dir.join(GLOBAL_FILE);
+                settings.load_file(&global, Source::Global(global.clone()));
+            }
+            Err(e) => settings.warnings.push(e),
+        }
+        let project = PathBuf::from(PROJECT_FILE);
+        settings.load_file(&project, Source::Project(project.clone()));
+        for (name, key) in LEGACY_ENV {
+            if let Ok(value) = std::env::var(name) {
+                settings.set(key, value, Source::Env(name.to_string()));
+            }
+        }
+        for definition in SETTINGS {
+            let name = env_name(definition.key);
+            if let Ok(value) = std::env::var(&name) {
+                settings.set(definition.key, value, Source::Env(name));
+            }
+        }
+        settings
+    }
+
+    fn load_file(&mut self, path: &Path, source: Source) {
+        let Ok(content) = fs::read_to_string(path) else {
+            return;
+        };
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                self.warnings
+                    .push(format!("Ignoring '{}' : {}", path.to_string_lossy(), e));
+                return;
+            }
+        };
+        let mut values = vec![];
+        flatten(None, &toml::Value::Table(table), &mut values);
+        for (key, value) in values {
+            self.set(&key, value, source.clone());
+        }
+    }
+
+    fn set(&mut self, key: &str, value: String, source: Source) {
+        let Some(definition) = definition(key) else {
+            self.warnings
+                .push(format!("Unknown setting '{}' in {}", key, source));
+            return;
+        };
+        if let Err(e) = validate(definition, &value) {
+            self.warnings.push(format!("{} in {}, ignored", e, source));
+            return;
+        }
+        self.values.insert(definition.key, (value, source));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&(String, Source)> {
+        self.values.get(key)
+    }
+}
+
+/// `server.max_ram` is read from `MCT_SERVER_MAX_RAM`
+pub fn env_name(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.replace('.', "_").to_uppercase())
+}
+
+fn definition(key: &str) -> Option<&'static SettingDefinition> {
+    SETTINGS.iter().find(|d| d.key == key)
+}
+
+fn validate(definition: &SettingDefinition, value: &str) -> Result<(), String> {
+    match definition.kind {
+        SettingKind::Text => Ok(()),
+        SettingKind::Integer => value
+            .parse::<usize>()
+            .map(|_| ())
+            .map_err(|_| format!("{} expects a number, got '{}'", definition.key, value)),
+        SettingKind::Choice(choices) => {
+            if choices.contains(&value.to_lowercase().as_str()) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} expects {}, got '{}'",
+                    definition.key,
+                    choices.join(" | "),
+                    value
+                ))
+            }
+        }
+    }
+}
+
+/// `[server] max_ram = "4g"` becomes `("server.max_ram", "4g")`
+fn flatten(prefix: Option<&str>, value: &toml::Value, values: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = match prefix {
+                    Some(prefix) => format!("{}.{}", prefix, key),
+                    None => key.clone(),
+                };
+                flatten(Some(&key), value, values);
+            }
+        }
+        toml::Value::String(s) => values.push((prefix.unwrap_or_default().to_owned(), s.clone())),
+        other => values.push((prefix.unwrap_or_default().to_owned(), other.to_string())),
+    }
+}
+
+/// Settings of this invocation, loaded once
+pub fn settings() -> &'static Settings {
+    static SETTINGS_CELL: OnceLock<Settings> = OnceLock::new();
+    SETTINGS_CELL.get_or_init(|| {
+        let settings = Settings::load();
+        for warning in &settings.warnings {
+            println!("⚠️ {}", warning);
+        }
+        settings
+    })
+}
+
+pub fn get(key: &str) -> Option<String> {
+    settings().get(key).map(|(value, _)| value.clone())
+}
+
+pub fn get_usize(key: &str) -> Option<usize> {
+    get(key).and_then(|value| value.parse().ok())
+}
+
+/// Base URL of an API without its trailing slash, ex : `api("modrinth")`
+pub fn api(name: &str) -> String {
+    get(&format!("api.{}", name))
+        .unwrap_or_default()
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+/// Values hidden by `mct config show`
+fn is_secret(key: &str) -> bool {
+    ["secret", "token", "password", "api_key"]
+        .iter()
+        .any(|suffix| key.ends_with(suffix))
+}
+
+/// `mct config show`
+pub fn print_effective() {
+    let settings = settings();
+    println!("{:<22} {:<28} SOURCE", "KEY", "VALUE");
+    for definition in SETTINGS {
+        match settings.get(definition.key) {
+            Some((_, source)) if is_secret(definition.key) => {
+                println!("{:<22} {:<28} {}", definition.key, "********", source)
+            }
+            Some((value, source)) => println!("{:<22} {:<28} {}", definition.key, value, source),
+            None => println!("{:<22} {:<28} unset", definition.key, "-"),
+        }
+    }
+    println!(
+        "➡️ Files : {} then ./{}, env vars : {}<SECTION>_<KEY>, command flags override everything",
+        registry::config_dir()
+            .map(|dir| dir.join(GLOBAL_FILE).to_string_lossy().into_owned())
+            .unwrap_or(String::from("no config directory")),
+        PROJECT_FILE,
+        ENV_PREFIX
+    );
+}
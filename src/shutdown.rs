@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Instant;
+
+use crate::session;
+
+/// Time given to the server to exit after `stop` before it is killed
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(60);
+/// Key of the schedule in MCA.json
+const SCHEDULE_KEY: &str = "restart_schedule";
+const DEFAULT_WARNINGS: [u64; 4] = [600, 300, 60, 10];
+/// Seconds left at which a countdown is broadcast
+const COUNTDOWN_MARKS: [u64; 11] = [600, 300, 120, 60, 30, 10, 5, 4, 3, 2, 1];
+
+/// Daily restart of a server, stored in its MCA.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartSchedule {
+    /// Local time of the restart, `HH:MM`
+    pub daily_at: String,
+    /// Seconds before the restart at which players are warned
+    pub warnings: Vec<u64>,
+}
+
+impl RestartSchedule {
+    pub fn parse(daily_at: &str, warnings: Option<&str>) -> Result<Self, String> {
+        NaiveTime::parse_from_str(daily_at, "%H:%M")
+            .map_err(|_| format!("Invalid time '{}', expected HH:MM", daily_at))?;
+        let mut warnings = match warnings {
+            Some(warnings) => warnings
+                .split(',')
+                .map(|w| w.trim().parse::<u64>())
+                .collect::<Result<Vec<u64>, _>>()
+                .map_err(|_| format!("Invalid warnings '{}', expected seconds ex : 600,60,10", warnings))?,
+            None => DEFAULT_WARNINGS.to_vec(),
+        };
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+        warnings.dedup();
+        Ok(Self {
+            daily_at: daily_at.to_owned(),
+            warnings,
+        })
+    }
+
+    pub fn load(server_path: &Path) -> Option<Self> {
+        session::read_metadata(server_path)
+            .and_then(|metadata| serde_json::from_value(metadata[SCHEDULE_KEY].clone()).ok())
+    }
+
+    /// Writes the schedule in MCA.json, `None` disables the restarts
+    pub fn save(server_path: &Path, schedule: Option<&Self>) -> std::io::Result<()> {
+        session::write_metadata(server_path, &json!({ SCHEDULE_KEY: schedule }))
+    }
+
+    /// Next occurrence of `daily_at` in the local time zone
+    pub fn next_restart(&self) -> Option<chrono::DateTime<Local>> {
+        let time = NaiveTime::parse_from_str(&self.daily_at, "%H:%M").ok()?;
+        let now = Local::now();
+        (0..=2).find_map(|days| {
+            let date = now.date_naive() + chrono::Days::new(days);
+            // `earliest` keeps the restart on days where the time is ambiguous (DST)
+            Local
+                .from_local_datetime(&date.and_time(time))
+                .earliest()
+                .filter(|restart| *restart > now)
+        })
+    }
+}
+
+/// What the supervisor has to do when the timer fires
+pub enum RestartEvent {
+    /// Seconds left before the restart
+    Warning(u64),
+    Restart,
+}
+
+/// Warnings then restart of one server run
+pub struct RestartTimer {
+    events: VecDeque<(Instant, Option<u64>)>,
+}
+
+impl RestartTimer {
+    pub fn build(schedule: Option<&RestartSchedule>) -> Self {
+        let mut events = VecDeque::new();
+        if let Some((schedule, restart)) = schedule.and_then(|s| Some((s, s.next_restart()?))) {
+            let delay = (restart - Local::now()).to_std().unwrap_or_default();
+            let restart_at = Instant::now() + delay;
+            for warning in &schedule.warnings {
+                if Duration::from_secs(*warning) < delay {
+                    events.push_back((restart_at - Duration::from_secs(*warning), Some(*warning)));
+                }
+            }
+            events.push_back((restart_at, None));
+        }
+        Self { events }
+    }
+
+    /// Waits for the next event, forever when there is no schedule
+    pub async fn next(&mut self) -> RestartEvent {
+        let Some((at, warning)) = self.events.front().cloned() else {
+            return std::future::pending().await;
+        };
+        tokio::time::sleep_until(at).await;
+        self.events.pop_front();
+        match warning {
+            Some(seconds) => RestartEvent::Warning(seconds),
+            None => RestartEvent::Restart,
+        }
+    }
+}
+
+/// `10 minutes`, `1 minute 30 seconds`, `5 seconds`
+pub fn format_delay(seconds: u64) -> String {
+    let plural = |n: u64, unit: &str| format!("{} {}{}", n, unit, if n > 1 { "s" } else { "" });
+    match (seconds / 60, seconds % 60) {
+        (0, s) => plural(s, "second"),
+        (m, 0) => plural(m, "minute"),
+        (m, s) => format!("{} {}", plural(m, "minute"), plural(s, "second")),
+    }
+}
+
+/// Broadcasts a countdown, saves and stops a running server, killing it after `timeout`
+pub async fn stop_server(server_path: &Path, countdown: u64, timeout: Duration) {
+    let Some(pid) = session::running_pid(server_path) else {
+        println!("❌ Server '{}' is not running", server_path.to_string_lossy());
+        return;
+    };
+
+    let mut remaining = countdown;
+    let mut marks: Vec<u64> = COUNTDOWN_MARKS
+        .iter()
+        .filter(|mark| **mark < countdown)
+        .cloned()
+        .collect();
+    if countdown > 0 {
+        marks.insert(0, countdown);
+    }
+    let mut console = true;
+    for mark in marks {
+        tokio::time::sleep(Duration::from_secs(remaining - mark)).await;
+        remaining = mark;
+        let message = format!("say Server stopping in {}", format_delay(mark));
+        if let Err(e) = session::send_command(server_path, &message).await {
+            println!("⚠️ {}", e);
+            console = false;
+            break;
+        }
+        println!("➡️ Server stopping in {}", format_delay(mark));
+    }
+    if console {
+        tokio::time::sleep(Duration::from_secs(remaining)).await;
+        console = session::send_command(server_path, "save-all").await.is_ok()
+            && session::send_command(server_path, "stop").await.is_ok();
+    }
+    if !console {
+        // The supervisor forwards SIGTERM as a `stop`
+        println!("➡️ No console available, asking MCT (pid {}) to stop the server", pid);
+        session::signal_process(pid, false);
+    }
+
+    if wait_for_exit(server_path, timeout).await {
+        println!("✅ Server stopped");
+        return;
+    }
+    println!("⚠️ Server still running after {}s, killing it", timeout.as_secs());
+    // Read first, the pidfile is gone once MCT exits
+    let server_pid = session::server_pid(server_path);
+    // The supervisor kills the server on its second SIGTERM
+    for _ in 0..2 {
+        session::signal_process(pid, false);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    if !wait_for_exit(server_path, Duration::from_secs(5)).await {
+        // java runs in its own process group, killing MCT alone would leave it running
+        if let Some(server_pid) = server_pid {
+            session::kill_process_group(server_pid);
+        }
+        session::signal_process(pid, true);
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let alive = || {
+        session::is_process_alive(pid) || server_pid.is_some_and(session::is_process_alive)
+    };
+    while alive() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    if alive() {
+        println!("❌ Unable to kill the server, it is still running");
+    } else {
+        println!("✅ Server killed");
+    }
+}
+
+async fn wait_for_exit(server_path: &Path, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if session::running_pid(server_path).is_none() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    session::running_pid(server_path).is_none()
+}
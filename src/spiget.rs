@@ -0,0 +1,164 @@
+use serde_json::{json, Value};
+
+use crate::plugins::{self, Release};
+use crate::settings;
+
+fn endpoint(path: &str) -> String {
+    format!("{}{}", settings::api("spiget"), path)
+}
+
+/// Spiget sort of a Modrinth sorting ex : `downloads` gives `-downloads`
+pub fn sort_of(sorting: &str) -> Option<&'static str> {
+    match sorting {
+        "downloads" => Some("-downloads"),
+        "follows" => Some("-likes"),
+        "newest" => Some("-releaseDate"),
+        "updated" => Some("-updateDate"),
+        _ => None,
+    }
+}
+
+/// Resources matching `query`, as hits with the fields of the Modrinth search
+pub async fn search(
+    query: &str,
+    limit: usize,
+    offset: usize,
+    sort: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let limit = limit.max(1);
+    let mut parameters = vec![
+        ("field", String::from("name")),
+        ("size", limit.to_string()),
+        // Spiget pages start at 1
+        ("page", (offset / limit + 1).to_string()),
+    ];
+    if let Some(sort) = sort {
+        parameters.push(("sort", sort.to_owned()));
+    }
+    let mut url = reqwest::Url::parse(&endpoint("/search/resources"))
+        .map_err(|e| format!("Invalid Spiget API address : {}", e))?;
+    url.path_segments_mut()
+        .map_err(|_| String::from("Invalid Spiget API address"))?
+        .push(query);
+    let data = plugins::get_json(url.as_str(), &parameters).await;
+    // Spiget answers 404 when nothing matches
+    let data = match data {
+        Err(e) if e.contains("404") => return Ok(Vec::new()),
+        data => data?,
+    };
+    Ok(data
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|resource| {
+            let mut description = resource["tag"].as_str().unwrap_or_default().to_owned();
+            if resource["premium"].as_bool() == Some(true) {
+                description.push_str(" (premium)");
+            } else if resource["external"].as_bool() == Some(true) {
+                description.push_str(" (external download)");
+            }
+            json!({
+                "project_id": resource["id"].to_string(),
+                "title": resource["name"],
+                "project_type": "plugin",
+                "versions": resource["testedVersions"],
+                "description": description,
+            })
+        })
+        .collect())
+}
+
+/// Name of the downloaded file, resource names are free text ex : `EssentialsX | Chat`
+fn file_name(id: &str, name: &str, file_type: &str) -> String {
+    let name: String = name
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' '))
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace(' ', "-");
+    let extension = file_type.strip_prefix('.').unwrap_or("jar");
+    if name.is_empty() {
+        format!("spigot-{}.{}", id, extension)
+    } else {
+        format!("{}.{}", name, extension)
+    }
+}
+
+/// Given version of a resource, or its latest one, when Spiget can download it
+pub async fn release(
+    id: &str,
+    version: Option<&str>,
+    game_version: Option<&str>,
+) -> Result<Release, String> {
+    if id.parse::<u64>().is_err() {
+        return Err(format!(
+            "'{}' is not a Spigot resource id, ex : 9089 for spigotmc.org/resources/9089",
+            id
+        ));
+    }
+    let resource = plugins::get_json(&endpoint(&format!("/resources/{}", id)), &[]).await?;
+    let name = resource["name"].as_str().unwrap_or(id).to_owned();
+    if resource["premium"].as_bool() == Some(true) {
+        return Err(format!(
+            "{} is a premium resource, buy and download it from https://www.spigotmc.org/resources/{}",
+            name, id
+        ));
+    }
+    if resource["external"].as_bool() == Some(true) || resource["file"]["type"] == "external" {
+        return Err(format!(
+            "{} is only available from {}, download it manually",
+            name,
+            resource["file"]["externalUrl"]
+                .as_str()
+                .unwrap_or("an external site")
+        ));
+    }
+    if let (Some(game_version), Some(tested)) =
+        (game_version, resource["testedVersions"].as_array())
+    {
+        let tested: Vec<&str> = tested.iter().filter_map(|v| v.as_str()).collect();
+        if !tested.is_empty() && !tested.iter().any(|v| game_version.starts_with(v)) {
+            println!(
+                "⚠️ {} is tested on {}, not on {}",
+                name,
+                tested.join(", "),
+                game_version
+            );
+        }
+    }
+
+    let (version, url) = match version {
+        Some(version) => (
+            plugins::get_json(
+                &endpoint(&format!("/resources/{}/versions/{}", id, version)),
+                &[],
+            )
+            .await?,
+            endpoint(&format!("/resources/{}/versions/{}/download", id, version)),
+        ),
+        None => (
+            plugins::get_json(
+                &endpoint(&format!("/resources/{}/versions/latest", id)),
+                &[],
+            )
+            .await?,
+            endpoint(&format!("/resources/{}/download", id)),
+        ),
+    };
+    Ok(Release {
+        project: id.to_owned(),
+        file_name: file_name(
+            id,
+            &name,
+            resource["file"]["type"].as_str().unwrap_or(".jar"),
+        ),
+        name,
+        version: version["name"].as_str().unwrap_or_default().to_owned(),
+        url,
+        // Spiget knows neither hashes nor dependencies
+        hash: None,
+        dependencies: Vec::new(),
+        credentials: None,
+    })
+}
@@ -0,0 +1,487 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Notify;
+
+use crate::lan::LanAnnouncement;
+use crate::server_properties::ServerProperties;
+use crate::server_status::DEFAULT_SERVER_PORT;
+use crate::session::{self, ConsoleHub};
+use crate::shutdown::{self, RestartEvent, RestartSchedule, RestartTimer, DEFAULT_STOP_TIMEOUT};
+use crate::tunnel::TunnelProvider;
+
+/// File (in the server directory) where every server exit is recorded
+const EXIT_HISTORY_FILE: &str = "MCA_exits.json";
+/// Only the most recent exits are kept in the history file
+const EXIT_HISTORY_LIMIT: usize = 50;
+/// G1 tuning recommended for Paper servers, see https://docs.papermc.io/paper/aikars-flags
+const AIKAR_FLAGS: [&str; 20] = [
+    "-XX:+UseG1GC",
+    "-XX:+ParallelRefProcEnabled",
+    "-XX:MaxGCPauseMillis=200",
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+DisableExplicitGC",
+    "-XX:+AlwaysPreTouch",
+    "-XX:G1NewSizePercent=30",
+    "-XX:G1MaxNewSizePercent=40",
+    "-XX:G1HeapRegionSize=8M",
+    "-XX:G1ReservePercent=20",
+    "-XX:G1HeapWastePercent=5",
+    "-XX:G1MixedGCCountTarget=4",
+    "-XX:InitiatingHeapOccupancyPercent=15",
+    "-XX:G1MixedGCLiveThresholdPercent=90",
+    "-XX:G1RSetUpdatingPauseTimePercent=5",
+    "-XX:SurvivorRatio=32",
+    "-XX:+PerfDisableSharedMem",
+    "-XX:MaxTenuringThreshold=1",
+    "-Dusing.aikars.flags=https://mcflags.emc.gs",
+    "-Daikars.new.flags=true",
+];
+
+/// Describes when a crashed server should be restarted
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Number of crashes allowed inside `crash_window` before giving up
+    pub max_crashes: usize,
+    pub crash_window: Duration,
+    /// First delay before a restart, doubled after each consecutive crash
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_crashes: 5,
+            crash_window: Duration::from_secs(600),
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn new(max_crashes: Option<usize>, crash_window: Option<u64>) -> Self {
+        let mut policy = Self::default();
+        if let Some(max_crashes) = max_crashes {
+            policy.max_crashes = max_crashes;
+        }
+        if let Some(crash_window) = crash_window {
+            policy.crash_window = Duration::from_secs(crash_window);
+        }
+        policy
+    }
+}
+
+/// Where the server console is read from and written to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConsoleMode {
+    /// The console of the terminal running MCT
+    #[default]
+    Inherit,
+    /// A unix socket in the server directory, used by detached servers
+    Socket,
+}
+
+/// How `start_server` runs the server process
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub restart_policy: Option<RestartPolicy>,
+    pub console: ConsoleMode,
+    /// Given to java before the server arguments
+    pub jvm_args: Vec<String>,
+    /// Given to the server after its jar, ex : `--port 25566`
+    pub server_args: Vec<String>,
+    /// Exposes the server port publicly while the server runs
+    pub tunnel: Option<Arc<dyn TunnelProvider>>,
+    /// Announces the server in the LAN Worlds list of the local network
+    pub lan: bool,
+    /// Stops the server like a Ctrl-C when notified, for callers running it in-process
+    pub stop_request: Option<Arc<Notify>>,
+}
+
+/// JVM flags of a profile : `default` (none) or `aikar`
+pub fn jvm_profile_args(profile: &str) -> Vec<String> {
+    match profile.to_lowercase().as_str() {
+        "aikar" => AIKAR_FLAGS.iter().map(|flag| flag.to_string()).collect(),
+        _ => vec![],
+    }
+}
+
+/// One server exit, as stored in `MCA_exits.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitRecord {
+    /// Unix timestamp (seconds) of the exit
+    pub timestamp: u64,
+    /// Process exit code, `None` if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub uptime_secs: u64,
+    pub crashed: bool,
+}
+
+/// Keeps a server process in the foreground, forwards the console and
+/// restarts it on crashes according to its `RestartPolicy`
+pub struct Supervisor {
+    server_path: PathBuf,
+    java_args: Vec<String>,
+    options: LaunchOptions,
+    crash_times: Vec<Instant>,
+    hub: Option<ConsoleHub>,
+}
+
+impl Supervisor {
+    pub fn build(server_path: PathBuf, java_args: Vec<String>) -> Self {
+        Self {
+            server_path,
+            java_args,
+            options: LaunchOptions::default(),
+            crash_times: vec![],
+            hub: None,
+        }
+    }
+
+    /// Without a restart policy the server is only run once
+    pub fn with_options(mut self, options: LaunchOptions) -> Self {
+        self.java_args.splice(0..0, options.jvm_args.iter().cloned());
+        self.java_args.extend(options.server_args.iter().cloned());
+        self.options = options;
+        self
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        let mut command = tokio::process::Command::new("java");
+        command
+            .args(&self.java_args)
+            .current_dir(&self.server_path)
+            .stdin(Stdio::piped());
+        if self.hub.is_some() {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        } else {
+            command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        }
+        // Keep the server out of our process group so a Ctrl-C in the terminal
+        // reaches MCT only and is forwarded as a graceful `stop`
+        #[cfg(unix)]
+        command.process_group(0);
+        let mut child = command.spawn()?;
+        if let Some(hub) = &self.hub {
+            if let Some(stdout) = child.stdout.take() {
+                hub.pump(stdout);
+            }
+            if let Some(stderr) = child.stderr.take() {
+                hub.pump(stderr);
+            }
+        }
+        Ok(child)
+    }
+
+    /// Runs the server until it stops cleanly, the user asks for a shutdown
+    /// or the crash loop limit of the policy is reached
+    pub async fn run(&mut self) {
+        if let Err(e) = session::acquire_pidfile(&self.server_path) {
+            println!("❌ {}", e);
+            return;
+        }
+        let (sender, mut console) = mpsc::unbounded_channel();
+        match self.options.console {
+            ConsoleMode::Inherit => spawn_console_reader(sender),
+            ConsoleMode::Socket => {
+                let hub = ConsoleHub::build();
+                if let Err(e) = hub.listen(&self.server_path, sender) {
+                    println!("❌ Error while opening the console socket : {}", e);
+                    session::release_pidfile(&self.server_path);
+                    return;
+                }
+                self.hub = Some(hub);
+            }
+        }
+
+        let port = ServerProperties::load(&self.server_path)
+            .get_u16("server-port")
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        // The tunnel is kept across restarts so the public address does not change
+        let tunnel = self.options.tunnel.clone().and_then(|provider| {
+            provider
+                .open(port)
+                .map_err(|e| println!("❌ {}", e))
+                .ok()
+        });
+        let lan = if self.options.lan {
+            LanAnnouncement::for_server(&self.server_path, port)
+                .await
+                .map_err(|e| println!("❌ {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        self.supervise(&mut console).await;
+
+        if let Some(lan) = lan {
+            lan.close().await;
+        }
+        if let Some(tunnel) = tunnel {
+            tunnel.close().await;
+        }
+        if let Some(hub) = self.hub.take() {
+            hub.close(&self.server_path);
+        }
+        session::release_pidfile(&self.server_path);
+    }
+
+    async fn supervise(&mut self, console: &mut mpsc::UnboundedReceiver<String>) {
+        let mut signals = ShutdownSignals::new();
+        let stop_request = self.options.stop_request.clone();
+
+        loop {
+            println!("🚀 Starting server : java {}", self.java_args.join(" "));
+            let started = Instant::now();
+            let mut child = match self.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    println!("➡️ Command: java {:?}", self.java_args);
+                    println!("    ❌➡️{}", e);
+                    return;
+                }
+            };
+            session::record_server_pid(&self.server_path, child.id());
+            let mut stdin = child.stdin.take();
+            let mut stopping = false;
+            let mut restarting = false;
+            // Read again on every start so a new schedule applies after the next restart
+            let mut restart_timer = RestartTimer::build(RestartSchedule::load(&self.server_path).as_ref());
+            let kill_deadline = tokio::time::sleep(Duration::MAX);
+            tokio::pin!(kill_deadline);
+            // The deadline stays ready once passed, the server is only killed once
+            let mut killed = false;
+
+            let status = loop {
+                tokio::select! {
+                    status = child.wait() => break status,
+                    Some(line) = console.recv() => {
+                        // A stop typed by an operator is a clean shutdown, not a crash
+                        if is_stop_command(&line) {
+                            stopping = true;
+                            restarting = false;
+                        }
+                        send_line(&mut stdin, &line).await;
+                    }
+                    _ = shutdown_requested(&mut signals, stop_request.as_deref()) => {
+                        if stopping {
+                            println!("⚠️ Second shutdown request, killing the server");
+                            let _ = child.start_kill();
+                        } else {
+                            println!("➡️ Shutdown requested, sending stop to the server");
+                            stopping = true;
+                            restarting = false;
+                            send_line(&mut stdin, "save-all").await;
+                            send_line(&mut stdin, "stop").await;
+                            kill_deadline.as_mut().reset(tokio::time::Instant::now() + DEFAULT_STOP_TIMEOUT);
+                        }
+                    }
+                    event = restart_timer.next(), if !stopping => match event {
+                        RestartEvent::Warning(seconds) => {
+                            let message = format!("Server restarting in {}", shutdown::format_delay(seconds));
+                            println!("➡️ {}", message);
+                            send_line(&mut stdin, &format!("say {}", message)).await;
+                        }
+                        RestartEvent::Restart => {
+                            println!("🔄 Scheduled restart, sending stop to the server");
+                            stopping = true;
+                            restarting = true;
+                            send_line(&mut stdin, "save-all").await;
+                            send_line(&mut stdin, "stop").await;
+                            kill_deadline.as_mut().reset(tokio::time::Instant::now() + DEFAULT_STOP_TIMEOUT);
+                        }
+                    },
+                    _ = &mut kill_deadline, if !killed => {
+                        println!("⚠️ Server still running after {}s, killing it", DEFAULT_STOP_TIMEOUT.as_secs());
+                        killed = true;
+                        let _ = child.start_kill();
+                    }
+                }
+            };
+            session::record_server_pid(&self.server_path, None);
+
+            let status = match status {
+                Ok(status) => status,
+                Err(e) => {
+                    println!("❌ Error while waiting for the server : {}", e);
+                    return;
+                }
+            };
+            let crashed = !stopping && !status.success();
+            self.record_exit(&status, started.elapsed(), crashed);
+
+            if restarting {
+                continue;
+            }
+            if !crashed {
+                println!("✅ Server stopped");
+                return;
+            }
+            let Some(policy) = self.options.restart_policy.clone() else {
+                println!("❌ Server exited with {}", status);
+                return;
+            };
+
+            let now = Instant::now();
+            self.crash_times.push(now);
+            self.crash_times
+                .retain(|t| now.duration_since(*t) <= policy.crash_window);
+            if self.crash_times.len() >= policy.max_crashes {
+                println!(
+                    "❌ Server crashed {} times in the last {}s, giving up",
+                    self.crash_times.len(),
+                    policy.crash_window.as_secs()
+                );
+                return;
+            }
+
+            let backoff = backoff_delay(&policy, self.crash_times.len());
+            println!(
+                "⚠️ Server crashed ({}), restarting in {}s ({}/{} crashes)",
+                status,
+                backoff.as_secs(),
+                self.crash_times.len(),
+                policy.max_crashes
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = signals.recv() => {
+                    println!("➡️ Shutdown requested, not restarting");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn record_exit(&self, status: &ExitStatus, uptime: Duration, crashed: bool) {
+        let path = self.server_path.join(EXIT_HISTORY_FILE);
+        let mut history: Vec<ExitRecord> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        history.push(ExitRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            exit_code: status.code(),
+            uptime_secs: uptime.as_secs(),
+            crashed,
+        });
+        if history.len() > EXIT_HISTORY_LIMIT {
+            history.drain(..history.len() - EXIT_HISTORY_LIMIT);
+        }
+        if let Err(e) = fs::write(&path, serde_json::to_string_pretty(&history).unwrap()) {
+            println!("❌ Error while writting {} : {}", EXIT_HISTORY_FILE, e);
+        }
+    }
+}
+
+/// Exponential backoff : initial_backoff * 2^(crashes - 1), capped at max_backoff
+fn backoff_delay(policy: &RestartPolicy, crashes: usize) -> Duration {
+    let exponent = crashes.saturating_sub(1).min(16) as u32;
+    policy
+        .initial_backoff
+        .saturating_mul(2u32.pow(exponent))
+        .min(policy.max_backoff)
+}
+
+async fn send_line(stdin: &mut Option<ChildStdin>, line: &str) {
+    if let Some(pipe) = stdin {
+        let line = format!("{}\n", line.trim_end());
+        if pipe.write_all(line.as_bytes()).await.is_err() || pipe.flush().await.is_err() {
+            *stdin = None;
+        }
+    }
+}
+
+fn is_stop_command(line: &str) -> bool {
+    let line = line.trim();
+    line.strip_prefix('/').unwrap_or(line).eq_ignore_ascii_case("stop")
+}
+
+/// Reads the terminal on a dedicated thread so console commands survive restarts
+pub fn spawn_console_reader(sender: UnboundedSender<String>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            match line {
+                Ok(line) => {
+                    if sender.send(line).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => return,
+            }
+        }
+    });
+}
+
+/// A Ctrl-C, a SIGTERM or a notified stop request
+async fn shutdown_requested(signals: &mut ShutdownSignals, stop_request: Option<&Notify>) {
+    match stop_request {
+        Some(stop_request) => tokio::select! {
+            _ = signals.recv() => {}
+            _ = stop_request.notified() => {}
+        },
+        None => signals.recv().await,
+    }
+}
+
+/// SIGINT / SIGTERM (Ctrl-C only on other platforms)
+pub struct ShutdownSignals {
+    #[cfg(unix)]
+    interrupt: Option<tokio::signal::unix::Signal>,
+    #[cfg(unix)]
+    terminate: Option<tokio::signal::unix::Signal>,
+}
+
+impl ShutdownSignals {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Self {
+                interrupt: signal(SignalKind::interrupt()).ok(),
+                terminate: signal(SignalKind::terminate()).ok(),
+            }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        {
+            let interrupt = async {
+                match self.interrupt.as_mut() {
+                    Some(s) => s.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let terminate = async {
+                match self.terminate.as_mut() {
+                    Some(s) => s.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = interrupt => {}
+                _ = terminate => {}
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use regex::Regex;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::bore::BoreClient;
+use crate::settings;
+use crate::upnp::PortForwarding;
+
+pub const DEFAULT_SSH_HOST: &str = "serveo.net";
+pub const PROVIDERS: [&str; 4] = ["ssh", "bore", "upnp", "custom"];
+/// `host:port` printed by a custom command, ex : `tcp://0.tcp.eu.ngrok.io:12345`
+const DEFAULT_ADDRESS_PATTERN: &str = r"(?:tcp://)?([A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+:\d{2,5})\b";
+/// Colors and cursor moves of the ssh banners
+const ANSI_PATTERN: &str = r"\x1b\[[0-9;]*[A-Za-z]";
+
+/// A service exposing the local server port on a public address
+pub trait TunnelProvider: Debug + Send + Sync {
+    /// Starts forwarding `local_port`, the public address is printed once known
+    fn open(self: Arc<Self>, local_port: u16) -> Result<Tunnel, String>;
+}
+
+/// A tunnel run by an external program
+pub trait CommandTunnel: Debug + Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    /// Program and arguments forwarding `local_port`
+    fn command(&self, local_port: u16) -> (String, Vec<String>);
+
+    /// Public address announced in a line printed by the command
+    fn parse_address(&self, line: &str) -> Option<String>;
+}
+
+/// `ssh -R 0:localhost:<port> <host>`, serveo.net style services
+#[derive(Debug)]
+pub struct SshReverse {
+    pub host: String,
+    forwarding: Regex,
+    allocated: Regex,
+}
+
+impl SshReverse {
+    pub fn build(host: Option<String>) -> Self {
+        Self {
+            host: host.unwrap_or(DEFAULT_SSH_HOST.to_owned()),
+            // Printed by serveo
+            forwarding: Regex::new(r"Forwarding TCP connections from (?:tcp://)?(\S+:\d+)").unwrap(),
+            // Printed by OpenSSH when the remote port is 0
+            allocated: Regex::new(r"Allocated port (\d+) for remote forward").unwrap(),
+        }
+    }
+}
+
+impl CommandTunnel for SshReverse {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn command(&self, local_port: u16) -> (String, Vec<String>) {
+        let args = [
+            "-T",
+            "-o",
+            "ServerAliveInterval=30",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "StrictHostKeyChecking=accept-new",
+            "-R",
+            &format!("0:localhost:{}", local_port),
+            &self.host,
+        ];
+        (String::from("ssh"), args.iter().map(|a| a.to_string()).collect())
+    }
+
+    fn parse_address(&self, line: &str) -> Option<String> {
+        if let Some(captures) = self.forwarding.captures(line) {
+            return Some(captures[1].to_owned());
+        }
+        let port = &self.allocated.captures(line)?[1];
+        let host = self.host.rsplit('@').next().unwrap_or(&self.host);
+        Some(format!("{}:{}", host, port))
+    }
+}
+
+/// Any command, `{port}` is replaced by the server port
+#[derive(Debug)]
+pub struct CustomCommand {
+    pub command: String,
+    /// The first group is the public address
+    address: Regex,
+}
+
+impl CustomCommand {
+    pub fn build(command: String, pattern: Option<&str>) -> Result<Self, String> {
+        let address = Regex::new(pattern.unwrap_or(DEFAULT_ADDRESS_PATTERN))
+            .map_err(|e| format!("Invalid tunnel pattern : {}", e))?;
+        if address.captures_len() < 2 {
+            return Err(String::from("The tunnel pattern needs a group around the address"));
+        }
+        Ok(Self { command, address })
+    }
+}
+
+impl CommandTunnel for CustomCommand {
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    fn command(&self, local_port: u16) -> (String, Vec<String>) {
+        let command = self.command.replace("{port}", &local_port.to_string());
+        if cfg!(windows) {
+            (String::from("cmd"), vec![String::from("/C"), command])
+        } else {
+            (String::from("sh"), vec![String::from("-c"), command])
+        }
+    }
+
+    fn parse_address(&self, line: &str) -> Option<String> {
+        Some(self.address.captures(line)?[1].to_owned())
+    }
+}
+
+impl<T: CommandTunnel> TunnelProvider for T {
+    fn open(self: Arc<Self>, local_port: u16) -> Result<Tunnel, String> {
+        open_command(self, local_port)
+    }
+}
+
+/// Provider named on the command line, the bore server and secret default to the settings
+pub fn provider(
+    name: &str,
+    host: Option<String>,
+    secret: Option<String>,
+    command: Option<String>,
+    pattern: Option<&str>,
+) -> Result<Arc<dyn TunnelProvider>, String> {
+    match name.to_lowercase().as_str() {
+        "ssh" | "serveo" => Ok(Arc::new(SshReverse::build(host))),
+        "bore" => Ok(Arc::new(BoreClient::build(
+            &host.or(settings::get("tunnel.bore_server")).unwrap_or_default(),
+            secret.or(settings::get("tunnel.bore_secret")),
+        )?)),
+        "upnp" | "nat-pmp" => Ok(Arc::new(PortForwarding::build(host))),
+        "custom" => match command {
+            Some(command) => Ok(Arc::new(CustomCommand::build(command, pattern)?)),
+            None => Err(String::from("The custom tunnel needs --tunnel_command")),
+        },
+        other => Err(format!(
+            "Unknown tunnel '{}', expected {}",
+            other,
+            PROVIDERS.join(" | ")
+        )),
+    }
+}
+
+/// A running tunnel
+pub struct Tunnel {
+    name: &'static str,
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl Tunnel {
+    /// Runs `tunnel` in the background, it receives the stop request of `close`
+    pub fn spawn<F, Fut>(name: &'static str, tunnel: F) -> Self
+    where
+        F: FnOnce(oneshot::Receiver<()>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (stop, stopped) = oneshot::channel();
+        Self {
+            name,
+            stop: Some(stop),
+            task: tokio::spawn(tunnel(stopped)),
+        }
+    }
+
+    /// Stops the tunnel and waits for it
+    pub async fn close(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.task).await;
+        println!("➡️ Tunnel ({}) closed", self.name);
+    }
+}
+
+fn open_command<T: CommandTunnel>(provider: Arc<T>, local_port: u16) -> Result<Tunnel, String> {
+    let name = provider.name();
+    let (program, args) = provider.command(local_port);
+    let mut command = tokio::process::Command::new(&program);
+    command
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Like the server, the tunnel only stops when MCT tells it to
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Unable to start the {} tunnel ({}) : {}", name, program, e))?;
+    println!("🚀 Starting {} tunnel for port {}", name, local_port);
+
+    let (lines, mut output) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        pump(stdout, lines.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        pump(stderr, lines);
+    }
+    Ok(Tunnel::spawn(name, |mut stopped| async move {
+        // Some services end the session when its input is closed
+        let _stdin = child.stdin.take();
+        let ansi = Regex::new(ANSI_PATTERN).unwrap();
+        let mut announced = false;
+        loop {
+            tokio::select! {
+                status = child.wait() => {
+                    match status {
+                        Ok(status) => println!("⚠️ Tunnel ({}) exited with {}", name, status),
+                        Err(e) => println!("⚠️ Tunnel ({}) exited : {}", name, e),
+                    }
+                    return;
+                }
+                Some(line) = output.recv() => {
+                    let line = ansi.replace_all(&line, "");
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match provider.parse_address(&line) {
+                        Some(address) if !announced => {
+                            announced = true;
+                            println!("🌐 Public address : {}", address);
+                        }
+                        _ => println!("[{}] {}", name, line),
+                    }
+                }
+                _ = &mut stopped => {
+                    // `sh -c` and wrapper scripts leave children, stop the whole group
+                    #[cfg(unix)]
+                    if let Some(pid) = child.id() {
+                        unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGTERM) };
+                    }
+                    let _ = child.kill().await;
+                    return;
+                }
+            }
+        }
+    }))
+}
+
+fn pump<R>(reader: R, lines: mpsc::UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = reader.next_line().await {
+            if lines.send(line).is_err() {
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,398 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+use tokio::net::UdpSocket;
+
+use crate::tunnel::{Tunnel, TunnelProvider};
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+/// WANIPConnection first, WANPPPConnection for DSL gateways
+const WAN_SERVICES: [&str; 2] = ["WANIPConnection", "WANPPPConnection"];
+const NAT_PMP_PORT: u16 = 5351;
+/// Lease asked for, the mapping is renewed at half of the granted one
+const LEASE_SECONDS: u32 = 3600;
+const MAPPING_DESCRIPTION: &str = "MCT Minecraft server";
+
+/// A gateway able to forward a port
+#[derive(Debug, Clone)]
+enum Gateway {
+    /// UPnP Internet Gateway Device
+    Igd {
+        control_url: String,
+        service_type: String,
+        local_ip: IpAddr,
+    },
+    NatPmp { address: Ipv4Addr },
+}
+
+impl Gateway {
+    /// `target` is the description URL of an IGD or the address of a NAT-PMP gateway,
+    /// SSDP then NAT-PMP on the default gateway when not given
+    async fn discover(target: Option<&str>) -> Result<Self, String> {
+        match target {
+            Some(url) if url.starts_with("http") => Self::igd(url).await,
+            Some(address) => address
+                .parse()
+                .map(|address| Gateway::NatPmp { address })
+                .map_err(|_| format!("Invalid gateway '{}', expected an IGD description URL or an IPv4 address", address)),
+            None => {
+                let ssdp_error = match ssdp_search().await {
+                    Ok(location) => match Self::igd(&location).await {
+                        Ok(gateway) => return Ok(gateway),
+                        Err(e) => e,
+                    },
+                    Err(e) => e,
+                };
+                match default_gateway() {
+                    Some(address) => {
+                        println!("➡️ {}, trying NAT-PMP on {}", ssdp_error, address);
+                        Ok(Gateway::NatPmp { address })
+                    }
+                    None => Err(ssdp_error),
+                }
+            }
+        }
+    }
+
+    async fn igd(location: &str) -> Result<Self, String> {
+        let description = reqwest::get(location)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Unable to read the gateway description {} : {}", location, e))?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+        let service = Regex::new(r"(?s)<service>(.*?)</service>").unwrap();
+        let (service_type, control_url) = WAN_SERVICES
+            .iter()
+            .find_map(|wan| {
+                service.captures_iter(&description).find_map(|block| {
+                    let service_type = xml_value(&block[1], "serviceType")?;
+                    let control_url = xml_value(&block[1], "controlURL")?;
+                    service_type.contains(wan).then_some((service_type, control_url))
+                })
+            })
+            .ok_or(format!("{} is not an Internet Gateway Device", location))?;
+        let base = xml_value(&description, "URLBase")
+            .filter(|base| !base.is_empty())
+            .unwrap_or(location.to_owned());
+        let control_url = reqwest::Url::parse(&base)
+            .and_then(|base| base.join(&control_url))
+            .map_err(|e| format!("Invalid control URL '{}' : {}", control_url, e))?;
+        let local_ip = local_ip_towards(
+            control_url.host_str().unwrap_or_default(),
+            control_url.port_or_known_default().unwrap_or(80),
+        )?;
+        Ok(Gateway::Igd {
+            control_url: control_url.to_string(),
+            service_type,
+            local_ip,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Gateway::Igd { .. } => "UPnP",
+            Gateway::NatPmp { .. } => "NAT-PMP",
+        }
+    }
+
+    async fn external_ip(&self) -> Result<IpAddr, String> {
+        match self {
+            Gateway::Igd { .. } => {
+                let response = self.soap("GetExternalIPAddress", &[]).await?;
+                xml_value(&response, "NewExternalIPAddress")
+                    .and_then(|ip| ip.parse().ok())
+                    .ok_or(String::from("The gateway did not give its external IP"))
+            }
+            Gateway::NatPmp { address } => {
+                let response = nat_pmp_request(*address, &[0, 0], 12).await?;
+                Ok(IpAddr::V4(Ipv4Addr::new(response[8], response[9], response[10], response[11])))
+            }
+        }
+    }
+
+    /// Forwards the TCP `port` to this machine, returns the external port and the granted lease (0 is permanent)
+    async fn map(&self, port: u16) -> Result<(u16, u32), String> {
+        match self {
+            Gateway::Igd { local_ip, .. } => {
+                let port_text = port.to_string();
+                let local_ip = local_ip.to_string();
+                let mut arguments = vec![
+                    ("NewRemoteHost", ""),
+                    ("NewExternalPort", port_text.as_str()),
+                    ("NewProtocol", "TCP"),
+                    ("NewInternalPort", port_text.as_str()),
+                    ("NewInternalClient", local_ip.as_str()),
+                    ("NewEnabled", "1"),
+                    ("NewPortMappingDescription", MAPPING_DESCRIPTION),
+                ];
+                let lease = LEASE_SECONDS.to_string();
+                arguments.push(("NewLeaseDuration", lease.as_str()));
+                match self.soap("AddPortMapping", &arguments).await {
+                    Ok(_) => Ok((port, LEASE_SECONDS)),
+                    // 725 OnlyPermanentLeasesSupported
+                    Err(e) if e.contains("725") => {
+                        arguments.pop();
+                        arguments.push(("NewLeaseDuration", "0"));
+                        self.soap("AddPortMapping", &arguments).await.map(|_| (port, 0))
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Gateway::NatPmp { address } => {
+                let response = nat_pmp_request(*address, &mapping_request(port, LEASE_SECONDS), 16).await?;
+                Ok((
+                    u16::from_be_bytes([response[10], response[11]]),
+                    u32::from_be_bytes([response[12], response[13], response[14], response[15]]),
+                ))
+            }
+        }
+    }
+
+    async fn unmap(&self, port: u16) -> Result<(), String> {
+        match self {
+            Gateway::Igd { .. } => {
+                let port = port.to_string();
+                self.soap(
+                    "DeletePortMapping",
+                    &[
+                        ("NewRemoteHost", ""),
+                        ("NewExternalPort", port.as_str()),
+                        ("NewProtocol", "TCP"),
+                    ],
+                )
+                .await
+                .map(|_| ())
+            }
+            Gateway::NatPmp { address } => {
+                let mut request = mapping_request(port, 0);
+                // A lifetime of 0 with an external port of 0 deletes the mapping
+                request[6..8].copy_from_slice(&[0, 0]);
+                nat_pmp_request(*address, &request, 16).await.map(|_| ())
+            }
+        }
+    }
+
+    async fn soap(&self, action: &str, arguments: &[(&str, &str)]) -> Result<String, String> {
+        let Gateway::Igd {
+            control_url,
+            service_type,
+            ..
+        } = self
+        else {
+            return Err(String::from("Not a UPnP gateway"));
+        };
+        let arguments: String = arguments
+            .iter()
+            .map(|(name, value)| format!("<{0}>{1}</{0}>", name, value))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\"?>\r\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{0} xmlns:u=\"{1}\">{2}</u:{0}></s:Body></s:Envelope>",
+            action, service_type, arguments
+        );
+        let response = reqwest::Client::new()
+            .post(control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("SOAPAction", format!("\"{}#{}\"", service_type, action))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Unable to reach the gateway : {}", e))?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if status.is_success() {
+            Ok(text)
+        } else {
+            Err(format!(
+                "{} refused by the gateway : {} {}",
+                action,
+                xml_value(&text, "errorCode").unwrap_or(status.as_u16().to_string()),
+                xml_value(&text, "errorDescription").unwrap_or_default()
+            ))
+        }
+    }
+}
+
+/// Text of the first `<tag>`, namespace prefixes ignored
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<(?:\w+:)?{0}(?:\s[^>]*)?>(.*?)</(?:\w+:)?{0}>", tag);
+    Regex::new(&pattern)
+        .ok()?
+        .captures(xml)
+        .map(|captures| captures[1].trim().to_owned())
+}
+
+/// Location of the first Internet Gateway Device answering an SSDP search
+async fn ssdp_search() -> Result<String, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| e.to_string())?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n",
+        SSDP_ADDRESS
+    );
+    socket
+        .send_to(request.as_bytes(), SSDP_ADDRESS)
+        .await
+        .map_err(|e| format!("SSDP search failed : {}", e))?;
+    let location = Regex::new(r"(?im)^location:\s*(\S+)").unwrap();
+    let mut buffer = [0u8; 2048];
+    let search = async {
+        loop {
+            let Ok((length, _)) = socket.recv_from(&mut buffer).await else {
+                return None;
+            };
+            let response = String::from_utf8_lossy(&buffer[..length]);
+            if let Some(captures) = location.captures(&response) {
+                return Some(captures[1].to_owned());
+            }
+        }
+    };
+    tokio::time::timeout(SSDP_TIMEOUT, search)
+        .await
+        .ok()
+        .flatten()
+        .ok_or(String::from("No UPnP gateway answered on the network"))
+}
+
+/// Default IPv4 gateway from the routing table (Linux only)
+fn default_gateway() -> Option<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        // The table is in host (little endian) order
+        Some(Ipv4Addr::from(gateway.to_le_bytes()))
+    })
+}
+
+/// Address of this machine on the network of `host`
+fn local_ip_towards(host: &str, port: u16) -> Result<IpAddr, String> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    socket
+        .connect((host, port))
+        .and_then(|_| socket.local_addr())
+        .map(|address| address.ip())
+        .map_err(|e| format!("Unable to find the local address towards {} : {}", host, e))
+}
+
+/// Version 0, opcode 2 (TCP), internal port, suggested external port, lifetime
+fn mapping_request(port: u16, lifetime: u32) -> Vec<u8> {
+    let mut request = vec![0, 2, 0, 0];
+    request.extend(port.to_be_bytes());
+    request.extend(port.to_be_bytes());
+    request.extend(lifetime.to_be_bytes());
+    request
+}
+
+/// Sends a NAT-PMP request with the retries of RFC 6886, checks the result code
+async fn nat_pmp_request(gateway: Ipv4Addr, request: &[u8], length: usize) -> Result<Vec<u8>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| e.to_string())?;
+    let address = SocketAddr::from((gateway, NAT_PMP_PORT));
+    let mut timeout = Duration::from_millis(250);
+    let mut buffer = [0u8; 64];
+    for _ in 0..4 {
+        socket
+            .send_to(request, address)
+            .await
+            .map_err(|e| format!("Unable to reach the NAT-PMP gateway {} : {}", gateway, e))?;
+        if let Ok(Ok((received, _))) = tokio::time::timeout(timeout, socket.recv_from(&mut buffer)).await {
+            if received < length || buffer[1] != request[1] + 128 {
+                return Err(String::from("Invalid answer from the NAT-PMP gateway"));
+            }
+            return match u16::from_be_bytes([buffer[2], buffer[3]]) {
+                0 => Ok(buffer[..received].to_vec()),
+                code => Err(format!("The NAT-PMP gateway refused the request (result code {})", code)),
+            };
+        }
+        timeout *= 2;
+    }
+    Err(format!("No NAT-PMP answer from {}", gateway))
+}
+
+/// Private, shared (CGNAT) and loopback addresses are not reachable from internet
+fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || (ip.octets()[0] == 100 && (64..128).contains(&ip.octets()[1]))
+        }
+        IpAddr::V6(ip) => ip.is_loopback(),
+    }
+}
+
+/// Port mapping on the router of the network, UPnP IGD or NAT-PMP
+#[derive(Debug)]
+pub struct PortForwarding {
+    /// IGD description URL or NAT-PMP gateway, discovered when not given
+    pub gateway: Option<String>,
+}
+
+impl PortForwarding {
+    pub fn build(gateway: Option<String>) -> Self {
+        Self { gateway }
+    }
+}
+
+impl TunnelProvider for PortForwarding {
+    fn open(self: Arc<Self>, local_port: u16) -> Result<Tunnel, String> {
+        println!("🚀 Forwarding port {} on the router", local_port);
+        Ok(Tunnel::spawn("upnp", |mut stopped| async move {
+            let gateway = tokio::select! {
+                gateway = Gateway::discover(self.gateway.as_deref()) => gateway,
+                _ = &mut stopped => return,
+            };
+            let gateway = match gateway {
+                Ok(gateway) => gateway,
+                Err(e) => {
+                    println!("❌ {}", e);
+                    return;
+                }
+            };
+            let (external_port, mut lease) = match gateway.map(local_port).await {
+                Ok(mapping) => mapping,
+                Err(e) => {
+                    println!("❌ {}", e);
+                    return;
+                }
+            };
+            match gateway.external_ip().await {
+                Ok(ip) => {
+                    println!("🌐 Public address : {}", SocketAddr::new(ip, external_port));
+                    if is_private(&ip) {
+                        println!("⚠️ The router is itself behind a NAT (CGNAT), the server is not reachable from internet, use a tunnel instead");
+                    }
+                }
+                Err(e) => println!("⚠️ Port {} forwarded by {} but {}", external_port, gateway.name(), e),
+            }
+            loop {
+                // A permanent mapping is still refreshed in case the router rebooted
+                let renew_in = Duration::from_secs(if lease == 0 { LEASE_SECONDS } else { lease } as u64 / 2);
+                tokio::select! {
+                    _ = tokio::time::sleep(renew_in) => match gateway.map(local_port).await {
+                        Ok((_, granted)) => lease = granted,
+                        Err(e) => println!("⚠️ Unable to renew the port mapping : {}", e),
+                    },
+                    _ = &mut stopped => {
+                        match gateway.unmap(local_port).await {
+                            Ok(_) => println!("✅ Port {} mapping removed from the router", local_port),
+                            Err(e) => println!("⚠️ Unable to remove the port mapping : {}", e),
+                        }
+                        return;
+                    }
+                }
+            }
+        }))
+    }
+}